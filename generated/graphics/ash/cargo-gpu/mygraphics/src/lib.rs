@@ -1,2 +0,0 @@
-pub mod ash_renderer;
-pub mod util;
@@ -0,0 +1,108 @@
+use anyhow::bail;
+use cargo_gpu_install::install::Install;
+use cargo_gpu_install::spirv_builder::{Capability, ShaderPanicStrategy, SpirvMetadata};
+use std::path::PathBuf;
+
+/// The shader crates to compile, each a workspace member next to `mygraphics`. Every crate is passed to the main
+/// crate as the `SHADER_SPV_PATH_<NAME>` env var, its name in uppercase with `-` replaced by `_`. The first crate is
+/// additionally passed as `SHADER_SPV_PATH`. Add new crates to `util::SHADER_MODULES` to switch to them at runtime.
+const SHADER_CRATES: &[&str] = &["mygraphics-shaders"];
+
+/// The Vulkan version the shaders are compiled for, passed to the main crate as the `VULKAN_VERSION` env var to create
+/// the device with the same version. The prebuilt shaders are always compiled for 1.3.
+const VULKAN_VERSION: &str = "1.2";
+
+pub fn main() -> anyhow::Result<()> {
+    println!("cargo::rustc-env=VULKAN_VERSION={VULKAN_VERSION}");
+    for (i, shader_crate) in SHADER_CRATES.iter().enumerate() {
+        let spv_path = build_shader(shader_crate)?;
+        let env_name = shader_crate.to_uppercase().replace('-', "_");
+        println!(
+            "cargo::rustc-env=SHADER_SPV_PATH_{env_name}={}",
+            spv_path.display()
+        );
+        if i == 0 {
+            println!("cargo::rustc-env=SHADER_SPV_PATH={}", spv_path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the path to the SPIR-V module of `shader_crate`
+fn build_shader(shader_crate: &str) -> anyhow::Result<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    let crate_path = [manifest_dir, "..", shader_crate]
+        .iter()
+        .copied()
+        .collect::<PathBuf>();
+
+    let install = Install::from_shader_crate(crate_path.clone())
+        .within_build_script()
+        .run()?;
+    let mut builder =
+        install.to_spirv_builder(crate_path, format!("spirv-unknown-vulkan{VULKAN_VERSION}"));
+    builder.build_script.defaults = true;
+    builder.shader_panic_strategy = ShaderPanicStrategy::SilentExit;
+    builder.spirv_metadata = spirv_metadata()?;
+    builder.release = release()?;
+    if bindless() {
+        builder.shader_crate_features.features.push("bindless".to_string());
+        builder.capabilities.push(Capability::RuntimeDescriptorArray);
+    }
+    if sample_shading() {
+        builder.shader_crate_features.features.push("sample-shading".to_string());
+        builder.capabilities.push(Capability::SampleRateShading);
+    }
+    if gpu_vertices() {
+        builder.shader_crate_features.features.push("gpu-vertices".to_string());
+    }
+
+    let compile_result = builder.build()?;
+    Ok(compile_result.module.unwrap_single().to_path_buf())
+}
+
+/// Whether the `bindless` feature of this crate is enabled, which is forwarded to the shader crates to compile the
+/// entry points indexing into descriptor arrays
+fn bindless() -> bool {
+    std::env::var_os("CARGO_FEATURE_BINDLESS").is_some()
+}
+
+/// Whether the `sample-shading` feature of this crate is enabled, which is forwarded to the shader crates to compile
+/// the entry points running once per sample
+fn sample_shading() -> bool {
+    std::env::var_os("CARGO_FEATURE_SAMPLE_SHADING").is_some()
+}
+
+/// Whether the `gpu-vertices` feature of this crate is enabled, which is forwarded to the shader crates to compile the
+/// compute shader generating vertices
+fn gpu_vertices() -> bool {
+    std::env::var_os("CARGO_FEATURE_GPU_VERTICES").is_some()
+}
+
+/// The debug information included in the SPIR-V, set by the `SHADER_METADATA` env var:
+/// * `full` (default): names of everything and source locations, for debuggers like `RenderDoc`
+/// * `name-variables`: only names of interface variables, enough for shader reflection
+/// * `none`: strip all debug information, for the smallest binaries
+fn spirv_metadata() -> anyhow::Result<SpirvMetadata> {
+    println!("cargo::rerun-if-env-changed=SHADER_METADATA");
+    Ok(match std::env::var("SHADER_METADATA").as_deref() {
+        Err(_) | Ok("full") => SpirvMetadata::Full,
+        Ok("name-variables") => SpirvMetadata::NameVariables,
+        Ok("none") => SpirvMetadata::None,
+        Ok(other) => {
+            bail!("Unknown SHADER_METADATA `{other}`, expected `none`, `name-variables` or `full`")
+        }
+    })
+}
+
+/// Whether to optimize the shaders, set by the `SHADER_OPT_LEVEL` env var:
+/// * `release` (default): optimize with `spirv-opt`
+/// * `debug`: skip optimizations, which compiles faster and keeps the SPIR-V closer to the source
+fn release() -> anyhow::Result<bool> {
+    println!("cargo::rerun-if-env-changed=SHADER_OPT_LEVEL");
+    Ok(match std::env::var("SHADER_OPT_LEVEL").as_deref() {
+        Err(_) | Ok("release") => true,
+        Ok("debug") => false,
+        Ok(other) => bail!("Unknown SHADER_OPT_LEVEL `{other}`, expected `release` or `debug`"),
+    })
+}
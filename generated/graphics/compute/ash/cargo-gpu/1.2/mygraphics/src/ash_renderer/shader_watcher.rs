@@ -0,0 +1,81 @@
+use anyhow::Context;
+use ash::util::read_spv;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError, channel};
+use std::time::Duration;
+
+/// How often to try reading a shader that is still being written
+const READ_ATTEMPTS: u32 = 10;
+const READ_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Watches a compiled SPIR-V file and reloads it whenever it changes on disk
+pub struct ShaderWatcher {
+    path: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl ShaderWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        // compilers usually replace the file instead of writing to it, so watch the directory to not lose track of it
+        let dir = path
+            .parent()
+            .with_context(|| format!("shader path {} has no parent directory", path.display()))?;
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            path,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Returns the new shader code if the file has changed since the last call
+    pub fn poll(&mut self) -> Option<Vec<u32>> {
+        let mut changed = false;
+        loop {
+            match self.events.try_recv() {
+                Ok(Ok(event)) => {
+                    changed |= (event.kind.is_create() || event.kind.is_modify())
+                        && event.paths.iter().any(|p| p == &self.path);
+                }
+                Ok(Err(err)) => eprintln!("shader watcher error: {err}"),
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+            }
+        }
+        if !changed {
+            return None;
+        }
+
+        match read_shader(&self.path) {
+            Ok(code) => {
+                println!("reloaded shader {}", self.path.display());
+                Some(code)
+            }
+            Err(err) => {
+                eprintln!("failed to reload shader {}: {err:#}", self.path.display());
+                None
+            }
+        }
+    }
+}
+
+/// Read a SPIR-V file, retrying if the compiler hasn't finished writing it yet
+fn read_shader(path: &Path) -> anyhow::Result<Vec<u32>> {
+    let mut attempt = 1;
+    loop {
+        // a partially written file is usually missing, empty or not a multiple of 4 bytes long
+        match std::fs::read(path).and_then(|bytes| read_spv(&mut std::io::Cursor::new(bytes))) {
+            Ok(code) if !code.is_empty() => return Ok(code),
+            _ if attempt < READ_ATTEMPTS => {
+                attempt += 1;
+                std::thread::sleep(READ_RETRY_DELAY);
+            }
+            Ok(_) => anyhow::bail!("shader file is empty"),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
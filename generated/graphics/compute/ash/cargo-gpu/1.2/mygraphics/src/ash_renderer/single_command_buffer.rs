@@ -10,10 +10,17 @@ pub struct SingleCommandBuffer {
 }
 
 impl SingleCommandBuffer {
+    /// For submitting to [`MyDevice::main_queue`]
     pub fn new(device: Arc<MyDevice>) -> anyhow::Result<Self> {
+        let queue_family = device.main_queue_family;
+        Self::with_queue_family(device, queue_family)
+    }
+
+    /// For submitting to a queue of `queue_family`, like [`MyDevice::transfer_queue_family`]
+    pub fn with_queue_family(device: Arc<MyDevice>, queue_family: u32) -> anyhow::Result<Self> {
         unsafe {
             let pool = device.device.create_command_pool(
-                &vk::CommandPoolCreateInfo::default().queue_family_index(device.main_queue_family),
+                &vk::CommandPoolCreateInfo::default().queue_family_index(queue_family),
                 None,
             )?;
 
@@ -0,0 +1,975 @@
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use crate::ash_renderer::image::{ImageCreateInfo, MyImage};
+use crate::util::{FrameStats, OutputColorSpace, PresentMode, RESIZE_DEBOUNCE};
+use anyhow::{Context, anyhow};
+use ash::vk;
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The synchronization primitives of a single frame in flight, see [`MySwapchainManager`] for how they are used
+#[derive(Copy, Clone)]
+struct SwapchainSync {
+    acquire_semaphore: vk::Semaphore,
+    render_fence: vk::Fence,
+}
+
+impl SwapchainSync {
+    unsafe fn new(device: &MyDevice, frame_index: usize) -> Result<Self, RendererError> {
+        unsafe {
+            let signaled_fence =
+                vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
+            let sync = Self {
+                acquire_semaphore: device
+                    .create_semaphore(&vk::SemaphoreCreateInfo::default(), None)?,
+                render_fence: device.create_fence(&signaled_fence, None)?,
+            };
+            device.set_object_name(
+                sync.acquire_semaphore,
+                &format!("frame {frame_index} acquire semaphore"),
+            );
+            device.set_object_name(
+                sync.render_fence,
+                &format!("frame {frame_index} render fence"),
+            );
+            Ok(sync)
+        }
+    }
+
+    unsafe fn destroy(&self, device: &MyDevice) {
+        unsafe {
+            device.destroy_semaphore(self.acquire_semaphore, None);
+            device.destroy_fence(self.render_fence, None);
+        }
+    }
+}
+
+/// The 8-bit sRGB formats preferred by [`select_surface_format`], the only sRGB formats surfaces commonly support
+const SRGB_FORMATS: [vk::Format; 5] = [
+    vk::Format::R8G8B8_SRGB,
+    vk::Format::B8G8R8_SRGB,
+    vk::Format::R8G8B8A8_SRGB,
+    vk::Format::B8G8R8A8_SRGB,
+    vk::Format::A8B8G8R8_SRGB_PACK32,
+];
+
+/// Whether writes to an image of this format are converted from linear to sRGB. If not, the shader has to apply
+/// gamma correction manually.
+pub fn is_srgb(format: vk::Format) -> bool {
+    SRGB_FORMATS.contains(&format)
+}
+
+/// Selects the `requested` format if the surface supports it, otherwise an 8-bit sRGB format, otherwise the first
+/// format of the surface
+///
+/// Just taking the first format isn't enough, as many drivers list a UNORM format like `B8G8R8A8_UNORM` first. Its
+/// writes aren't sRGB encoded, which makes the output look too dark unless the shaders encode it themselves, see
+/// [`OutputColorSpace`].
+pub fn select_surface_format(
+    formats: &[vk::SurfaceFormatKHR],
+    requested: Option<vk::Format>,
+) -> Option<vk::SurfaceFormatKHR> {
+    let find = |f: &dyn Fn(vk::Format) -> bool| {
+        formats
+            .iter()
+            .find(|sfmt| f(sfmt.format) && sfmt.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR)
+            .or_else(|| formats.iter().find(|sfmt| f(sfmt.format)))
+            .copied()
+    };
+    requested
+        .and_then(|requested| find(&|format| format == requested))
+        .or_else(|| find(&|format| SRGB_FORMATS.contains(&format)))
+        .or_else(|| formats.first().copied())
+}
+
+/// Selects the [`vk::PresentModeKHR`] matching `requested` if it is `supported`, otherwise FIFO, which every surface
+/// has to support. Logs a warning naming both modes when falling back.
+pub fn select_present_mode(
+    supported: &[vk::PresentModeKHR],
+    requested: PresentMode,
+) -> vk::PresentModeKHR {
+    let mode = match requested {
+        PresentMode::Vsync => vk::PresentModeKHR::FIFO,
+        PresentMode::Immediate => vk::PresentModeKHR::IMMEDIATE,
+        PresentMode::Mailbox => vk::PresentModeKHR::MAILBOX,
+    };
+    if mode == vk::PresentModeKHR::FIFO || supported.contains(&mode) {
+        mode
+    } else {
+        let fallback = vk::PresentModeKHR::FIFO;
+        log::warn!(
+            "{requested:?} ({mode:?}) is not supported by the surface, falling back to {fallback:?}"
+        );
+        fallback
+    }
+}
+
+/// The format of the optional depth image allocated alongside the swapchain
+pub const DEPTH_FORMAT: vk::Format = vk::Format::D32_SFLOAT;
+
+/// Additional attachments allocated alongside the swapchain images, must match the config of the pipeline
+#[derive(Copy, Clone, Debug)]
+pub struct AttachmentConfig {
+    /// whether to allocate a depth image of [`DEPTH_FORMAT`]
+    pub depth: bool,
+    /// the MSAA sample count, [`vk::SampleCountFlags::TYPE_1`] disables multisampling
+    ///
+    /// With multisampling, we render into a transient multisampled color image and resolve it into the swapchain
+    /// image. The depth image is multisampled as well.
+    pub samples: vk::SampleCountFlags,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            depth: false,
+            samples: vk::SampleCountFlags::TYPE_1,
+        }
+    }
+}
+
+impl AttachmentConfig {
+    #[inline]
+    pub fn msaa(&self) -> bool {
+        self.samples != vk::SampleCountFlags::TYPE_1
+    }
+
+    /// Allocate the depth and msaa images this config requires, for rendering to a color image of `format`
+    pub fn create_images(
+        &self,
+        device: &Arc<MyDevice>,
+        format: vk::Format,
+        extent: vk::Extent2D,
+    ) -> Result<(Option<MyImage>, Option<MyImage>), RendererError> {
+        let depth_image = self
+            .depth
+            .then(|| {
+                MyImage::new(
+                    device,
+                    ImageCreateInfo {
+                        format: DEPTH_FORMAT,
+                        extent,
+                        usage: vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+                        aspect: vk::ImageAspectFlags::DEPTH,
+                        samples: self.samples,
+                        name: Some("depth".into()),
+                    },
+                )
+            })
+            .transpose()?;
+        let msaa_image = self
+            .msaa()
+            .then(|| {
+                MyImage::new(
+                    device,
+                    ImageCreateInfo {
+                        format,
+                        extent,
+                        // only ever used within a single render pass, allowing tiled GPUs to keep it on-chip
+                        usage: vk::ImageUsageFlags::COLOR_ATTACHMENT
+                            | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                        aspect: vk::ImageAspectFlags::COLOR,
+                        samples: self.samples,
+                        name: Some("msaa color".into()),
+                    },
+                )
+            })
+            .transpose()?;
+        Ok((depth_image, msaa_image))
+    }
+}
+
+/// Takes care of all things swapchain related
+///
+/// Supports multiple frames in flight by cycling through a ring of [`SwapchainSync`], one per frame in flight. Before
+/// a frame slot is reused, we wait for its `render_fence`, so that all resources associated with that slot are no
+/// longer in use by the GPU.
+///
+/// The semaphores follow these lifetime rules, which are easy to get wrong:
+/// * The acquire semaphore belongs to the frame in flight. It is signaled by `acquire_next_image` and waited on by the
+///   submission of that frame. Once the frame's `render_fence` has signaled, that submission has finished waiting on
+///   it, so it may be reused for the next acquire of this frame slot.
+/// * The present semaphore belongs to the swapchain image. It is signaled by the submission and waited on by
+///   `queue_present`, but there is no fence telling us when the presentation engine is done waiting on it. The only
+///   guarantee we have is that an image is not acquired again before its previous presentation has finished waiting.
+///   Indexing present semaphores by frame in flight would reuse them while a present may still wait on them, which
+///   the validation layers report as a semaphore reuse error, as there may be more images than frames in flight.
+/// * Present semaphores are destroyed and recreated along with the swapchain, after waiting for the device to be idle.
+///   This also covers presents failing with `ERROR_OUT_OF_DATE_KHR`, which may leave the semaphore signaled.
+///
+/// With [`Self::set_frame_pacing`], every present is tagged with an increasing present id and before drawing the next
+/// frame, we wait until the previous one has been presented. At most one frame is queued for presentation that way,
+/// which reduces input latency compared to only being limited by the swapchain image count.
+///
+/// On some platforms, like Android and Wayland, the surface of a window may be destroyed while the app is suspended.
+/// [`Self::suspend`] destroys everything bound to the surface: the surface itself, the swapchain with its images,
+/// present semaphores and attachments. [`Self::resume`] creates a new surface for the window, the swapchain is then
+/// recreated on the next frame. The device, the frame in flight sync, the selected surface format and present mode
+/// and the frame stats survive, so the renderer and its pipelines can keep using the same format.
+pub struct MySwapchainManager {
+    pub device: Arc<MyDevice>,
+    pub window: Arc<winit::window::Window>,
+    /// selected when creating the manager and kept across [`Self::resume`], as the pipelines are created for it
+    pub surface_format: vk::SurfaceFormatKHR,
+    /// additional attachments matching the swapchain's extent
+    pub attachments: AttachmentConfig,
+    /// the present mode requested by [`Self::set_present_mode`], selected again for every new surface
+    requested_present_mode: PresentMode,
+    /// the image count passed to [`Self::new`], selected again for every new surface
+    requested_image_count: Option<u32>,
+
+    // state below
+    /// `None` while suspended
+    surface: Option<SurfaceState>,
+    /// bound to `surface`, always `None` while suspended
+    active: Option<ActiveSwapchain>,
+    should_recreate: bool,
+    /// see [`DrawFrame::swapchain_recreated`]
+    recreated: bool,
+    /// when the window was last resized, recreation is postponed until it's [`RESIZE_DEBOUNCE`] ago
+    last_resize_instant: Option<Instant>,
+    sync: Vec<SwapchainSync>,
+    frame_index: usize,
+    frame_stats: FrameStats,
+    /// see [`Self::set_frame_pacing`]
+    frame_pacing: bool,
+    /// the id of the last present, ids start at 1 and increase with every present
+    present_id: u64,
+}
+
+/// The surface of the window and its properties, destroyed by [`MySwapchainManager::suspend`]
+struct SurfaceState {
+    surface: vk::SurfaceKHR,
+    capabilities: vk::SurfaceCapabilitiesKHR,
+    /// the present modes supported by the surface
+    present_modes: Vec<vk::PresentModeKHR>,
+    /// the selected present mode, see [`MySwapchainManager::set_present_mode`]
+    present_mode: vk::PresentModeKHR,
+    /// the minimum number of swapchain images, see [`Self::select_image_count`]
+    image_count: u32,
+    pre_transform: vk::SurfaceTransformFlagsKHR,
+}
+
+impl SurfaceState {
+    unsafe fn new(
+        device: &MyDevice,
+        window: &winit::window::Window,
+        present_mode: PresentMode,
+        image_count: Option<u32>,
+    ) -> Result<Self, RendererError> {
+        unsafe {
+            let surface_ext = &device.surface_ext;
+            let surface = ash_window::create_surface(
+                &device.entry,
+                &device.instance,
+                window.display_handle().unwrap().into(),
+                window.window_handle().unwrap().into(),
+                None,
+            )?;
+            let properties = (|| {
+                let capabilities = surface_ext
+                    .get_physical_device_surface_capabilities(device.physical_device, surface)?;
+                let present_modes = surface_ext
+                    .get_physical_device_surface_present_modes(device.physical_device, surface)?;
+                Ok::<_, vk::Result>((capabilities, present_modes))
+            })();
+            let (capabilities, present_modes) = match properties {
+                Ok(properties) => properties,
+                Err(e) => {
+                    surface_ext.destroy_surface(surface, None);
+                    return Err(e.into());
+                }
+            };
+            let mut this = Self {
+                surface,
+                capabilities,
+                pre_transform: capabilities.current_transform,
+                present_modes,
+                // FIFO is guaranteed to be available
+                present_mode: vk::PresentModeKHR::FIFO,
+                image_count: 0,
+            };
+            this.select_present_mode(present_mode);
+            this.select_image_count(image_count);
+            Ok(this)
+        }
+    }
+
+    /// See [`select_present_mode`]
+    fn select_present_mode(&mut self, present_mode: PresentMode) {
+        self.present_mode = select_present_mode(&self.present_modes, present_mode);
+        log::info!("Selected present mode {:?}", self.present_mode);
+    }
+
+    /// Select the `requested` image count, or one more than the minimum of the surface by default, so there is always
+    /// an image to draw to while the presentation engine holds the others. Clamped to the range the surface supports.
+    ///
+    /// More images allow the CPU and GPU to run further ahead of the display, which increases throughput but also
+    /// latency. The driver may create more images than selected, see [`MySwapchainManager::image_count`].
+    fn select_image_count(&mut self, requested: Option<u32>) {
+        let capabilities = &self.capabilities;
+        let mut image_count = requested.unwrap_or(capabilities.min_image_count + 1);
+        if capabilities.max_image_count != 0 {
+            // 0 means there is no maximum
+            image_count = image_count.min(capabilities.max_image_count);
+        }
+        image_count = image_count.max(capabilities.min_image_count);
+        if let Some(requested) = requested
+            && requested != image_count
+        {
+            log::warn!(
+                "Surface doesn't support {requested} swapchain images, using {image_count} instead"
+            );
+        }
+        self.image_count = image_count;
+    }
+}
+
+struct ActiveSwapchain {
+    extent: vk::Extent2D,
+    swapchain: vk::SwapchainKHR,
+    images: Vec<(vk::Image, vk::ImageView)>,
+    /// one per image in `images`, see [`MySwapchainManager`]
+    present_semaphores: Vec<vk::Semaphore>,
+    /// the present id of the last frame presented with this swapchain, if it was tagged with one
+    last_present_id: Option<u64>,
+    depth_image: Option<MyImage>,
+    msaa_image: Option<MyImage>,
+}
+
+impl MySwapchainManager {
+    pub fn new(
+        device: Arc<MyDevice>,
+        window: Arc<winit::window::Window>,
+        frames_in_flight: usize,
+        attachments: AttachmentConfig,
+        present_mode: PresentMode,
+        surface_format: Option<vk::Format>,
+        image_count: Option<u32>,
+    ) -> Result<Self, RendererError> {
+        assert!(
+            frames_in_flight > 0,
+            "must have at least one frame in flight"
+        );
+        if device.headless {
+            return Err(anyhow!("Cannot create a swapchain on a headless device").into());
+        }
+        unsafe {
+            let surface = SurfaceState::new(&device, &window, present_mode, image_count)?;
+            // from here on, dropping `this` cleans up after errors
+            let mut this = Self {
+                device,
+                frame_stats: FrameStats::new(window.title()),
+                window,
+                surface_format: vk::SurfaceFormatKHR::default(),
+                attachments,
+                requested_present_mode: present_mode,
+                requested_image_count: image_count,
+
+                surface: Some(surface),
+                active: None,
+                should_recreate: true,
+                recreated: false,
+                last_resize_instant: None,
+                frame_index: 0,
+                frame_pacing: false,
+                present_id: 0,
+                sync: Vec::new(),
+            };
+            this.surface_format = select_surface_format(&this.surface_formats()?, surface_format)
+                .context("Surface does not support any formats")?;
+            log::info!(
+                "Selected surface format {:?}, sRGB: {}",
+                this.surface_format,
+                is_srgb(this.surface_format.format)
+            );
+            for frame_index in 0..frames_in_flight {
+                let sync = SwapchainSync::new(&this.device, frame_index)?;
+                this.sync.push(sync);
+            }
+            Ok(this)
+        }
+    }
+
+    /// The formats supported by the current surface, empty while suspended
+    unsafe fn surface_formats(&self) -> Result<Vec<vk::SurfaceFormatKHR>, RendererError> {
+        let Some(surface) = &self.surface else {
+            return Ok(Vec::new());
+        };
+        unsafe {
+            Ok(self
+                .device
+                .surface_ext
+                .get_physical_device_surface_formats(
+                    self.device.physical_device,
+                    surface.surface,
+                )?)
+        }
+    }
+
+    /// Call on [`winit::application::ApplicationHandler::suspended`], the surface of the window may be destroyed
+    /// afterwards. Destroys the surface and everything bound to it, see [`MySwapchainManager`]. Until
+    /// [`Self::resume`] is called, [`Self::render`] doesn't draw anything.
+    pub fn suspend(&mut self) -> Result<(), RendererError> {
+        unsafe {
+            let Some(surface) = self.surface.take() else {
+                return Ok(());
+            };
+            self.device.device_wait_idle()?;
+            if let Some(mut active) = self.active.take() {
+                active.destroy_image_views(&self.device);
+                active.destroy_swapchain(&self.device);
+            }
+            self.device
+                .surface_ext
+                .destroy_surface(surface.surface, None);
+            log::info!("Suspended, destroyed the surface");
+            Ok(())
+        }
+    }
+
+    /// Call on [`winit::application::ApplicationHandler::resumed`] after [`Self::suspend`], to create a new surface
+    /// for the window. The swapchain is recreated on the next frame.
+    ///
+    /// Fails if the new surface doesn't support the [`Self::surface_format`] the pipelines were created for.
+    pub fn resume(&mut self) -> Result<(), RendererError> {
+        if self.surface.is_some() {
+            return Ok(());
+        }
+        unsafe {
+            self.surface = Some(SurfaceState::new(
+                &self.device,
+                &self.window,
+                self.requested_present_mode,
+                self.requested_image_count,
+            )?);
+            if !self.surface_formats()?.contains(&self.surface_format) {
+                let surface = self.surface.take().unwrap();
+                self.device
+                    .surface_ext
+                    .destroy_surface(surface.surface, None);
+                return Err(anyhow!(
+                    "Resumed surface doesn't support the surface format {:?} anymore",
+                    self.surface_format
+                )
+                .into());
+            }
+        }
+        log::info!("Resumed, created a new surface");
+        self.should_recreate = true;
+        Ok(())
+    }
+
+    /// Whether [`Self::suspend`] was called without [`Self::resume`]
+    #[inline]
+    pub fn is_suspended(&self) -> bool {
+        self.surface.is_none()
+    }
+
+    /// Select the [`vk::PresentModeKHR`] matching `present_mode`, or FIFO if the surface doesn't support it, and
+    /// recreate the swapchain with it on the next frame
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        self.requested_present_mode = present_mode;
+        if let Some(surface) = &mut self.surface {
+            surface.select_present_mode(present_mode);
+        }
+        self.should_recreate = true;
+    }
+
+    /// Wait for the previous frame to be presented before drawing the next one, capping the queued frames to one.
+    /// Requires [`MyDevice::present_wait`], otherwise frames are only limited by the swapchain image count.
+    pub fn set_frame_pacing(&mut self, frame_pacing: bool) {
+        if frame_pacing && !self.device.present_wait {
+            log::warn!(
+                "Frame pacing requires VK_KHR_present_wait, which the device doesn't support"
+            );
+        }
+        self.frame_pacing = frame_pacing;
+        log::info!("Frame pacing {}", if frame_pacing { "on" } else { "off" });
+    }
+
+    #[inline]
+    pub fn frame_pacing(&self) -> bool {
+        self.frame_pacing
+    }
+
+    #[inline]
+    pub fn should_recreate(&mut self) {
+        self.should_recreate = true;
+    }
+
+    /// Call on every [`winit::event::WindowEvent::Resized`] to recreate the swapchain once the size is stable
+    #[inline]
+    pub fn resized(&mut self) {
+        self.last_resize_instant = Some(Instant::now());
+        self.should_recreate();
+    }
+
+    /// Whether the window was resized within the last [`RESIZE_DEBOUNCE`]
+    fn is_resizing(&self) -> bool {
+        self.last_resize_instant
+            .is_some_and(|instant| instant.elapsed() < RESIZE_DEBOUNCE)
+    }
+
+    /// Whether the surface format is sRGB, see [`is_srgb`]
+    #[inline]
+    pub fn is_srgb(&self) -> bool {
+        is_srgb(self.surface_format.format)
+    }
+
+    /// How the shaders have to encode their output for the surface
+    #[inline]
+    pub fn color_space(&self) -> OutputColorSpace {
+        OutputColorSpace::new(
+            self.is_srgb(),
+            self.surface_format.color_space == vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+        )
+    }
+
+    /// The stats reported in the window title, to add the GPU time measured by the renderer
+    #[inline]
+    pub fn frame_stats(&mut self) -> &mut FrameStats {
+        &mut self.frame_stats
+    }
+
+    /// The number of images of the current swapchain, which may be more than requested in [`Self::new`]. `None`
+    /// before the first frame and while suspended.
+    #[inline]
+    pub fn image_count(&self) -> Option<usize> {
+        self.active.as_ref().map(|active| active.images.len())
+    }
+
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.sync.len()
+    }
+
+    /// Whether the window has no area, e.g. while it's minimized. A swapchain can't have a zero extent, so there is
+    /// nothing to render to.
+    fn is_zero_sized(&self) -> bool {
+        let window_size = self.window.inner_size();
+        window_size.width == 0 || window_size.height == 0
+    }
+
+    /// The extent a swapchain must have to match the surface right now, or `None` if the surface has no area, e.g.
+    /// while the window is minimized, or while suspended.
+    unsafe fn surface_extent(&self) -> Result<Option<vk::Extent2D>, RendererError> {
+        unsafe {
+            let Some(surface) = &self.surface else {
+                return Ok(None);
+            };
+            if self.is_zero_sized() {
+                return Ok(None);
+            }
+            let window_size = self.window.inner_size();
+            let capabilities = self
+                .device
+                .surface_ext
+                .get_physical_device_surface_capabilities(
+                    self.device.physical_device,
+                    surface.surface,
+                )?;
+            let extent = if capabilities.current_extent.width != u32::MAX {
+                // the surface dictates the extent
+                capabilities.current_extent
+            } else {
+                // the swapchain dictates the size of the surface, e.g. on Wayland
+                let min = capabilities.min_image_extent;
+                let max = capabilities.max_image_extent;
+                vk::Extent2D {
+                    width: u32::clamp(window_size.width, min.width, max.width),
+                    height: u32::clamp(window_size.height, min.height, max.height),
+                }
+            };
+            Ok((extent.width != 0 && extent.height != 0).then_some(extent))
+        }
+    }
+
+    /// After this function is called, `Self.active` is initialized with a swapchain of `extent`
+    ///
+    /// # Safety
+    /// The old swapchain and its attachments must no longer be in use by the GPU. Must not be suspended.
+    unsafe fn recreate_swapchain(&mut self, extent: vk::Extent2D) -> Result<(), RendererError> {
+        unsafe {
+            let device = &self.device;
+            let surface = self
+                .surface
+                .as_ref()
+                .context("Cannot create a swapchain while suspended")?;
+            let swapchain_ext = &device.swapchain_ext;
+            let format = self.surface_format.format;
+
+            let mut old = self.active.take();
+            if let Some(old) = old.as_mut() {
+                old.destroy_image_views(device);
+            }
+
+            let swapchain = swapchain_ext.create_swapchain(
+                &vk::SwapchainCreateInfoKHR::default()
+                    .surface(surface.surface)
+                    .min_image_count(surface.image_count)
+                    .image_color_space(self.surface_format.color_space)
+                    .image_format(format)
+                    .image_extent(extent)
+                    .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
+                    .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .pre_transform(surface.pre_transform)
+                    .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
+                    .present_mode(surface.present_mode)
+                    .clipped(true)
+                    .image_array_layers(1)
+                    .old_swapchain(
+                        old.as_ref()
+                            .map_or(vk::SwapchainKHR::null(), |old| old.swapchain),
+                    ),
+                None,
+            )?;
+
+            if let Some(old) = old.as_ref() {
+                old.destroy_swapchain(device);
+            }
+
+            let images = device.swapchain_ext.get_swapchain_images(swapchain)?;
+            log::debug!(
+                "Created swapchain of {extent:?} with {} images, requested {}",
+                images.len(),
+                surface.image_count
+            );
+            let images = images
+                .into_iter()
+                .map(|image| {
+                    let image_view = device.create_image_view(
+                        &vk::ImageViewCreateInfo::default()
+                            .image(image)
+                            .view_type(vk::ImageViewType::TYPE_2D)
+                            .format(format)
+                            .components(vk::ComponentMapping::default()) // identity
+                            .subresource_range(vk::ImageSubresourceRange {
+                                aspect_mask: vk::ImageAspectFlags::COLOR,
+                                base_mip_level: 0,
+                                level_count: 1,
+                                base_array_layer: 0,
+                                layer_count: 1,
+                            }),
+                        None,
+                    )?;
+                    Ok::<_, RendererError>((image, image_view))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let present_semaphores = (0..images.len())
+                .map(|index| {
+                    let semaphore =
+                        device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None)?;
+                    device.set_object_name(
+                        semaphore,
+                        &format!("swapchain image {index} present semaphore"),
+                    );
+                    Ok::<_, RendererError>(semaphore)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let (depth_image, msaa_image) =
+                self.attachments.create_images(device, format, extent)?;
+            for image in [&depth_image, &msaa_image].into_iter().flatten() {
+                assert_eq!(image.extent, extent, "attachment extent mismatch");
+            }
+
+            self.active = Some(ActiveSwapchain {
+                swapchain,
+                images,
+                present_semaphores,
+                last_present_id: None,
+                extent,
+                depth_image,
+                msaa_image,
+            });
+            self.recreated = true;
+            Ok(())
+        }
+    }
+}
+
+impl ActiveSwapchain {
+    /// We must destroy the image views we own, but not the images, those are owned by the swapchain. The depth and
+    /// msaa images are entirely owned by us, so they're destroyed as well, along with the present semaphores.
+    unsafe fn destroy_image_views(&mut self, device: &Arc<MyDevice>) {
+        unsafe {
+            for (_, image_view) in &self.images {
+                device.destroy_image_view(*image_view, None);
+            }
+            for semaphore in self.present_semaphores.drain(..) {
+                device.destroy_semaphore(semaphore, None);
+            }
+            for image in [&mut self.depth_image, &mut self.msaa_image]
+                .into_iter()
+                .flatten()
+            {
+                image.destroy(device);
+            }
+        }
+    }
+
+    /// Destroying the swapchain destroys* the images, so image views must be destroyed beforehand.
+    unsafe fn destroy_swapchain(&self, device: &MyDevice) {
+        unsafe { device.swapchain_ext.destroy_swapchain(self.swapchain, None) }
+    }
+}
+
+impl Drop for MySwapchainManager {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.device_wait_idle().ok();
+            for sync in &self.sync {
+                sync.destroy(&self.device);
+            }
+            if let Some(active) = self.active.as_mut() {
+                active.destroy_image_views(&self.device);
+                active.destroy_swapchain(&self.device);
+            }
+            if let Some(surface) = &self.surface {
+                self.device
+                    .surface_ext
+                    .destroy_surface(surface.surface, None);
+            }
+        }
+    }
+}
+
+/// Metadata on drawing a single frame on some image
+pub struct DrawFrame {
+    /// the index of the frame in flight, in `0..frames_in_flight`
+    ///
+    /// Resources associated with this frame index are guaranteed to no longer be in use by the GPU.
+    pub frame_index: usize,
+    /// the size of the image
+    pub extent: vk::Extent2D,
+    /// the [`vk::Image`] to draw to
+    pub image: vk::Image,
+    /// the [`vk::Image`] to draw to, created from `image`
+    pub image_view: vk::ImageView,
+    /// the depth image and its view, if [`AttachmentConfig::depth`] is set
+    pub depth_image: Option<(vk::Image, vk::ImageView)>,
+    /// the multisampled color image and its view, to be resolved into `image`, if [`AttachmentConfig::msaa`]
+    pub msaa_image: Option<(vk::Image, vk::ImageView)>,
+    /// the index of `image` in the swapchain, in `0..image_count`
+    pub image_index: u32,
+    /// the semaphore of this frame in flight signaled by `acquire_next_image`, which must be waited for before draw
+    /// commands write to `image`
+    pub acquire_semaphore: vk::Semaphore,
+    /// the semaphore of this swapchain image waited on by `queue_present`, which must be signaled when drawing to
+    /// the image has finished
+    pub present_semaphore: vk::Semaphore,
+    /// the fence of this frame in flight, which must be signaled when drawing to the image has finished
+    pub draw_finished_fence: vk::Fence,
+    /// whether the swapchain was recreated since the previous frame, destroying the image views of all previous
+    /// frames. Anything created from them, like framebuffers, must be recreated.
+    pub swapchain_recreated: bool,
+}
+
+impl MySwapchainManager {
+    /// With [`Self::set_frame_pacing`], wait until the last frame has been presented
+    unsafe fn wait_for_previous_present(&mut self) -> Result<(), RendererError> {
+        /// don't block forever if the presentation engine stops presenting, e.g. while the window is hidden
+        const PRESENT_TIMEOUT_NS: u64 = 100_000_000;
+        unsafe {
+            let Some(active) = &self.active else {
+                return Ok(());
+            };
+            let Some(present_id) = active.last_present_id.filter(|_| self.frame_pacing) else {
+                return Ok(());
+            };
+            match self.device.present_wait_ext.wait_for_present(
+                active.swapchain,
+                present_id,
+                PRESENT_TIMEOUT_NS,
+            ) {
+                Ok(()) | Err(vk::Result::TIMEOUT) => Ok(()),
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                    self.should_recreate = true;
+                    Ok(())
+                }
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+
+    /// Acquire the next image, recreating the swapchain if necessary, call `f` to draw to it and present it. Returns
+    /// [`RendererError::SwapchainOutOfDate`] if no image could be acquired, the next call simply tries again.
+    ///
+    /// Does nothing while suspended or while the window has no area, e.g. while it's minimized, without waiting for
+    /// a frame slot or acquiring an image. Rendering resumes once the window has an area again.
+    pub fn render(
+        &mut self,
+        f: impl FnOnce(DrawFrame) -> Result<(), RendererError>,
+    ) -> Result<(), RendererError> {
+        if self.is_suspended() || self.is_zero_sized() {
+            return Ok(());
+        }
+        profile_span!("frame");
+        unsafe {
+            let frame_index = self.frame_index;
+            self.frame_index = (self.frame_index + 1) % self.sync.len();
+            let sync = self.sync[frame_index];
+            {
+                profile_span!("wait");
+                // only wait for the frame that previously used this slot, not the entire device
+                self.device
+                    .wait_for_fences(&[sync.render_fence], true, !0)?;
+                self.wait_for_previous_present()?;
+            }
+
+            const RECREATE_ATTEMPTS: u32 = 10;
+            let mut out_of_date = false;
+            for _ in 0..RECREATE_ATTEMPTS {
+                // Compare against the real surface size instead of only relying on `Resized` events or
+                // `ERROR_OUT_OF_DATE_KHR`, which some platforms never report. Recreating and acquiring within the same
+                // call avoids presenting a frame of the old size, which shows up as a black flash while resizing.
+                //
+                // While the window is being resized, recreating every frame is slow, so we keep presenting with the
+                // old swapchain until the size is stable. Only if the surface rejects the old swapchain with
+                // `ERROR_OUT_OF_DATE_KHR` do we have to recreate immediately.
+                let Some(extent) = self.surface_extent()? else {
+                    // nothing to render to, the fence of this frame slot stays signaled
+                    return Ok(());
+                };
+                if self
+                    .active
+                    .as_ref()
+                    .is_none_or(|active| active.extent != extent)
+                {
+                    self.should_recreate = true;
+                }
+
+                let debounce = self.active.is_some() && !out_of_date && self.is_resizing();
+                if self.should_recreate && !debounce {
+                    self.should_recreate = false;
+
+                    // *In theory*, recreating the swapchain allows you to present any acquired images from the old
+                    // swapchain. Which (iirc) requires you to wait for all images to be presented before destroying
+                    // the old swapchain. But we just use the [`ash::Device::device_wait_idle`] "hack" to wait for all
+                    // previous images to finish before immediately destroying the swapchain.
+                    self.device.device_wait_idle()?;
+                    self.recreate_swapchain(extent)?;
+                }
+
+                let active = self.active.as_ref().unwrap();
+                let swapchain_ext = &self.device.swapchain_ext;
+                let acquired = {
+                    profile_span!("acquire");
+                    swapchain_ext.acquire_next_image(
+                        active.swapchain,
+                        !0,
+                        sync.acquire_semaphore,
+                        vk::Fence::null(),
+                    )
+                };
+                match acquired {
+                    Ok((id, suboptimal)) => {
+                        if suboptimal {
+                            self.should_recreate = true;
+                        }
+                        assert!(
+                            debounce || active.extent == extent,
+                            "rendering into a swapchain of stale extent"
+                        );
+                        // only reset the fence once we're certain to submit work signaling it
+                        self.device.reset_fences(&[sync.render_fence])?;
+                        let (image, image_view) = active.images[id as usize];
+                        let present_semaphore = active.present_semaphores[id as usize];
+                        f(DrawFrame {
+                            frame_index,
+                            image_index: id,
+                            extent: active.extent,
+                            image,
+                            image_view,
+                            depth_image: active
+                                .depth_image
+                                .as_ref()
+                                .map(|depth| (depth.image, depth.image_view)),
+                            msaa_image: active
+                                .msaa_image
+                                .as_ref()
+                                .map(|msaa| (msaa.image, msaa.image_view)),
+                            acquire_semaphore: sync.acquire_semaphore,
+                            present_semaphore,
+                            draw_finished_fence: sync.render_fence,
+                            swapchain_recreated: std::mem::take(&mut self.recreated),
+                        })?;
+
+                        let mut present_info = vk::PresentInfoKHR::default()
+                            .swapchains(std::slice::from_ref(&active.swapchain))
+                            .image_indices(std::slice::from_ref(&id))
+                            .wait_semaphores(std::slice::from_ref(&present_semaphore));
+                        // tag every present while supported, so frame pacing can be turned on at any time
+                        self.present_id += 1;
+                        let present_ids = [self.present_id];
+                        let mut present_id = vk::PresentIdKHR::default().present_ids(&present_ids);
+                        if self.device.present_wait {
+                            present_info = present_info.push_next(&mut present_id);
+                        }
+                        let present_result = {
+                            profile_span!("present");
+                            swapchain_ext.queue_present(self.device.main_queue, &present_info)
+                        };
+                        if self.device.present_wait {
+                            self.active.as_mut().unwrap().last_present_id = Some(self.present_id);
+                        }
+                        match present_result {
+                            Ok(false) => (),
+                            // the frame may not have been presented, but the next one will be after recreation
+                            Ok(true) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                                self.should_recreate = true;
+                            }
+                            Err(e) => return Err(e.into()),
+                        }
+                        self.frame_stats.tick(&self.window);
+                        return Ok(());
+                    }
+                    Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                        // retry
+                        self.should_recreate = true;
+                        out_of_date = true;
+                    }
+                    Err(e) => {
+                        return Err(e.into());
+                    }
+                }
+            }
+            // the fence of this frame slot stays signaled, so the next frame can simply try again
+            log::warn!("Failed to acquire a swapchain image after {RECREATE_ATTEMPTS} attempts");
+            Err(RendererError::SwapchainOutOfDate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn select_present_mode_fallback() {
+        let fifo_only = [vk::PresentModeKHR::FIFO];
+        let all = [
+            vk::PresentModeKHR::FIFO,
+            vk::PresentModeKHR::IMMEDIATE,
+            vk::PresentModeKHR::MAILBOX,
+        ];
+        for (requested, expected) in [
+            (PresentMode::Vsync, vk::PresentModeKHR::FIFO),
+            (PresentMode::Immediate, vk::PresentModeKHR::IMMEDIATE),
+            (PresentMode::Mailbox, vk::PresentModeKHR::MAILBOX),
+        ] {
+            assert_eq!(select_present_mode(&all, requested), expected);
+            assert_eq!(
+                select_present_mode(&fifo_only, requested),
+                vk::PresentModeKHR::FIFO
+            );
+        }
+        // FIFO is guaranteed, even if a broken driver doesn't list it
+        assert_eq!(
+            select_present_mode(&[], PresentMode::Vsync),
+            vk::PresentModeKHR::FIFO
+        );
+    }
+}
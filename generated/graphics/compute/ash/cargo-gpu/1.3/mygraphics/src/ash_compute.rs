@@ -0,0 +1,132 @@
+use crate::ash_renderer::buffer::{BufferCreateInfo, MyBuffer};
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::get_shaders;
+use crate::ash_renderer::global_descriptor_set::{GlobalDescriptorSet, GlobalDescriptorSetLayout};
+use crate::ash_renderer::single_command_buffer::SingleCommandBuffer;
+use crate::util::{DevicePreference, enable_debug_layer, print_saxpy, saxpy_input};
+use ash::vk;
+use gpu_allocator::MemoryLocation;
+use mygraphics_shaders::{MAIN_CS_THREADS, SaxpyElement};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+pub fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let device = MyDevice::new_headless(enable_debug_layer(), &DevicePreference::from_env())?;
+    let input = saxpy_input();
+
+    let mut data = MyBuffer::new(
+        &device,
+        BufferCreateInfo {
+            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+            location: MemoryLocation::GpuToCpu,
+            name: Some(Cow::from("saxpy data")),
+        },
+        size_of_val(input.as_slice()) as u64,
+    )?;
+    let result = (|| {
+        data.write_slice(&input)?;
+        let output = unsafe { dispatch(&device, &data, input.len() as u32)? };
+        print_saxpy(&input, &output)
+    })();
+
+    unsafe {
+        device.device_wait_idle().ok();
+        data.destroy(&device);
+    }
+    result
+}
+
+/// Runs `main_cs` over the first `len` elements of `data` and waits for it to finish
+///
+/// # Safety
+/// `data` must be a host visible storage buffer holding at least `len` [`SaxpyElement`]s
+unsafe fn dispatch(
+    device: &Arc<MyDevice>,
+    data: &MyBuffer,
+    len: u32,
+) -> anyhow::Result<Vec<SaxpyElement>> {
+    unsafe {
+        let layout =
+            GlobalDescriptorSetLayout::new(device.clone(), vk::DescriptorType::STORAGE_BUFFER)?;
+        let mut descriptor_set = GlobalDescriptorSet::new(&layout, Some(data.buffer))?;
+        let shader_module = device.create_shader_module(
+            &vk::ShaderModuleCreateInfo::default().code(&get_shaders()?),
+            None,
+        )?;
+        let pipeline_layout = device.create_pipeline_layout(
+            &vk::PipelineLayoutCreateInfo::default().set_layouts(&[layout.layout]),
+            None,
+        )?;
+        let command = SingleCommandBuffer::new(device.clone())?;
+        let pipeline = device
+            .create_compute_pipelines(
+                device.pipeline_cache,
+                &[vk::ComputePipelineCreateInfo::default()
+                    .stage(
+                        vk::PipelineShaderStageCreateInfo::default()
+                            .stage(vk::ShaderStageFlags::COMPUTE)
+                            .module(shader_module)
+                            .name(c"main_cs"),
+                    )
+                    .layout(pipeline_layout)],
+                None,
+            )
+            .map_err(|(_, e)| e)?[0];
+        device.set_object_name(pipeline, "saxpy pipeline");
+        device.set_object_name(pipeline_layout, "saxpy pipeline layout");
+        device.set_object_name(command.cmd, "saxpy command buffer");
+
+        let result = (|| {
+            let cmd = command.cmd;
+            device.begin_command_buffer(
+                cmd,
+                &vk::CommandBufferBeginInfo::default()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )?;
+            device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, pipeline);
+            device.cmd_bind_descriptor_sets(
+                cmd,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline_layout,
+                0,
+                &[descriptor_set.set],
+                &[],
+            );
+            device.cmd_dispatch(cmd, len.div_ceil(MAIN_CS_THREADS), 1, 1);
+            device.cmd_pipeline_barrier2(
+                cmd,
+                &vk::DependencyInfo::default().buffer_memory_barriers(&[
+                    vk::BufferMemoryBarrier2::default()
+                        .buffer(data.buffer)
+                        .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                        .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                        .dst_access_mask(vk::AccessFlags2::HOST_READ)
+                        .dst_stage_mask(vk::PipelineStageFlags2::HOST)
+                        .size(vk::WHOLE_SIZE),
+                ]),
+            );
+            device.end_command_buffer(cmd)?;
+
+            device.queue_submit2(
+                device.main_queue,
+                &[vk::SubmitInfo2::default().command_buffer_infos(&[
+                    vk::CommandBufferSubmitInfo::default().command_buffer(cmd),
+                ])],
+                vk::Fence::null(),
+            )?;
+            device.queue_wait_idle(device.main_queue)?;
+
+            let bytes = &data.read_bytes()?[..len as usize * size_of::<SaxpyElement>()];
+            Ok(bytemuck::cast_slice::<u8, SaxpyElement>(bytes).to_vec())
+        })();
+
+        device.device_wait_idle().ok();
+        drop(command);
+        device.destroy_pipeline(pipeline, None);
+        device.destroy_pipeline_layout(pipeline_layout, None);
+        device.destroy_shader_module(shader_module, None);
+        descriptor_set.destroy();
+        result
+    }
+}
@@ -0,0 +1,149 @@
+use crate::ash_renderer::buffer::{BufferCreateInfo, MyBuffer};
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::image::{ImageCreateInfo, MyImage, subresource_range};
+use crate::ash_renderer::single_command_buffer::SingleCommandBuffer;
+use ash::vk;
+use gpu_allocator::MemoryLocation;
+use image::RgbaImage;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// A sampled texture in device local memory, together with the sampler used to read it
+pub struct MyTexture {
+    pub image: MyImage,
+    pub sampler: vk::Sampler,
+}
+
+impl MyTexture {
+    /// Uploads `rgba` through a staging buffer, blocking until the upload has finished
+    pub fn new(device: &Arc<MyDevice>, rgba: &RgbaImage, name: &str) -> anyhow::Result<Self> {
+        unsafe {
+            let extent = vk::Extent2D {
+                width: rgba.width(),
+                height: rgba.height(),
+            };
+            let mut image = MyImage::new(
+                device,
+                ImageCreateInfo {
+                    format: vk::Format::R8G8B8A8_SRGB,
+                    extent,
+                    usage: vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+                    aspect: vk::ImageAspectFlags::COLOR,
+                    samples: vk::SampleCountFlags::TYPE_1,
+                    name: Some(Cow::from(name)),
+                },
+            )?;
+            let result = MyBuffer::from_slice(
+                device,
+                BufferCreateInfo {
+                    usage: vk::BufferUsageFlags::TRANSFER_SRC,
+                    location: MemoryLocation::CpuToGpu,
+                    name: Some(Cow::from(format!("{name} staging"))),
+                },
+                rgba.as_raw(),
+            )
+            .map_err(anyhow::Error::from)
+            .and_then(|mut staging| {
+                let result = upload(device, &staging, &image, extent);
+                staging.destroy(device);
+                result
+            });
+            if let Err(e) = result {
+                image.destroy(device);
+                return Err(e);
+            }
+
+            let sampler = device.create_sampler(
+                &vk::SamplerCreateInfo::default()
+                    .mag_filter(vk::Filter::LINEAR)
+                    .min_filter(vk::Filter::LINEAR)
+                    .address_mode_u(vk::SamplerAddressMode::REPEAT)
+                    .address_mode_v(vk::SamplerAddressMode::REPEAT)
+                    .address_mode_w(vk::SamplerAddressMode::REPEAT),
+                None,
+            )?;
+            device.set_object_name(sampler, name);
+            Ok(Self { image, sampler })
+        }
+    }
+
+    /// Destroy this texture
+    ///
+    /// # Safety
+    /// Texture must not be in use
+    pub unsafe fn destroy(&mut self, device: &Arc<MyDevice>) {
+        unsafe {
+            device.destroy_sampler(self.sampler, None);
+            self.image.destroy(device);
+        }
+    }
+}
+
+/// Copies `staging` into `image`, leaving it in [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`]
+unsafe fn upload(
+    device: &Arc<MyDevice>,
+    staging: &MyBuffer,
+    image: &MyImage,
+    extent: vk::Extent2D,
+) -> anyhow::Result<()> {
+    unsafe {
+        let command = SingleCommandBuffer::new(device.clone())?;
+        let cmd = command.cmd;
+        device.begin_command_buffer(
+            cmd,
+            &vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+        )?;
+        device.cmd_pipeline_barrier2(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&[
+                vk::ImageMemoryBarrier2::default()
+                    .image(image.image)
+                    .src_access_mask(vk::AccessFlags2::NONE)
+                    .src_stage_mask(vk::PipelineStageFlags2::NONE)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+                    .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .subresource_range(subresource_range(vk::ImageAspectFlags::COLOR)),
+            ]),
+        );
+        device.cmd_copy_buffer_to_image(
+            cmd,
+            staging.buffer,
+            image.image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[vk::BufferImageCopy::default()
+                .image_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .layer_count(1),
+                )
+                .image_extent(extent.into())],
+        );
+        device.cmd_pipeline_barrier2(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&[
+                vk::ImageMemoryBarrier2::default()
+                    .image(image.image)
+                    .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .dst_access_mask(vk::AccessFlags2::SHADER_SAMPLED_READ)
+                    .dst_stage_mask(vk::PipelineStageFlags2::FRAGMENT_SHADER)
+                    .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .subresource_range(subresource_range(vk::ImageAspectFlags::COLOR)),
+            ]),
+        );
+        device.end_command_buffer(cmd)?;
+        device.queue_submit2(
+            device.main_queue,
+            &[vk::SubmitInfo2::default().command_buffer_infos(&[
+                vk::CommandBufferSubmitInfo::default().command_buffer(cmd),
+            ])],
+            vk::Fence::null(),
+        )?;
+        device.queue_wait_idle(device.main_queue)?;
+        Ok(())
+    }
+}
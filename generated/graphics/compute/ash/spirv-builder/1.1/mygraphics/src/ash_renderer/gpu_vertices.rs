@@ -0,0 +1,182 @@
+use crate::ash_renderer::buffer::{BufferCreateInfo, MyBuffer};
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use crate::ash_renderer::get_shaders;
+use crate::ash_renderer::global_descriptor_set::{GlobalDescriptorSet, GlobalDescriptorSetLayout};
+use crate::ash_renderer::mesh::{MeshBuffers, MyMesh};
+use ash::vk;
+use gpu_allocator::MemoryLocation;
+use mygraphics_shaders::{GPU_VERTEX_COUNT, MAIN_CS_VERTICES_THREADS, ShaderConstants, Vertex};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Generates the vertices of [`Self::mesh`] with the `main_cs_vertices` compute shader every frame, which are then
+/// drawn like any other mesh
+///
+/// The compute shader writes the vertex buffer as a storage buffer, bound to binding 0 of its own
+/// [`GlobalDescriptorSet`]. Before the draw reads it as vertex input, [`Self::cmd_dispatch`] records a barrier making
+/// the writes visible. There is only a single vertex buffer shared by all frames in flight, so it also waits for the
+/// draws of the previous frames to finish reading before overwriting it.
+pub struct GpuVertices {
+    pub device: Arc<MyDevice>,
+    /// the vertex buffer written by the compute shader, with an index buffer of `0..GPU_VERTEX_COUNT`
+    pub mesh: MyMesh,
+    descriptor_set: GlobalDescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+}
+
+impl GpuVertices {
+    pub fn new(device: Arc<MyDevice>) -> Result<Self, RendererError> {
+        unsafe {
+            let vertices = MyBuffer::new(
+                &device,
+                BufferCreateInfo {
+                    usage: vk::BufferUsageFlags::VERTEX_BUFFER
+                        | vk::BufferUsageFlags::STORAGE_BUFFER,
+                    location: MemoryLocation::GpuOnly,
+                    name: Some(Cow::from("gpu vertices")),
+                },
+                u64::from(GPU_VERTEX_COUNT) * size_of::<Vertex>() as u64,
+            )?;
+            let indices = MyBuffer::from_slice_staged(
+                &device,
+                BufferCreateInfo {
+                    usage: vk::BufferUsageFlags::INDEX_BUFFER,
+                    location: MemoryLocation::GpuOnly,
+                    name: Some(Cow::from("gpu vertices indices")),
+                },
+                &(0..GPU_VERTEX_COUNT).collect::<Vec<_>>(),
+            )?;
+            let mesh = MyMesh {
+                buffers: Some(MeshBuffers {
+                    vertices,
+                    indices,
+                    index_count: GPU_VERTEX_COUNT,
+                }),
+                indirect: None,
+            };
+
+            let layout =
+                GlobalDescriptorSetLayout::new(device.clone(), vk::DescriptorType::STORAGE_BUFFER)?;
+            let vertex_buffer = mesh.buffers.as_ref().unwrap().vertices.buffer;
+            let descriptor_set = GlobalDescriptorSet::new(&layout, Some(vertex_buffer))?;
+            device.set_object_name(descriptor_set.set, "gpu vertices descriptor set");
+            let pipeline_layout = device.create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::default()
+                    .set_layouts(&[layout.layout])
+                    .push_constant_ranges(&[vk::PushConstantRange::default()
+                        .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                        .size(size_of::<ShaderConstants>() as u32)]),
+                None,
+            )?;
+            device.set_object_name(pipeline_layout, "gpu vertices pipeline layout");
+            // from here on, dropping `this` cleans up after errors
+            let mut this = Self {
+                device,
+                mesh,
+                descriptor_set,
+                pipeline_layout,
+                pipeline: vk::Pipeline::null(),
+            };
+
+            let device = this.device.clone();
+            let shader_module = device.create_shader_module(
+                &vk::ShaderModuleCreateInfo::default().code(&get_shaders()?),
+                None,
+            )?;
+            let pipeline = device
+                .create_compute_pipelines(
+                    device.pipeline_cache,
+                    &[vk::ComputePipelineCreateInfo::default()
+                        .stage(
+                            vk::PipelineShaderStageCreateInfo::default()
+                                .stage(vk::ShaderStageFlags::COMPUTE)
+                                .module(shader_module)
+                                .name(c"main_cs_vertices"),
+                        )
+                        .layout(this.pipeline_layout)],
+                    None,
+                )
+                .map_err(|(_, e)| RendererError::PipelineCreation(e));
+            device.destroy_shader_module(shader_module, None);
+            this.pipeline = pipeline?[0];
+            device.set_object_name(this.pipeline, "gpu vertices pipeline");
+            Ok(this)
+        }
+    }
+
+    /// Records the compute dispatch writing the vertices of [`Self::mesh`] for `constants`, followed by a barrier
+    /// making them visible to vertex input. Must be recorded outside of a render pass, before drawing the mesh.
+    ///
+    /// # Safety
+    /// `cmd` must be a command buffer of [`Self::device`] in the recording state
+    pub unsafe fn cmd_dispatch(&self, cmd: vk::CommandBuffer, constants: &ShaderConstants) {
+        unsafe {
+            let device = &self.device;
+            let _label = device.debug_label(cmd, "gpu vertices");
+            let vertices = self.mesh.buffers.as_ref().unwrap().vertices.buffer;
+            // the draws of previous frames must have finished reading the vertices before we overwrite them, an
+            // execution dependency is enough for this write-after-read hazard
+            device.cmd_pipeline_barrier2(
+                cmd,
+                &vk::DependencyInfo::default().buffer_memory_barriers(&[
+                    vk::BufferMemoryBarrier2::default()
+                        .buffer(vertices)
+                        .src_access_mask(vk::AccessFlags2::NONE)
+                        .src_stage_mask(vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT)
+                        .dst_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                        .dst_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                        .size(vk::WHOLE_SIZE),
+                ]),
+            );
+            device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::COMPUTE, self.pipeline);
+            device.cmd_bind_descriptor_sets(
+                cmd,
+                vk::PipelineBindPoint::COMPUTE,
+                self.pipeline_layout,
+                0,
+                &[self.descriptor_set.set],
+                &[],
+            );
+            device.cmd_push_constants(
+                cmd,
+                self.pipeline_layout,
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                bytemuck::bytes_of(constants),
+            );
+            device.cmd_dispatch(
+                cmd,
+                GPU_VERTEX_COUNT.div_ceil(MAIN_CS_VERTICES_THREADS),
+                1,
+                1,
+            );
+            device.cmd_pipeline_barrier2(
+                cmd,
+                &vk::DependencyInfo::default().buffer_memory_barriers(&[
+                    vk::BufferMemoryBarrier2::default()
+                        .buffer(vertices)
+                        .src_access_mask(vk::AccessFlags2::SHADER_STORAGE_WRITE)
+                        .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                        .dst_access_mask(vk::AccessFlags2::VERTEX_ATTRIBUTE_READ)
+                        .dst_stage_mask(vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT)
+                        .size(vk::WHOLE_SIZE),
+                ]),
+            );
+        }
+    }
+}
+
+impl Drop for GpuVertices {
+    fn drop(&mut self) {
+        unsafe {
+            let device = &self.device;
+            device.device_wait_idle().ok();
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            self.descriptor_set.destroy();
+            self.mesh.destroy(device);
+        }
+    }
+}
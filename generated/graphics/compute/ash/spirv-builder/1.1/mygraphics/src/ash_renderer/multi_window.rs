@@ -0,0 +1,252 @@
+//! Multiple windows, each with its own [`MySwapchainManager`], drawn by a single [`MyRenderer`] on one [`MyDevice`]
+
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use crate::ash_renderer::renderer::MyRenderer;
+use crate::ash_renderer::swapchain::{AttachmentConfig, MySwapchainManager};
+use crate::ash_renderer::{
+    CONSTANTS_MODE, DEPTH_TEST, FRAMES_IN_FLIGHT, INSTANCE_COUNT, MSAA_SAMPLES, PRESENT_MODE,
+    SURFACE_FORMAT, SWAPCHAIN_IMAGE_COUNT,
+};
+use crate::util::{AppConfig, RunLimit};
+use anyhow::{Context, bail};
+use bytemuck::Zeroable;
+use mygraphics_shaders::ShaderConstants;
+use raw_window_handle::HasDisplayHandle;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::{
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::{Key, NamedKey},
+    window::WindowId,
+};
+
+/// Like [`super::run`], but opens `window_count` windows sharing one device and renderer. Closing a window keeps
+/// drawing the others, closing the last one or pressing `Escape` exits.
+pub fn run(config: AppConfig, window_count: usize) -> anyhow::Result<()> {
+    env_logger::init();
+    let event_loop = EventLoop::new()?;
+    let mut app = App {
+        limit: RunLimit::new(&config),
+        config,
+        window_count,
+        state: None,
+        error: None,
+    };
+    event_loop.run_app(&mut app)?;
+    app.error.map_or(Ok(()), Err)
+}
+
+struct App {
+    config: AppConfig,
+    window_count: usize,
+    limit: RunLimit,
+    state: Option<State>,
+    /// the error that made the app exit, returned by [`run`] to report it with the exit code of `main`
+    error: Option<anyhow::Error>,
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        match &mut self.state {
+            None => match State::new(event_loop, &self.config, self.window_count) {
+                Ok(state) => self.state = Some(state),
+                Err(e) => {
+                    self.error = Some(e);
+                    event_loop.exit();
+                }
+            },
+            Some(state) => {
+                for window in state.windows.values_mut() {
+                    if let Err(e) = window.swapchain.resume() {
+                        self.error.get_or_insert(e.into());
+                        event_loop.exit();
+                    }
+                }
+            }
+        }
+        // every window is drawn in `about_to_wait`, instead of requesting a redraw of each
+        event_loop.set_control_flow(ControlFlow::Poll);
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(state) = &mut self.state {
+            // the cached framebuffers reference the image views of the swapchains
+            if let Err(e) = state.renderer.pipeline.clear_framebuffers() {
+                self.error.get_or_insert(e.into());
+                event_loop.exit();
+            }
+            for window in state.windows.values_mut() {
+                if let Err(e) = window.swapchain.suspend() {
+                    self.error.get_or_insert(e.into());
+                    event_loop.exit();
+                }
+            }
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        // gone if creating it failed and the app is exiting
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Escape),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => event_loop.exit(),
+            WindowEvent::CloseRequested => {
+                // the cached framebuffers may reference the image views of the removed swapchain
+                if let Err(e) = state.renderer.pipeline.clear_framebuffers() {
+                    self.error.get_or_insert(e.into());
+                    event_loop.exit();
+                }
+                state.windows.remove(&id);
+                if state.windows.is_empty() {
+                    event_loop.exit();
+                }
+            }
+            WindowEvent::Resized(_) => {
+                if let Some(window) = state.windows.get_mut(&id) {
+                    window.swapchain.resized();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.limit.reached(Instant::now()) {
+            event_loop.exit();
+            return;
+        }
+        if let Some(state) = &mut self.state {
+            if let Err(e) = state.render() {
+                self.error.get_or_insert(e);
+                event_loop.exit();
+                return;
+            }
+            self.limit.frame_rendered();
+        }
+    }
+}
+
+/// A window and its swapchain
+struct WindowState {
+    swapchain: MySwapchainManager,
+    /// The first frame slot of the renderer used by this window
+    ///
+    /// A swapchain only waits for the fences of its own frames in flight, so the renderer has separate slots for the
+    /// frames of every window. The [`DrawFrame::frame_index`](crate::ash_renderer::swapchain::DrawFrame::frame_index)
+    /// of the swapchain is offset by this.
+    frame_offset: usize,
+}
+
+struct State {
+    start: Instant,
+    renderer: MyRenderer,
+    /// one per open window, all presenting with the device of `renderer`
+    windows: HashMap<WindowId, WindowState>,
+}
+
+impl State {
+    fn new(
+        event_loop: &ActiveEventLoop,
+        config: &AppConfig,
+        window_count: usize,
+    ) -> anyhow::Result<Self> {
+        let extensions =
+            ash_window::enumerate_required_extensions(event_loop.display_handle()?.as_raw())?;
+        let device = MyDevice::new(extensions, config.validation, &config.device)?;
+        let attachments = AttachmentConfig {
+            depth: DEPTH_TEST,
+            samples: device.sample_count(MSAA_SAMPLES)?,
+        };
+
+        // the pipeline of the renderer is created for a single format, which all windows must share
+        let mut out_format = None;
+        let mut windows = HashMap::new();
+        for i in 0..window_count {
+            let attributes =
+                config
+                    .window_attributes()?
+                    .with_title(format!("{} {}", config.title, i + 1));
+            let window = Arc::new(event_loop.create_window(attributes)?);
+            let swapchain = MySwapchainManager::new(
+                device.clone(),
+                window.clone(),
+                FRAMES_IN_FLIGHT,
+                attachments,
+                config.present_mode.unwrap_or(PRESENT_MODE),
+                out_format.or(SURFACE_FORMAT),
+                SWAPCHAIN_IMAGE_COUNT,
+            )?;
+            let format = swapchain.surface_format.format;
+            if let Some(out_format) = out_format
+                && format != out_format
+            {
+                bail!(
+                    "Window {} has format {format:?}, but the other windows have {out_format:?}",
+                    window.title()
+                );
+            }
+            out_format = Some(format);
+            windows.insert(
+                window.id(),
+                WindowState {
+                    swapchain,
+                    frame_offset: i * FRAMES_IN_FLIGHT,
+                },
+            );
+        }
+        let out_format = out_format.context("No windows to render to")?;
+
+        let renderer = MyRenderer::new(
+            device,
+            out_format,
+            window_count * FRAMES_IN_FLIGHT,
+            attachments,
+            CONSTANTS_MODE,
+        )?;
+        Ok(Self {
+            start: Instant::now(),
+            renderer,
+            windows,
+        })
+    }
+
+    /// Draw every window with the shared renderer, each with the extent of its own swapchain
+    fn render(&mut self) -> anyhow::Result<()> {
+        let time = self.start.elapsed().as_secs_f32();
+        for window in self.windows.values_mut() {
+            let color_space = window.swapchain.color_space();
+            let result = window.swapchain.render(|mut frame| {
+                frame.frame_index += window.frame_offset;
+                let mut shader_constants = ShaderConstants {
+                    time,
+                    width: frame.extent.width,
+                    height: frame.extent.height,
+                    instance_count: INSTANCE_COUNT,
+                    ..Zeroable::zeroed()
+                };
+                color_space.update_constants(&mut shader_constants);
+                self.renderer.render_frame(frame, &shader_constants)
+            });
+            match result {
+                // skip this window, its swapchain is recreated on the next frame
+                Err(RendererError::SwapchainOutOfDate) => (),
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+}
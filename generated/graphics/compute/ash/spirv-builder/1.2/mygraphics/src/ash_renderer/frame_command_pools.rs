@@ -0,0 +1,55 @@
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use crate::ash_renderer::single_command_buffer::SingleCommandBuffer;
+use ash::vk;
+use std::sync::Arc;
+
+/// A ring of command pools with a single command buffer each, one per frame in flight and indexed by
+/// [`DrawFrame::frame_index`](crate::ash_renderer::swapchain::DrawFrame::frame_index)
+///
+/// Resetting a pool resets all command buffers allocated from it, so sharing one pool between frames in flight would
+/// reset command buffers the GPU may still be executing. Instead, every slot has its own pool, which is only reset
+/// when the slot is reused, after the fence of its previous submission signaled.
+pub struct FrameCommandPools {
+    slots: Vec<SingleCommandBuffer>,
+}
+
+impl FrameCommandPools {
+    pub fn new(device: &Arc<MyDevice>, frames_in_flight: usize) -> Result<Self, RendererError> {
+        let slots = (0..frames_in_flight)
+            .map(|frame_index| {
+                let command = SingleCommandBuffer::new(device.clone())?;
+                unsafe {
+                    device.set_object_name(
+                        command.pool,
+                        &format!("frame {frame_index} command pool"),
+                    );
+                    device.set_object_name(
+                        command.cmd,
+                        &format!("frame {frame_index} command buffer"),
+                    );
+                }
+                Ok(command)
+            })
+            .collect::<Result<Vec<_>, RendererError>>()?;
+        Ok(Self { slots })
+    }
+
+    /// Resets the pool of `frame_index` and begins recording its command buffer for a single submission
+    ///
+    /// # Safety
+    /// The previous submission of the command buffer of `frame_index` must have finished executing
+    pub unsafe fn begin(&self, frame_index: usize) -> Result<vk::CommandBuffer, RendererError> {
+        unsafe {
+            let command = &self.slots[frame_index];
+            let device = &command.device;
+            device.reset_command_pool(command.pool, vk::CommandPoolResetFlags::default())?;
+            device.begin_command_buffer(
+                command.cmd,
+                &vk::CommandBufferBeginInfo::default()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )?;
+            Ok(command.cmd)
+        }
+    }
+}
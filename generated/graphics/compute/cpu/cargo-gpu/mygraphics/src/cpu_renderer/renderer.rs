@@ -0,0 +1,165 @@
+use crate::util::Renderer;
+use anyhow::anyhow;
+use glam::{Vec2, Vec3, Vec4, Vec4Swizzles, vec2};
+use image::{Rgba, RgbaImage};
+use mygraphics_shaders::{ShaderConstants, linear_to_srgb, main_fs, main_vs};
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use winit::window::Window;
+
+/// Renders by calling the shader entry points directly on the CPU, presenting the result with [`softbuffer`]
+pub struct MyRenderer {
+    _context: softbuffer::Context<Arc<Window>>,
+    surface: softbuffer::Surface<Arc<Window>, Arc<Window>>,
+    size: Option<(NonZeroU32, NonZeroU32)>,
+    clear_color: [f32; 4],
+}
+
+impl MyRenderer {
+    pub fn new(window: Arc<Window>) -> anyhow::Result<Self> {
+        let context = softbuffer::Context::new(window.clone()).map_err(|e| anyhow!("{e}"))?;
+        let surface = softbuffer::Surface::new(&context, window).map_err(|e| anyhow!("{e}"))?;
+        Ok(Self {
+            _context: context,
+            surface,
+            size: None,
+            clear_color: [0., 0., 0., 1.],
+        })
+    }
+
+    /// Set the color the window is cleared to before drawing, in linear RGBA. Defaults to black.
+    #[inline]
+    pub fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        self.clear_color = clear_color;
+    }
+
+    pub fn render(&mut self, shader_constants: &ShaderConstants) -> anyhow::Result<()> {
+        // a minimized window has a size of 0, there's nothing to draw
+        let (Some(width), Some(height)) = (
+            NonZeroU32::new(shader_constants.width),
+            NonZeroU32::new(shader_constants.height),
+        ) else {
+            return Ok(());
+        };
+        if self.size != Some((width, height)) {
+            self.surface
+                .resize(width, height)
+                .map_err(|e| anyhow!("{e}"))?;
+            self.size = Some((width, height));
+        }
+
+        let mut buffer = self.surface.buffer_mut().map_err(|e| anyhow!("{e}"))?;
+        buffer.fill(to_pixel(Vec4::from_array(self.clear_color)));
+        rasterize(shader_constants, &mut buffer);
+        buffer.present().map_err(|e| anyhow!("{e}"))?;
+        Ok(())
+    }
+}
+
+impl Renderer for MyRenderer {
+    type Target<'a> = ();
+
+    fn render(&mut self, shader_constants: &ShaderConstants, _target: ()) -> anyhow::Result<()> {
+        MyRenderer::render(self, shader_constants)
+    }
+
+    fn capture(&mut self, shader_constants: &ShaderConstants) -> anyhow::Result<RgbaImage> {
+        Ok(render_image(shader_constants, self.clear_color))
+    }
+
+    fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        MyRenderer::set_clear_color(self, clear_color);
+    }
+}
+
+/// Renders a single frame into an image without a window, e.g. for golden image tests
+pub fn render_image(shader_constants: &ShaderConstants, clear_color: [f32; 4]) -> RgbaImage {
+    let (width, height) = (shader_constants.width, shader_constants.height);
+    let mut pixels =
+        vec![to_pixel(Vec4::from_array(clear_color)); width as usize * height as usize];
+    rasterize(shader_constants, &mut pixels);
+    RgbaImage::from_fn(width, height, |x, y| {
+        let [_, r, g, b] = pixels[(y * width + x) as usize].to_be_bytes();
+        Rgba([r, g, b, u8::MAX])
+    })
+}
+
+/// A vertex after running [`main_vs`], in pixel coordinates
+struct Vertex {
+    position: Vec2,
+    color: Vec3,
+}
+
+/// Draws [`ShaderConstants::instances`] triangles into `pixels`, which is `width * height` pixels large in the 0RGB
+/// format of [`softbuffer`]
+fn rasterize(constants: &ShaderConstants, pixels: &mut [u32]) {
+    for instance_id in 0..constants.instances() {
+        rasterize_triangle(constants, instance_id, pixels);
+    }
+}
+
+/// Draws the triangle of instance `instance_id` into `pixels`, like [`rasterize`]
+fn rasterize_triangle(constants: &ShaderConstants, instance_id: u32, pixels: &mut [u32]) {
+    let width = constants.width as usize;
+    let size = vec2(constants.width as f32, constants.height as f32);
+    let [a, b, c] = std::array::from_fn(|vert_id| {
+        let mut position = Vec4::ZERO;
+        let mut color = Vec3::ZERO;
+        main_vs(
+            vert_id as i32,
+            instance_id,
+            constants,
+            &mut position,
+            &mut color,
+        );
+        // perspective divide and viewport transform, framebuffer y points down while clip space y points up
+        let ndc = position.xy() / position.w;
+        Vertex {
+            position: (vec2(ndc.x, -ndc.y) * 0.5 + 0.5) * size,
+            color,
+        }
+    });
+
+    // twice the signed area of the triangle, the sign depends on the winding order
+    let area = edge(a.position, b.position, c.position);
+    if area == 0. {
+        return;
+    }
+
+    let min = a
+        .position
+        .min(b.position)
+        .min(c.position)
+        .floor()
+        .max(Vec2::ZERO);
+    let max = a.position.max(b.position).max(c.position).ceil().min(size);
+    for y in min.y as usize..max.y as usize {
+        for x in min.x as usize..max.x as usize {
+            // sample at the pixel center, like the GPU does
+            let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+            let wa = edge(b.position, c.position, p) / area;
+            let wb = edge(c.position, a.position, p) / area;
+            let wc = edge(a.position, b.position, p) / area;
+            if wa < 0. || wb < 0. || wc < 0. {
+                continue;
+            }
+
+            // all vertices have w = 1, so there's no need for perspective correct interpolation
+            let vtx_color = a.color * wa + b.color * wb + c.color * wc;
+            let mut output = Vec4::ZERO;
+            main_fs(vtx_color, constants, &mut output);
+            pixels[y * width + x] = to_pixel(output);
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b - a).perp_dot(p - a)
+}
+
+/// Converts a linear color to 0RGB with sRGB encoding, matching the sRGB swapchain formats of the GPU renderers
+fn to_pixel(color: Vec4) -> u32 {
+    let encode = |linear: f32| (linear_to_srgb(linear.clamp(0., 1.)) * 255. + 0.5) as u32;
+    (encode(color.x) << 16) | (encode(color.y) << 8) | encode(color.z)
+}
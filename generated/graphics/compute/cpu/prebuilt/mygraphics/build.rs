@@ -0,0 +1,68 @@
+use anyhow::bail;
+use std::path::PathBuf;
+
+/// The shader crates to compile, each a workspace member next to `mygraphics`. Every crate is passed to the main
+/// crate as the `SHADER_SPV_PATH_<NAME>` env var, its name in uppercase with `-` replaced by `_`. The first crate is
+/// additionally passed as `SHADER_SPV_PATH`. Add new crates to `util::SHADER_MODULES` to switch to them at runtime.
+const SHADER_CRATES: &[&str] = &["mygraphics-shaders"];
+
+/// The Vulkan version the shaders are compiled for, passed to the main crate as the `VULKAN_VERSION` env var to create
+/// the device with the same version. The prebuilt shaders are always compiled for 1.3.
+const VULKAN_VERSION: &str = "1.3";
+
+pub fn main() -> anyhow::Result<()> {
+    println!("cargo::rustc-env=VULKAN_VERSION={VULKAN_VERSION}");
+    for (i, shader_crate) in SHADER_CRATES.iter().enumerate() {
+        let spv_path = build_shader(shader_crate)?;
+        let env_name = shader_crate.to_uppercase().replace('-', "_");
+        println!(
+            "cargo::rustc-env=SHADER_SPV_PATH_{env_name}={}",
+            spv_path.display()
+        );
+        if i == 0 {
+            println!("cargo::rustc-env=SHADER_SPV_PATH={}", spv_path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the path to the SPIR-V module of `shader_crate`
+fn build_shader(shader_crate: &str) -> anyhow::Result<PathBuf> {
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    if bindless() {
+        bail!("The prebuilt shaders are compiled without the `bindless` feature, use another integration");
+    }
+    if sample_shading() {
+        bail!("The prebuilt shaders are compiled without the `sample-shading` feature, use another integration");
+    }
+    if gpu_vertices() {
+        bail!("The prebuilt shaders are compiled without the `gpu-vertices` feature, use another integration");
+    }
+    // Instead of compiling the shaders, use the SPIR-V module checked into `<shader_crate>/prebuilt`. It does not
+    // change when you edit the shader crate, regenerate it with the `cargo-gpu` or `spirv-builder` integration.
+    let file_name = format!("{}.spv", shader_crate.replace('-', "_"));
+    let spv_path = [manifest_dir, "..", shader_crate, "prebuilt", &file_name]
+        .iter()
+        .copied()
+        .collect::<PathBuf>();
+    println!("cargo::rerun-if-changed={}", spv_path.display());
+    Ok(spv_path)
+}
+
+/// Whether the `bindless` feature of this crate is enabled, which is forwarded to the shader crates to compile the
+/// entry points indexing into descriptor arrays
+fn bindless() -> bool {
+    std::env::var_os("CARGO_FEATURE_BINDLESS").is_some()
+}
+
+/// Whether the `sample-shading` feature of this crate is enabled, which is forwarded to the shader crates to compile
+/// the entry points running once per sample
+fn sample_shading() -> bool {
+    std::env::var_os("CARGO_FEATURE_SAMPLE_SHADING").is_some()
+}
+
+/// Whether the `gpu-vertices` feature of this crate is enabled, which is forwarded to the shader crates to compile the
+/// compute shader generating vertices
+fn gpu_vertices() -> bool {
+    std::env::var_os("CARGO_FEATURE_GPU_VERTICES").is_some()
+}
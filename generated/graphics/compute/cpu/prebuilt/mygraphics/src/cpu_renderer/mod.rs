@@ -0,0 +1,180 @@
+use crate::cpu_renderer::renderer::{MyRenderer, render_image};
+use crate::util::{
+    AppConfig, CameraState, FrameStats, FullscreenState, MouseState, RunLimit, ShaderClock,
+};
+use bytemuck::Zeroable;
+use mygraphics_shaders::ShaderConstants;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use winit::event_loop::EventLoop;
+use winit::{
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::{Key, NamedKey},
+    window::{Window, WindowId},
+};
+
+pub mod renderer;
+
+/// How many instances of the triangle to draw, arranged in a grid.
+pub const INSTANCE_COUNT: u32 = 1;
+
+pub fn main() -> anyhow::Result<()> {
+    run(AppConfig::from_args("Rust GPU - cpu"))
+}
+
+/// Like [`main`], but with a custom title, size or icon for the window
+pub fn run(config: AppConfig) -> anyhow::Result<()> {
+    env_logger::init();
+    if let Some(path) = &config.capture {
+        return capture(&config, path);
+    }
+    let event_loop = EventLoop::new()?;
+    let mut app = App::new(config);
+    event_loop.run_app(&mut app)?;
+    Ok(())
+}
+
+/// Render a single frame of the size in `config` and save it to `path`
+pub fn capture(config: &AppConfig, path: &Path) -> anyhow::Result<()> {
+    let image = render_image(
+        &ShaderConstants {
+            width: config.size.width,
+            height: config.size.height,
+            instance_count: INSTANCE_COUNT,
+            ..Zeroable::zeroed()
+        },
+        [0., 0., 0., 1.],
+    );
+    image.save(path)?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+pub struct App {
+    config: AppConfig,
+    limit: RunLimit,
+    state: Option<State>,
+}
+
+impl App {
+    pub fn new(config: AppConfig) -> Self {
+        Self {
+            limit: RunLimit::new(&config),
+            config,
+            state: None,
+        }
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_none() {
+            self.state = Some(State::new(event_loop, &self.config).unwrap());
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        let state = self.state.as_mut().unwrap();
+        let redraw = matches!(event, WindowEvent::RedrawRequested);
+        state.window_event(event_loop, id, event).unwrap();
+        if redraw {
+            self.limit.frame_rendered();
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.limit.reached(Instant::now()) {
+            event_loop.exit();
+            return;
+        }
+        // continuously redraw
+        if let Some(state) = &self.state {
+            state.window.request_redraw();
+        }
+    }
+}
+
+struct State {
+    start: Instant,
+    clock: ShaderClock,
+    mouse: MouseState,
+    camera: CameraState,
+    fullscreen: FullscreenState,
+    window: Arc<Window>,
+    renderer: MyRenderer,
+    frame_stats: FrameStats,
+}
+
+impl State {
+    fn new(event_loop: &ActiveEventLoop, config: &AppConfig) -> anyhow::Result<Self> {
+        let window = Arc::new(event_loop.create_window(config.window_attributes()?)?);
+
+        let renderer = MyRenderer::new(window.clone())?;
+        Ok(Self {
+            start: Instant::now(),
+            clock: ShaderClock::default(),
+            mouse: MouseState::default(),
+            camera: CameraState::default(),
+            fullscreen: FullscreenState::default(),
+            frame_stats: FrameStats::new(window.title()),
+            window,
+            renderer,
+        })
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _id: WindowId,
+        event: WindowEvent,
+    ) -> anyhow::Result<()> {
+        match event {
+            WindowEvent::RedrawRequested => {
+                let size = self.window.inner_size();
+                let real_time = self.start.elapsed().as_secs_f32();
+                let mut shader_constants = ShaderConstants {
+                    width: size.width,
+                    height: size.height,
+                    time: self.clock.update(real_time),
+                    instance_count: INSTANCE_COUNT,
+                    ..Zeroable::zeroed()
+                };
+                self.mouse.update_constants(&mut shader_constants);
+                self.camera.update(real_time);
+                self.camera.update_constants(&mut shader_constants);
+                self.renderer.render(&shader_constants)?;
+                self.frame_stats.tick(&self.window);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Escape),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            }
+            | WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::F11),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => self.fullscreen.toggle(&self.window),
+            WindowEvent::KeyboardInput { .. } if self.clock.window_event(&event) => (),
+            WindowEvent::KeyboardInput { .. } => self.camera.window_event(&event),
+            WindowEvent::CursorMoved { .. } | WindowEvent::MouseInput { .. } => {
+                self.mouse.window_event(&event, self.clock.accumulated);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
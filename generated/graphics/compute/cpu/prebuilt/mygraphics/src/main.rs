@@ -1,3 +1,3 @@
 pub fn main() -> anyhow::Result<()> {
-    mygraphics::ash_renderer::main()
+    mygraphics::cpu_compute::main()
 }
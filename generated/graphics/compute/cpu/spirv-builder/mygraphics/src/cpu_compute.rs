@@ -0,0 +1,17 @@
+use crate::util::{print_saxpy, saxpy_input};
+use glam::UVec3;
+use mygraphics_shaders::{MAIN_CS_THREADS, main_cs};
+
+pub fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let input = saxpy_input();
+    let mut data = input.clone();
+
+    // run every invocation of every workgroup one after another, like a dispatch on the GPU would
+    let workgroups = (data.len() as u32).div_ceil(MAIN_CS_THREADS);
+    for id in 0..workgroups * MAIN_CS_THREADS {
+        main_cs(UVec3::new(id, 0, 0), &mut data);
+    }
+
+    print_saxpy(&input, &data)
+}
@@ -0,0 +1,3 @@
+pub mod cpu_compute;
+pub mod cpu_renderer;
+pub mod util;
@@ -0,0 +1,100 @@
+use crate::util::{DevicePreference, print_saxpy, saxpy_input};
+use crate::wgpu_renderer::{request_device, select_adapter};
+use mygraphics_shaders::{MAIN_CS_THREADS, SaxpyElement};
+use pollster::block_on;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BindingType, BufferBindingType, BufferDescriptor, BufferUsages, CommandEncoderDescriptor,
+    ComputePassDescriptor, ComputePipelineDescriptor, MapMode, PipelineLayoutDescriptor, PollType,
+    ShaderStages, include_spirv,
+};
+
+pub fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let instance =
+        wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+    let adapter = block_on(select_adapter(
+        &instance,
+        None,
+        &DevicePreference::from_env(),
+    ))?;
+    let (device, queue) = block_on(request_device(&adapter))?;
+
+    let input = saxpy_input();
+    let size = size_of_val(input.as_slice()) as u64;
+    let data = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("saxpy data"),
+        contents: bytemuck::cast_slice(&input),
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+    });
+    let readback = device.create_buffer(&BufferDescriptor {
+        label: Some("saxpy readback"),
+        size,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("saxpy bind group layout"),
+        entries: &[BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("saxpy bind group"),
+        layout: &bind_group_layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: data.as_entire_binding(),
+        }],
+    });
+    let module = device.create_shader_module(include_spirv!(env!("SHADER_SPV_PATH")));
+    let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+        label: Some("saxpy pipeline layout"),
+        bind_group_layouts: &[Some(&bind_group_layout)],
+        immediate_size: 0,
+    });
+    let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("saxpy pipeline"),
+        layout: Some(&layout),
+        module: &module,
+        entry_point: Some("main_cs"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let mut cmd = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("saxpy"),
+    });
+    let mut cpass = cmd.begin_compute_pass(&ComputePassDescriptor {
+        label: Some("saxpy"),
+        timestamp_writes: None,
+    });
+    cpass.set_pipeline(&pipeline);
+    cpass.set_bind_group(0, &bind_group, &[]);
+    cpass.dispatch_workgroups((input.len() as u32).div_ceil(MAIN_CS_THREADS), 1, 1);
+    drop(cpass);
+    cmd.copy_buffer_to_buffer(&data, 0, &readback, 0, size);
+    queue.submit(std::iter::once(cmd.finish()));
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    readback
+        .slice(..)
+        .map_async(MapMode::Read, move |result| sender.send(result).unwrap());
+    device.poll(PollType::wait_indefinitely())?;
+    receiver.recv()??;
+
+    let mapped = readback.slice(..).get_mapped_range();
+    let output = bytemuck::cast_slice::<u8, SaxpyElement>(&mapped).to_vec();
+    drop(mapped);
+    readback.unmap();
+    print_saxpy(&input, &output)
+}
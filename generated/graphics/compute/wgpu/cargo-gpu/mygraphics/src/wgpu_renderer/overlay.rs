@@ -0,0 +1,451 @@
+use crate::util::ShaderClock;
+use egui::epaint::{ImageData, ImageDelta, Primitive, Vertex};
+use egui::{
+    ClippedPrimitive, Context, TextureFilter, TextureId, TextureOptions, TextureWrapMode,
+    TexturesDelta, ViewportId,
+};
+use mygraphics_shaders::{OverlayConstants, ShaderConstants};
+use std::collections::HashMap;
+use std::time::Instant;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::wgt::CommandEncoderDescriptor;
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    BufferAddress, BufferUsages, ColorTargetState, ColorWrites, Device, Extent3d, FilterMode,
+    FragmentState, IndexFormat, LoadOp, MultisampleState, Operations, Origin3d,
+    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, SamplerBindingType,
+    SamplerDescriptor, ShaderStages, StoreOp, TexelCopyBufferLayout, TexelCopyTextureInfo, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension, VertexAttribute,
+    VertexBufferLayout, VertexState, VertexStepMode, include_spirv, vertex_attr_array,
+};
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+/// A debug overlay drawn with egui on top of the output, showing the FPS and the [`ShaderConstants`] of every frame.
+/// Its sliders edit the constants before they are passed to the renderer.
+///
+/// Call [`Self::window_event`] for every event, [`Self::update`] before rendering a frame and [`Self::paint`] after.
+pub struct Overlay {
+    context: Context,
+    state: egui_winit::State,
+    painter: OverlayPainter,
+    /// toggled with `F1`, the constants are left untouched while hidden
+    pub visible: bool,
+    /// when the previous frame was updated
+    last_frame: Option<Instant>,
+    /// multiplies [`ShaderConstants::zoom`]
+    zoom: f32,
+    /// frames per second, smoothed over the last frames
+    fps: f32,
+    frame: Option<OverlayFrame>,
+}
+
+/// The output of the UI of a frame, waiting to be painted
+struct OverlayFrame {
+    primitives: Vec<ClippedPrimitive>,
+    textures_delta: TexturesDelta,
+    pixels_per_point: f32,
+    encode_srgb: u32,
+}
+
+impl Overlay {
+    pub fn new(device: &Device, window: &Window, out_format: TextureFormat) -> Self {
+        let context = Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            window.theme(),
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        Self {
+            context,
+            state,
+            painter: OverlayPainter::new(device, out_format),
+            visible: true,
+            last_frame: None,
+            zoom: 1.,
+            fps: 0.,
+            frame: None,
+        }
+    }
+
+    /// Feeds `event` to egui, returns whether egui consumed it, e.g. a click on a slider, so the app should ignore it
+    pub fn window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        let response = self.state.on_window_event(window, event);
+        self.visible && response.consumed
+    }
+
+    /// Runs the UI showing `shader_constants`, then applies the edits of its sliders to them. The `clock` controls
+    /// apply from the next frame on.
+    pub fn update(
+        &mut self,
+        window: &Window,
+        clock: &mut ShaderClock,
+        shader_constants: &mut ShaderConstants,
+    ) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            let dt = (now - last_frame).as_secs_f32();
+            if dt > 0. {
+                self.fps = self.fps * 0.9 + 0.1 / dt;
+            }
+        }
+        if !self.visible {
+            return;
+        }
+
+        let constants = *shader_constants;
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, |ctx| {
+            egui::Window::new("Debug").show(ctx, |ui| {
+                ui.label(format!("{:.0} FPS", self.fps));
+                ui.checkbox(&mut clock.paused, "paused");
+                ui.add(egui::Slider::new(&mut clock.scale, 0.0..=4.0).text("time scale"));
+                ui.add(
+                    egui::Slider::new(&mut self.zoom, 0.1..=10.0)
+                        .logarithmic(true)
+                        .text("zoom"),
+                );
+                ui.collapsing("ShaderConstants", |ui| constants_grid(ui, &constants));
+            });
+        });
+        self.state
+            .handle_platform_output(window, output.platform_output);
+
+        let zoom = if constants.zoom > 0. {
+            constants.zoom
+        } else {
+            1.
+        };
+        shader_constants.zoom = zoom * self.zoom;
+
+        self.frame = Some(OverlayFrame {
+            primitives: self
+                .context
+                .tessellate(output.shapes, output.pixels_per_point),
+            textures_delta: output.textures_delta,
+            pixels_per_point: output.pixels_per_point,
+            encode_srgb: shader_constants.encode_srgb,
+        });
+    }
+
+    /// Draws the UI of the last [`Self::update`] on top of `output`, in a renderpass of its own
+    pub fn paint(&mut self, device: &Device, queue: &Queue, output: &TextureView) {
+        if let Some(frame) = self.frame.take() {
+            self.painter.paint(device, queue, output, frame);
+        }
+    }
+}
+
+/// Lists the fields of `constants` in a table
+fn constants_grid(ui: &mut egui::Ui, constants: &ShaderConstants) {
+    let c = constants;
+    let rows = [
+        ("size", format!("{} x {}", c.width, c.height)),
+        ("time", format!("{:.2}", c.time)),
+        ("cursor", format!("{:.0}, {:.0}", c.cursor_x, c.cursor_y)),
+        (
+            "drag",
+            format!(
+                "{:.0}, {:.0} to {:.0}, {:.0}",
+                c.drag_start_x, c.drag_start_y, c.drag_end_x, c.drag_end_y
+            ),
+        ),
+        ("mouse buttons", format!("{:03b}", c.mouse_button_pressed)),
+        ("max luminance", format!("{:.2}", c.max_luminance)),
+        ("encode sRGB", format!("{}", c.encode_srgb != 0)),
+        ("camera", format!("{:.2?}", c.camera_pos)),
+        ("zoom", format!("{:.2}", c.zoom)),
+    ];
+    egui::Grid::new("constants").striped(true).show(ui, |ui| {
+        for (name, value) in rows {
+            ui.label(name);
+            ui.monospace(value);
+            ui.end_row();
+        }
+    });
+}
+
+/// Draws egui meshes with the `overlay_vs` and `overlay_fs` shaders
+struct OverlayPainter {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    /// the textures egui asked us to create, with the bind group to sample them
+    textures: HashMap<TextureId, (Texture, BindGroup)>,
+}
+
+impl OverlayPainter {
+    const VERTEX_ATTRIBUTES: [VertexAttribute; 3] =
+        vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Unorm8x4];
+
+    fn new(device: &Device, out_format: TextureFormat) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("overlay texture"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("overlay layout"),
+            bind_group_layouts: &[Some(&bind_group_layout)],
+            immediate_size: size_of::<OverlayConstants>() as u32,
+        });
+        // set in the build script
+        let module = device.create_shader_module(include_spirv!(env!("SHADER_SPV_PATH")));
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("overlay"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &module,
+                entry_point: Some("overlay_vs"),
+                compilation_options: Default::default(),
+                buffers: &[VertexBufferLayout {
+                    array_stride: size_of::<Vertex>() as BufferAddress,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &Self::VERTEX_ATTRIBUTES,
+                }],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            // drawn onto the resolved output, never multisampled
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &module,
+                entry_point: Some("overlay_fs"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format: out_format,
+                    // egui colors have premultiplied alpha
+                    blend: Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            multiview_mask: None,
+            cache: None,
+        });
+        Self {
+            pipeline,
+            bind_group_layout,
+            textures: HashMap::new(),
+        }
+    }
+
+    /// Creates the texture `id` or updates a region of it
+    fn update_texture(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        id: TextureId,
+        delta: &ImageDelta,
+    ) {
+        let ImageData::Color(image) = &delta.image;
+        let size = Extent3d {
+            width: image.width() as u32,
+            height: image.height() as u32,
+            depth_or_array_layers: 1,
+        };
+        let origin = if let Some([x, y]) = delta.pos {
+            Origin3d {
+                x: x as u32,
+                y: y as u32,
+                z: 0,
+            }
+        } else {
+            self.create_texture(device, id, delta.options, size);
+            Origin3d::ZERO
+        };
+        let Some((texture, _)) = self.textures.get(&id) else {
+            log::warn!("Overlay updates the unknown texture {id:?}");
+            return;
+        };
+        queue.write_texture(
+            TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin,
+                aspect: TextureAspect::All,
+            },
+            bytemuck::cast_slice(&image.pixels),
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(size.width * 4),
+                rows_per_image: None,
+            },
+            size,
+        );
+    }
+
+    /// Creates the texture `id` of `size`, replacing any previous texture with the same id
+    fn create_texture(
+        &mut self,
+        device: &Device,
+        id: TextureId,
+        options: TextureOptions,
+        size: Extent3d,
+    ) {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("overlay texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let filter = |filter| match filter {
+            TextureFilter::Nearest => FilterMode::Nearest,
+            TextureFilter::Linear => FilterMode::Linear,
+        };
+        let address_mode = match options.wrap_mode {
+            TextureWrapMode::ClampToEdge => AddressMode::ClampToEdge,
+            TextureWrapMode::Repeat => AddressMode::Repeat,
+            TextureWrapMode::MirroredRepeat => AddressMode::MirrorRepeat,
+        };
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("overlay sampler"),
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            mag_filter: filter(options.magnification),
+            min_filter: filter(options.minification),
+            ..SamplerDescriptor::default()
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("overlay texture"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        self.textures.insert(id, (texture, bind_group));
+    }
+
+    fn paint(&mut self, device: &Device, queue: &Queue, output: &TextureView, frame: OverlayFrame) {
+        for (id, delta) in &frame.textures_delta.set {
+            self.update_texture(device, queue, *id, delta);
+        }
+
+        // all meshes share one vertex and index buffer, drawn with different ranges of it
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut draws = Vec::new();
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in &frame.primitives
+        {
+            // callbacks draw with custom renderers, which we don't support
+            let Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            let first_index = indices.len() as u32;
+            draws.push((
+                *clip_rect,
+                mesh.texture_id,
+                first_index..first_index + mesh.indices.len() as u32,
+                vertices.len() as i32,
+            ));
+            vertices.extend_from_slice(&mesh.vertices);
+            indices.extend_from_slice(&mesh.indices);
+        }
+
+        if !draws.is_empty() {
+            let size = output.texture().size();
+            let constants = OverlayConstants {
+                screen_size: [
+                    size.width as f32 / frame.pixels_per_point,
+                    size.height as f32 / frame.pixels_per_point,
+                ],
+                encode_srgb: frame.encode_srgb,
+                _pad: 0,
+            };
+            let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("overlay vertices"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: BufferUsages::VERTEX,
+            });
+            let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("overlay indices"),
+                contents: bytemuck::cast_slice(&indices),
+                usage: BufferUsages::INDEX,
+            });
+
+            let mut cmd = device.create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("overlay"),
+            });
+            let mut rpass = cmd.begin_render_pass(&RenderPassDescriptor {
+                label: Some("overlay renderpass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: output,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: Operations {
+                        // draw on top of what the main renderpass left
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_immediates(0, bytemuck::bytes_of(&constants));
+            rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            rpass.set_index_buffer(index_buffer.slice(..), IndexFormat::Uint32);
+            for (clip_rect, texture_id, index_range, base_vertex) in draws {
+                // the clip rect is in points, the scissor in pixels within the output
+                let clip_rect = clip_rect * frame.pixels_per_point;
+                let min_x = (clip_rect.min.x.round().max(0.) as u32).min(size.width);
+                let min_y = (clip_rect.min.y.round().max(0.) as u32).min(size.height);
+                let max_x = (clip_rect.max.x.round().max(0.) as u32).min(size.width);
+                let max_y = (clip_rect.max.y.round().max(0.) as u32).min(size.height);
+                if max_x <= min_x || max_y <= min_y {
+                    continue;
+                }
+                let Some((_, bind_group)) = self.textures.get(&texture_id) else {
+                    continue;
+                };
+                rpass.set_scissor_rect(min_x, min_y, max_x - min_x, max_y - min_y);
+                rpass.set_bind_group(0, bind_group, &[]);
+                rpass.draw_indexed(index_range, base_vertex, 0..1);
+            }
+            drop(rpass);
+            queue.submit(std::iter::once(cmd.finish()));
+        }
+
+        for id in &frame.textures_delta.free {
+            self.textures.remove(id);
+        }
+    }
+}
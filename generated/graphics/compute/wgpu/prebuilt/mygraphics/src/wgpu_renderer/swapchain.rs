@@ -0,0 +1,391 @@
+use crate::util::{FrameStats, OutputColorSpace, PresentMode, RESIZE_DEBOUNCE};
+use anyhow::Context;
+use std::sync::Arc;
+use std::time::Instant;
+use wgpu::{Adapter, CurrentSurfaceTexture, Device, Instance, Surface, TextureFormat, TextureView};
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+
+/// Selects the [`wgpu::PresentMode`] matching `requested` if it is `supported`, otherwise `AutoVsync`, which is
+/// always available. Logs a warning naming both modes when falling back.
+pub fn select_present_mode(
+    supported: &[wgpu::PresentMode],
+    requested: PresentMode,
+) -> wgpu::PresentMode {
+    let mode = match requested {
+        // picks `FifoRelaxed` if available, otherwise `Fifo` which is always supported
+        PresentMode::Vsync => wgpu::PresentMode::AutoVsync,
+        PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+    };
+    if mode == wgpu::PresentMode::AutoVsync || supported.contains(&mode) {
+        mode
+    } else {
+        let fallback = wgpu::PresentMode::AutoVsync;
+        log::warn!(
+            "{requested:?} ({mode:?}) is not supported by the surface, falling back to {fallback:?}"
+        );
+        fallback
+    }
+}
+
+/// The format a surface is configured with and the format its textures are viewed and rendered with
+///
+/// They may only differ in being sRGB or not, which wgpu allows by listing the view format in
+/// [`wgpu::SurfaceConfiguration::view_formats`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct SurfaceFormat {
+    pub surface: TextureFormat,
+    pub view: TextureFormat,
+}
+
+/// Selects the `requested` format if the surface supports it or its sRGB / linear counterpart, otherwise an sRGB view
+/// of the first format that has an sRGB variant, otherwise the first format of the surface
+///
+/// Just taking `caps.formats[0]` isn't enough, as it is a UNORM format like `Bgra8Unorm` on many platforms. Its writes
+/// aren't sRGB encoded, which makes the output look too dark unless the shaders encode it themselves, see
+/// [`OutputColorSpace`]. A surface format that is sRGB itself is preferred over viewing a UNORM one as sRGB. Request a
+/// linear format like `Bgra8Unorm` to explicitly get a linear view.
+pub fn select_surface_format(
+    formats: &[TextureFormat],
+    requested: Option<TextureFormat>,
+) -> Option<SurfaceFormat> {
+    let requested = requested.and_then(|requested| {
+        let surface = formats
+            .iter()
+            .copied()
+            .filter(|format| format.remove_srgb_suffix() == requested.remove_srgb_suffix())
+            .min_by_key(|&format| format != requested)?;
+        Some(SurfaceFormat {
+            surface,
+            view: requested,
+        })
+    });
+    let srgb = || {
+        let surface = formats
+            .iter()
+            .copied()
+            .find(|format| format.is_srgb())
+            .or_else(|| {
+                formats
+                    .iter()
+                    .copied()
+                    .find(|format| format.add_srgb_suffix().is_srgb())
+            })?;
+        Some(SurfaceFormat {
+            surface,
+            view: surface.add_srgb_suffix(),
+        })
+    };
+    let first = || {
+        formats.first().map(|&format| SurfaceFormat {
+            surface: format,
+            view: format,
+        })
+    };
+    requested.or_else(srgb).or_else(first)
+}
+
+/// The HDR format, in the extended linear sRGB color space (scRGB). Request it from [`MySwapchainManager::new`] to
+/// render in HDR, falling back to SDR if the surface doesn't support it.
+///
+/// wgpu exposes HDR only through this format, with 1.0 being SDR white and values above it being brighter:
+/// * Vulkan: if the driver offers `VK_COLOR_SPACE_EXTENDED_SRGB_LINEAR_EXT`, e.g. on Windows with HDR enabled or some
+///   Wayland compositors
+/// * DX12: float16 swapchains are scRGB and appear HDR if HDR is enabled in the Windows display settings
+/// * Metal: opts into Extended Dynamic Range (EDR) on supporting displays on macOS and iOS
+/// * GL and WebGPU: not supported, falls back to SDR
+///
+/// HDR10 (`Rgb10a2Unorm` with the PQ transfer function) is not exposed by wgpu, as it doesn't allow selecting a
+/// color space.
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// wgpu doesn't report the peak luminance of the display. Assume a conservative 400 nits relative to the 80 nits of
+/// SDR white in scRGB, which most HDR displays can reach.
+pub const HDR_MAX_LUMINANCE: f32 = 400. / 80.;
+
+pub struct MySwapchainManager<'a> {
+    instance: Instance,
+    adapter: Adapter,
+    device: Device,
+    window: Arc<Window>,
+    surface: Surface<'a>,
+    format: SurfaceFormat,
+    /// the selected present mode, see [`Self::set_present_mode`]
+    present_mode: wgpu::PresentMode,
+
+    // state below
+    active: Option<ActiveConfiguration>,
+    should_recreate: bool,
+    /// when the window was last resized, reconfiguration is postponed until it's [`RESIZE_DEBOUNCE`] ago
+    last_resize_instant: Option<Instant>,
+    frame_stats: FrameStats,
+}
+
+pub struct ActiveConfiguration {
+    size: PhysicalSize<u32>,
+}
+
+impl<'a> MySwapchainManager<'a> {
+    pub fn new(
+        instance: Instance,
+        adapter: Adapter,
+        device: Device,
+        window: Arc<Window>,
+        surface: Surface<'a>,
+        present_mode: PresentMode,
+        format: Option<TextureFormat>,
+    ) -> anyhow::Result<Self> {
+        let caps = surface.get_capabilities(&adapter);
+        let format = select_surface_format(&caps.formats, format)
+            .context("Incompatible adapter for surface, no supported formats")?;
+        log::info!(
+            "Selected surface format {:?} viewed as {:?}, sRGB: {}",
+            format.surface,
+            format.view,
+            format.view.is_srgb()
+        );
+        let mut this = Self {
+            instance,
+            adapter,
+            device,
+            frame_stats: FrameStats::new(window.title()),
+            window,
+            surface,
+            format,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            active: None,
+            should_recreate: true,
+            last_resize_instant: None,
+        };
+        this.set_present_mode(present_mode);
+        Ok(this)
+    }
+
+    #[inline]
+    pub fn should_recreate(&mut self) {
+        self.should_recreate = true;
+    }
+
+    /// Call on every [`winit::event::WindowEvent::Resized`] to reconfigure the surface once the size is stable
+    #[inline]
+    pub fn resized(&mut self) {
+        self.last_resize_instant = Some(Instant::now());
+        self.should_recreate();
+    }
+
+    /// Whether the window was resized within the last [`RESIZE_DEBOUNCE`]
+    fn is_resizing(&self) -> bool {
+        self.last_resize_instant
+            .is_some_and(|instant| instant.elapsed() < RESIZE_DEBOUNCE)
+    }
+
+    /// The format surface textures are viewed and rendered with, see [`SurfaceFormat`]
+    pub fn format(&self) -> TextureFormat {
+        self.format.view
+    }
+
+    /// The format the surface is configured with, which may differ from [`Self::format`] in being sRGB or not
+    pub fn surface_format(&self) -> TextureFormat {
+        self.format.surface
+    }
+
+    /// Whether writes to the surface view are converted from linear to sRGB. If not, the shader has to apply gamma
+    /// correction manually, unless the surface [`Self::is_hdr`].
+    pub fn is_srgb(&self) -> bool {
+        self.format.view.is_srgb()
+    }
+
+    /// Whether the surface is HDR, see [`HDR_FORMAT`]. Its color space is linear, so no gamma correction is needed.
+    pub fn is_hdr(&self) -> bool {
+        self.format.view == HDR_FORMAT
+    }
+
+    /// How the shaders have to encode their output for the surface
+    pub fn color_space(&self) -> OutputColorSpace {
+        OutputColorSpace::new(self.is_srgb(), self.is_hdr())
+    }
+
+    /// The value for [`ShaderConstants::max_luminance`](mygraphics_shaders::ShaderConstants::max_luminance)
+    pub fn max_luminance(&self) -> f32 {
+        if self.is_hdr() { HDR_MAX_LUMINANCE } else { 1. }
+    }
+
+    /// Select the [`wgpu::PresentMode`] matching `present_mode`, see [`select_present_mode`], and reconfigure the
+    /// surface with it on the next frame
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        let caps = self.surface.get_capabilities(&self.adapter);
+        self.present_mode = select_present_mode(&caps.present_modes, present_mode);
+        log::info!("Selected present mode {:?}", self.present_mode);
+        self.should_recreate();
+    }
+
+    /// Acquire the next surface texture, reconfiguring the surface if necessary, call `f` to draw to it and present it
+    ///
+    /// Does nothing while the window has no area, e.g. while it's minimized, as the surface can't be configured with a
+    /// zero size. Rendering resumes once the window has an area again.
+    pub fn render(
+        &mut self,
+        f: impl FnOnce(TextureView) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let size = self.window.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+        profile_span!("frame");
+        if self
+            .active
+            .as_ref()
+            .is_none_or(|active| active.size != size)
+        {
+            self.should_recreate();
+        }
+
+        // Reconfigure and acquire again within the same call, instead of skipping a frame which shows up as a black
+        // flash while resizing. While the window is being resized, we keep the old configuration until the size is
+        // stable, unless the surface reports it as outdated.
+        const RECREATE_ATTEMPTS: u32 = 10;
+        let mut outdated = false;
+        for _ in 0..RECREATE_ATTEMPTS {
+            let debounce = self.active.is_some() && !outdated && self.is_resizing();
+            if self.should_recreate && !debounce {
+                self.should_recreate = false;
+                self.configure_surface(size)?;
+            }
+
+            let current_texture = {
+                profile_span!("acquire");
+                self.surface.get_current_texture()
+            };
+            let surface_texture = match current_texture {
+                CurrentSurfaceTexture::Success(surface_texture) => surface_texture,
+                CurrentSurfaceTexture::Suboptimal(surface_texture) => {
+                    // still usable, recreate on the next frame
+                    self.should_recreate();
+                    surface_texture
+                }
+                CurrentSurfaceTexture::Occluded | CurrentSurfaceTexture::Timeout => return Ok(()),
+                CurrentSurfaceTexture::Outdated => {
+                    self.should_recreate();
+                    outdated = true;
+                    continue;
+                }
+                CurrentSurfaceTexture::Validation => {
+                    anyhow::bail!("Validation error during surface texture acquisition")
+                }
+                CurrentSurfaceTexture::Lost => {
+                    self.surface = self.instance.create_surface(self.window.clone())?;
+                    self.should_recreate();
+                    continue;
+                }
+            };
+            let texture = &surface_texture.texture;
+            assert!(
+                debounce || (texture.width(), texture.height()) == (size.width, size.height),
+                "rendering into a surface texture of stale extent"
+            );
+            let output_view = texture.create_view(&wgpu::TextureViewDescriptor {
+                format: Some(self.format.view),
+                ..wgpu::TextureViewDescriptor::default()
+            });
+            f(output_view)?;
+            {
+                profile_span!("present");
+                surface_texture.present();
+            }
+            self.frame_stats.tick(&self.window);
+            return Ok(());
+        }
+        anyhow::bail!(
+            "looped {RECREATE_ATTEMPTS} times trying to acquire surface texture and failed repeatedly"
+        );
+    }
+
+    fn configure_surface(&mut self, size: PhysicalSize<u32>) -> anyhow::Result<()> {
+        let mut surface_config = self
+            .surface
+            .get_default_config(&self.adapter, size.width, size.height)
+            .with_context(|| {
+                format!(
+                    "Incompatible adapter for surface, returned capabilities: {:?}",
+                    self.surface.get_capabilities(&self.adapter)
+                )
+            })?;
+
+        surface_config.format = self.format.surface;
+        surface_config.view_formats = if self.format.view != self.format.surface {
+            vec![self.format.view]
+        } else {
+            Vec::new()
+        };
+        surface_config.present_mode = self.present_mode;
+        self.surface.configure(&self.device, &surface_config);
+
+        self.active = Some(ActiveConfiguration { size });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn select_surface_format_prefers_srgb() {
+        use TextureFormat::*;
+        let select = |formats: &[TextureFormat], requested| {
+            select_surface_format(formats, requested).map(|format| (format.surface, format.view))
+        };
+        // an sRGB surface format is used directly, a UNORM one is viewed as sRGB
+        assert_eq!(
+            select(&[Bgra8Unorm, Bgra8UnormSrgb], None),
+            Some((Bgra8UnormSrgb, Bgra8UnormSrgb))
+        );
+        assert_eq!(
+            select(&[Rgba16Float, Bgra8Unorm], None),
+            Some((Bgra8Unorm, Bgra8UnormSrgb))
+        );
+        // formats without an sRGB variant stay linear
+        assert_eq!(
+            select(&[Rgba16Float, Rgb10a2Unorm], None),
+            Some((Rgba16Float, Rgba16Float))
+        );
+        assert_eq!(select(&[], None), None);
+
+        // a requested linear format is respected, even if only its sRGB variant is supported by the surface
+        assert_eq!(
+            select(&[Bgra8UnormSrgb], Some(Bgra8Unorm)),
+            Some((Bgra8UnormSrgb, Bgra8Unorm))
+        );
+        assert_eq!(
+            select(&[Bgra8Unorm, Rgba16Float], Some(HDR_FORMAT)),
+            Some((HDR_FORMAT, HDR_FORMAT))
+        );
+        // an unsupported request falls back to sRGB
+        assert_eq!(
+            select(&[Bgra8Unorm], Some(HDR_FORMAT)),
+            Some((Bgra8Unorm, Bgra8UnormSrgb))
+        );
+    }
+
+    #[test]
+    pub fn select_present_mode_fallback() {
+        let fifo_only = [wgpu::PresentMode::Fifo];
+        let all = [
+            wgpu::PresentMode::Fifo,
+            wgpu::PresentMode::Immediate,
+            wgpu::PresentMode::Mailbox,
+        ];
+        for (requested, expected) in [
+            (PresentMode::Vsync, wgpu::PresentMode::AutoVsync),
+            (PresentMode::Immediate, wgpu::PresentMode::Immediate),
+            (PresentMode::Mailbox, wgpu::PresentMode::Mailbox),
+        ] {
+            assert_eq!(select_present_mode(&all, requested), expected);
+            assert_eq!(
+                select_present_mode(&fifo_only, requested),
+                wgpu::PresentMode::AutoVsync
+            );
+        }
+    }
+}
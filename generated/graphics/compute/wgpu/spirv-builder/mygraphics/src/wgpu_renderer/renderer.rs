@@ -0,0 +1,520 @@
+use crate::util::{Renderer, read_spirv_file, validate_spirv};
+use crate::wgpu_renderer::mesh::MyMesh;
+use crate::wgpu_renderer::render_pipeline::MyRenderPipeline;
+#[cfg(feature = "texture")]
+use crate::wgpu_renderer::texture::MyTexture;
+use anyhow::{Context, bail};
+use image::RgbaImage;
+use mygraphics_shaders::ShaderConstants;
+use pollster::block_on;
+use std::path::Path;
+use wgpu::util::{BufferInitDescriptor, DeviceExt, make_spirv};
+use wgpu::wgt::CommandEncoderDescriptor;
+use wgpu::{
+    Adapter, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+    BufferBinding, BufferBindingType, BufferDescriptor, BufferUsages, COPY_BYTES_PER_ROW_ALIGNMENT,
+    Color, Device, DownlevelFlags, ErrorFilter, Extent3d, Features, LoadOp, MapMode, Operations,
+    PollType, PolygonMode, PrimitiveState, Queue, RenderPassColorAttachment, RenderPassDescriptor,
+    ShaderModuleDescriptor, ShaderStages, StoreOp, TexelCopyBufferInfo, TexelCopyBufferLayout,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor, include_spirv,
+};
+
+/// How many multisampled color targets of different sizes [`MyRenderer`] keeps, e.g. one per window it renders into
+const MSAA_TARGET_CACHE: usize = 4;
+
+pub struct MyRenderer {
+    pub device: Device,
+    pub queue: Queue,
+    /// written every frame and read through `global_bind_group`, which is created once
+    shader_constants: Buffer,
+    global_bind_group: GlobalBindGroup,
+    pipeline: MyRenderPipeline,
+    out_format: TextureFormat,
+    sample_count: u32,
+    /// multisampled color targets of the most recently rendered sizes, the latest last, see [`Self::msaa_target`]
+    msaa_targets: Vec<TextureView>,
+    clear_color: [f32; 4],
+    mesh: Option<MyMesh>,
+}
+
+impl MyRenderer {
+    /// A `sample_count` of 1 disables MSAA, see [`validate_sample_count`] for supported values.
+    pub fn new(
+        device: Device,
+        queue: Queue,
+        out_format: TextureFormat,
+        sample_count: u32,
+    ) -> anyhow::Result<Self> {
+        let global_bind_group_layout = GlobalBindGroupLayout::new(&device);
+        // set in the build script
+        let shader_module = device.create_shader_module(include_spirv!(env!("SHADER_SPV_PATH")));
+        let pipeline = MyRenderPipeline::new(
+            &device,
+            &global_bind_group_layout,
+            shader_module,
+            out_format,
+            sample_count,
+            false,
+            PrimitiveState::default(),
+        )?;
+        let shader_constants = device.create_buffer(&BufferDescriptor {
+            label: Some("ShaderConstants"),
+            size: size_of::<ShaderConstants>() as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        #[cfg(feature = "texture")]
+        let texture = MyTexture::new(&device, &queue, &crate::util::load_texture()?, "texture");
+        let global_bind_group = global_bind_group_layout.create_from_buffer(
+            &device,
+            &shader_constants,
+            #[cfg(feature = "texture")]
+            &texture,
+        );
+        Ok(Self {
+            shader_constants,
+            global_bind_group,
+            pipeline,
+            device,
+            queue,
+            out_format,
+            sample_count,
+            msaa_targets: Vec::new(),
+            clear_color: [0., 0., 0., 1.],
+            mesh: None,
+        })
+    }
+
+    /// Draw `mesh` instead of the vertexless triangle, or go back to the triangle with `None`
+    pub fn set_mesh(&mut self, mesh: Option<MyMesh>) -> anyhow::Result<()> {
+        self.pipeline.set_vertex_input(&self.device, mesh.is_some());
+        self.mesh = mesh;
+        Ok(())
+    }
+
+    /// Replace the shaders with the SPIR-V module `spv`, which must have the same entry points. If the module fails
+    /// validation, the previous shaders are kept and an error is returned.
+    pub fn reload_shader(&mut self, spv: &[u8]) -> anyhow::Result<()> {
+        // `make_spirv` panics on anything that doesn't look like SPIR-V
+        validate_spirv(spv)?;
+        // catch validation errors instead of letting wgpu panic on them
+        let scope = self.device.push_error_scope(ErrorFilter::Validation);
+        let module = self.device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("MyRenderer shaders"),
+            source: make_spirv(spv),
+        });
+        let mut pipeline = self.pipeline.clone();
+        pipeline.set_shader_module(&self.device, module);
+        if let Some(err) = block_on(scope.pop()) {
+            bail!("Failed to reload shader: {err}");
+        }
+        self.pipeline = pipeline;
+        Ok(())
+    }
+
+    /// Replace the shaders with the SPIR-V module read from `path` at runtime, see [`Self::reload_shader`]
+    pub fn reload_shader_from_path(&mut self, path: &Path) -> anyhow::Result<()> {
+        self.reload_shader(&read_spirv_file(path)?)
+            .with_context(|| format!("Failed to load shader {}", path.display()))
+    }
+
+    /// Shade every sample individually with MSAA, for smoother high-frequency detail within triangles at the cost of
+    /// running the fragment shader more often. Check support with [`validate_sample_shading`] first.
+    #[inline]
+    pub fn set_sample_shading(&mut self, sample_shading: bool) {
+        self.pipeline
+            .set_sample_shading(&self.device, sample_shading);
+    }
+
+    /// Draw the outlines of triangles instead of filling them, for debugging geometry. Requires
+    /// [`Features::POLYGON_MODE_LINE`], without it a warning is logged and triangles stay filled.
+    pub fn set_wireframe(&mut self, wireframe: bool) {
+        if wireframe && !self.device.features().contains(Features::POLYGON_MODE_LINE) {
+            log::warn!(
+                "Wireframe requires the POLYGON_MODE_LINE feature, which the device does not support"
+            );
+            return;
+        }
+        let polygon_mode = if wireframe {
+            PolygonMode::Line
+        } else {
+            PolygonMode::Fill
+        };
+        self.pipeline.set_polygon_mode(&self.device, polygon_mode);
+    }
+
+    #[inline]
+    pub fn wireframe(&self) -> bool {
+        self.pipeline.polygon_mode() == PolygonMode::Line
+    }
+
+    /// Set the color the output is cleared to before drawing, in linear RGBA. Defaults to black.
+    #[inline]
+    pub fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        self.clear_color = clear_color;
+    }
+
+    /// The multisampled color target matching the size of `output`, if MSAA is enabled
+    ///
+    /// The targets of the last [`MSAA_TARGET_CACHE`] sizes are kept, so rendering into multiple windows of different
+    /// sizes doesn't recreate them every frame.
+    fn msaa_target(&mut self, output: &TextureView) -> Option<TextureView> {
+        if self.sample_count == 1 {
+            return None;
+        }
+        let size = output.texture().size();
+        let target = if let Some(index) = self
+            .msaa_targets
+            .iter()
+            .position(|target| target.texture().size() == size)
+        {
+            self.msaa_targets.remove(index)
+        } else {
+            let texture = self.device.create_texture(&TextureDescriptor {
+                label: Some("msaa color"),
+                size,
+                mip_level_count: 1,
+                sample_count: self.sample_count,
+                dimension: TextureDimension::D2,
+                format: self.out_format,
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            });
+            texture.create_view(&TextureViewDescriptor::default())
+        };
+        if self.msaa_targets.len() == MSAA_TARGET_CACHE {
+            self.msaa_targets.remove(0);
+        }
+        self.msaa_targets.push(target.clone());
+        Some(target)
+    }
+
+    pub fn render(
+        &mut self,
+        shader_constants: &ShaderConstants,
+        output: TextureView,
+    ) -> anyhow::Result<()> {
+        let cmd = {
+            profile_span!("record");
+            // the write happens before any command buffer of the next submit
+            self.queue.write_buffer(
+                &self.shader_constants,
+                0,
+                bytemuck::bytes_of(shader_constants),
+            );
+
+            // with MSAA, we render into the multisampled target and resolve it into `output`
+            let msaa_target = self.msaa_target(&output);
+            let (view, resolve_target) = match &msaa_target {
+                Some(msaa_target) => (msaa_target, Some(&output)),
+                None => (&output, None),
+            };
+
+            let mut cmd = self
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("main draw"),
+                });
+
+            let [r, g, b, a] = self.clear_color;
+            let mut rpass = cmd.begin_render_pass(&RenderPassDescriptor {
+                label: Some("main renderpass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    depth_slice: None,
+                    resolve_target,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color {
+                            r: f64::from(r),
+                            g: f64::from(g),
+                            b: f64::from(b),
+                            a: f64::from(a),
+                        }),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            // shows up as a group in debuggers like RenderDoc
+            rpass.push_debug_group("main draw");
+            self.pipeline.draw(
+                &mut rpass,
+                &self.global_bind_group,
+                self.mesh.as_ref(),
+                shader_constants.instances(),
+            )?;
+            rpass.pop_debug_group();
+            drop(rpass);
+            cmd.finish()
+        };
+
+        profile_span!("submit");
+        self.queue.submit(std::iter::once(cmd));
+        Ok(())
+    }
+
+    /// Renders a single frame of `width` x `height` into an offscreen texture and reads it back to the CPU
+    ///
+    /// This blocks until the GPU has finished, so it's meant for screenshots and tests, not for every frame.
+    pub fn capture_frame(
+        &mut self,
+        width: u32,
+        height: u32,
+        shader_constants: &ShaderConstants,
+    ) -> anyhow::Result<RgbaImage> {
+        let bgra = match self.out_format {
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+            TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+            format => bail!("Capturing textures of format {format:?} is not supported"),
+        };
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&TextureDescriptor {
+            label: Some("capture color"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: self.out_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        self.render(
+            shader_constants,
+            texture.create_view(&TextureViewDescriptor::default()),
+        )?;
+
+        // rows of a texture copy must be aligned to 256 bytes
+        let bytes_per_row = (width * 4).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback = self.device.create_buffer(&BufferDescriptor {
+            label: Some("capture readback"),
+            size: u64::from(bytes_per_row) * u64::from(height),
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut cmd = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("capture readback"),
+            });
+        cmd.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        self.queue.submit(std::iter::once(cmd.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        readback
+            .slice(..)
+            .map_async(MapMode::Read, move |result| sender.send(result).unwrap());
+        self.device.poll(PollType::wait_indefinitely())?;
+        receiver.recv()??;
+
+        // The texture and the PNG share the same encoding: sRGB formats store sRGB encoded bytes, just like the
+        // surface texture that's shown on screen, so we copy the bytes without any color conversion.
+        let mapped = readback.slice(..).get_mapped_range();
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+        for row in mapped.chunks_exact(bytes_per_row as usize) {
+            for pixel in row[..width as usize * 4].chunks_exact(4) {
+                let [r, g, b] = if bgra {
+                    [pixel[2], pixel[1], pixel[0]]
+                } else {
+                    [pixel[0], pixel[1], pixel[2]]
+                };
+                // surface textures are opaque, alpha is meaningless
+                rgba.extend_from_slice(&[r, g, b, u8::MAX]);
+            }
+        }
+        drop(mapped);
+        readback.unmap();
+        RgbaImage::from_raw(width, height, rgba).context("capture has an unexpected size")
+    }
+}
+
+impl Renderer for MyRenderer {
+    type Target<'a> = TextureView;
+
+    fn render(
+        &mut self,
+        shader_constants: &ShaderConstants,
+        target: TextureView,
+    ) -> anyhow::Result<()> {
+        MyRenderer::render(self, shader_constants, target)
+    }
+
+    fn capture(&mut self, shader_constants: &ShaderConstants) -> anyhow::Result<RgbaImage> {
+        self.capture_frame(
+            shader_constants.width,
+            shader_constants.height,
+            shader_constants,
+        )
+    }
+
+    fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        MyRenderer::set_clear_color(self, clear_color);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GlobalBindGroupLayout(pub BindGroupLayout);
+
+impl GlobalBindGroupLayout {
+    pub fn new(device: &Device) -> Self {
+        Self(device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("GlobalBindGroupLayout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                #[cfg(feature = "texture")]
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                #[cfg(feature = "texture")]
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        }))
+    }
+
+    pub fn create(
+        &self,
+        device: &Device,
+        shader_constants: &ShaderConstants,
+        #[cfg(feature = "texture")] texture: &MyTexture,
+    ) -> GlobalBindGroup {
+        let shader_constants = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("ShaderConstants"),
+            contents: bytemuck::bytes_of(shader_constants),
+            usage: BufferUsages::STORAGE,
+        });
+        self.create_from_buffer(
+            device,
+            &shader_constants,
+            #[cfg(feature = "texture")]
+            texture,
+        )
+    }
+
+    pub fn create_from_buffer(
+        &self,
+        device: &Device,
+        shader_constants: &Buffer,
+        #[cfg(feature = "texture")] texture: &MyTexture,
+    ) -> GlobalBindGroup {
+        GlobalBindGroup(device.create_bind_group(&BindGroupDescriptor {
+            label: Some("GlobalBindGroup"),
+            layout: &self.0,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: shader_constants,
+                        offset: 0,
+                        size: None,
+                    }),
+                },
+                #[cfg(feature = "texture")]
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&texture.view),
+                },
+                #[cfg(feature = "texture")]
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GlobalBindGroup(pub BindGroup);
+
+/// Checks that `sample_count` is supported for rendering to `format` on the device. Sample counts other than 1 and 4
+/// additionally require the [`Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`] feature to be enabled.
+pub fn validate_sample_count(
+    adapter: &Adapter,
+    device: &Device,
+    format: TextureFormat,
+    sample_count: u32,
+) -> anyhow::Result<u32> {
+    if !matches!(sample_count, 1 | 4)
+        && !device
+            .features()
+            .contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    {
+        bail!(
+            "Sample count {sample_count} requires the `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES` feature, which is not supported"
+        );
+    }
+    let format_features = adapter.get_texture_format_features(format);
+    if !format_features.flags.sample_count_supported(sample_count) {
+        bail!(
+            "Sample count {sample_count} is not supported for format {format:?}, supported: {:?}",
+            format_features.flags.supported_sample_counts()
+        );
+    }
+    Ok(sample_count)
+}
+
+/// Checks whether shading every sample with [`MyRenderer::set_sample_shading`] is possible, logging a warning and
+/// returning `false` if it's not. It requires the `sample-shading` feature to compile `main_fs_per_sample` and
+/// [`DownlevelFlags::MULTISAMPLED_SHADING`], and only has an effect with MSAA and without the `texture` feature.
+pub fn validate_sample_shading(adapter: &Adapter, sample_count: u32, sample_shading: bool) -> bool {
+    if !sample_shading {
+        return false;
+    }
+    if !cfg!(feature = "sample-shading") {
+        log::warn!("Sample shading requires the `sample-shading` feature to compile its shaders");
+        return false;
+    }
+    if !adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(DownlevelFlags::MULTISAMPLED_SHADING)
+    {
+        log::warn!("Sample shading is not supported by the adapter");
+        return false;
+    }
+    if sample_count == 1 || cfg!(feature = "texture") {
+        log::warn!("Sample shading only has an effect with MSAA and without the `texture` feature");
+        return false;
+    }
+    true
+}
@@ -0,0 +1,90 @@
+//! Renders a cube from a vertex and index buffer without opening a window and writes it to `cube.png`
+
+use ash::vk;
+use bytemuck::Zeroable;
+use mygraphics::ash_renderer::device::MyDevice;
+use mygraphics::ash_renderer::mesh::MyMesh;
+use mygraphics::ash_renderer::render_pipeline::{ConstantsMode, PrimitiveConfig};
+use mygraphics::ash_renderer::renderer::MyRenderer;
+use mygraphics::ash_renderer::swapchain::AttachmentConfig;
+use mygraphics::util::{DevicePreference, enable_debug_layer};
+use mygraphics_shaders::{ShaderConstants, Vertex};
+
+/// The faces of the unit cube as `(normal, u, v)` with `u × v = normal`, so their corners are counter-clockwise when
+/// looking at the face from outside
+const FACES: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+    ([1., 0., 0.], [0., 1., 0.], [0., 0., 1.]),
+    ([-1., 0., 0.], [0., 0., 1.], [0., 1., 0.]),
+    ([0., 1., 0.], [0., 0., 1.], [1., 0., 0.]),
+    ([0., -1., 0.], [1., 0., 0.], [0., 0., 1.]),
+    ([0., 0., 1.], [1., 0., 0.], [0., 1., 0.]),
+    ([0., 0., -1.], [0., 1., 0.], [1., 0., 0.]),
+];
+
+const COLORS: [[f32; 3]; 6] = [
+    [1., 0., 0.],
+    [0., 1., 1.],
+    [0., 1., 0.],
+    [1., 0., 1.],
+    [0., 0., 1.],
+    [1., 1., 0.],
+];
+
+/// Rotates the cube towards the viewer and projects it orthographically, as `main_vs_mesh` takes 2D positions and
+/// corrects them for the aspect ratio. The faces pointing away from the viewer end up clockwise and are culled, so the
+/// cube needs no depth buffer.
+fn cube() -> (Vec<Vertex>, Vec<u32>) {
+    let (yaw, pitch) = (35f32.to_radians(), 25f32.to_radians());
+    let project = |[x, y, z]: [f32; 3]| {
+        let (x, z) = (x * yaw.cos() + z * yaw.sin(), z * yaw.cos() - x * yaw.sin());
+        let y = y * pitch.cos() - z * pitch.sin();
+        [x * 0.4, y * 0.4]
+    };
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for ((normal, u, v), color) in FACES.into_iter().zip(COLORS) {
+        let first = vertices.len() as u32;
+        for (s, t) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let corner = std::array::from_fn(|i| normal[i] + s * u[i] + t * v[i]);
+            vertices.push(Vertex {
+                position: project(corner),
+                color,
+            });
+        }
+        indices.extend([0, 1, 2, 0, 2, 3].map(|i| first + i));
+    }
+    (vertices, indices)
+}
+
+pub fn main() -> anyhow::Result<()> {
+    let device = MyDevice::new_headless(enable_debug_layer(), &DevicePreference::from_env())?;
+    let mut renderer = MyRenderer::new(
+        device.clone(),
+        vk::Format::R8G8B8A8_SRGB,
+        1,
+        AttachmentConfig::default(),
+        ConstantsMode::default(),
+    )?;
+    let extent = vk::Extent2D {
+        width: 1280,
+        height: 720,
+    };
+    let (vertices, indices) = cube();
+    renderer.set_mesh(MyMesh::new(&device, &vertices, &indices)?)?;
+    renderer.pipeline.set_primitive(PrimitiveConfig {
+        cull_back_faces: true,
+        ..PrimitiveConfig::default()
+    });
+    let image = renderer.capture_frame(
+        extent,
+        &ShaderConstants {
+            width: extent.width,
+            height: extent.height,
+            ..Zeroable::zeroed()
+        },
+    )?;
+    image.save("cube.png")?;
+    println!("wrote cube.png");
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! Renders the vertexless triangle with an indirect draw filled from the CPU, without opening a window, and writes it
+//! to `indirect.png`
+
+use ash::vk;
+use bytemuck::Zeroable;
+use mygraphics::ash_renderer::device::MyDevice;
+use mygraphics::ash_renderer::mesh::MyMesh;
+use mygraphics::ash_renderer::render_pipeline::ConstantsMode;
+use mygraphics::ash_renderer::renderer::MyRenderer;
+use mygraphics::ash_renderer::swapchain::AttachmentConfig;
+use mygraphics::util::{DevicePreference, enable_debug_layer};
+use mygraphics_shaders::{DrawIndirectCommand, ShaderConstants};
+
+/// The triangle, followed by a draw without any vertices, as a culling compute shader would leave it
+const COMMANDS: [DrawIndirectCommand; 2] = [
+    DrawIndirectCommand {
+        vertex_count: 3,
+        instance_count: 1,
+        first_vertex: 0,
+        first_instance: 0,
+    },
+    DrawIndirectCommand {
+        vertex_count: 0,
+        instance_count: 1,
+        first_vertex: 0,
+        first_instance: 0,
+    },
+];
+
+pub fn main() -> anyhow::Result<()> {
+    let device = MyDevice::new_headless(enable_debug_layer(), &DevicePreference::from_env())?;
+    if !device.multi_draw_indirect {
+        println!("multiDrawIndirect is not supported, recording one indirect draw per command");
+    }
+    let mut renderer = MyRenderer::new(
+        device.clone(),
+        vk::Format::R8G8B8A8_SRGB,
+        1,
+        AttachmentConfig::default(),
+        ConstantsMode::default(),
+    )?;
+    renderer.set_mesh(MyMesh::vertexless_indirect(&device, &COMMANDS)?)?;
+    let extent = vk::Extent2D {
+        width: 1280,
+        height: 720,
+    };
+    let image = renderer.capture_frame(
+        extent,
+        &ShaderConstants {
+            width: extent.width,
+            height: extent.height,
+            ..Zeroable::zeroed()
+        },
+    )?;
+    image.save("indirect.png")?;
+    println!("wrote indirect.png");
+    Ok(())
+}
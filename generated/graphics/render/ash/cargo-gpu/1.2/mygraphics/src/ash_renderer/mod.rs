@@ -0,0 +1,482 @@
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use crate::ash_renderer::mesh::MyMesh;
+use crate::ash_renderer::render_pipeline::ConstantsMode;
+use crate::ash_renderer::renderer::{MyRenderer, ThrottleMode};
+use crate::ash_renderer::swapchain::{AttachmentConfig, MySwapchainManager};
+use crate::util::{
+    AppConfig, CameraState, FullscreenState, MouseState, PresentMode, QUAD_INDICES, QUAD_VERTICES,
+    RunLimit, SHADER_MODULES, ShaderClock, ShaderModule, read_spirv_file,
+};
+use ash::util::read_spv;
+use ash::vk;
+use bytemuck::Zeroable;
+use mygraphics_shaders::ShaderConstants;
+use raw_window_handle::HasDisplayHandle;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use winit::event_loop::EventLoop;
+use winit::{
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::{Key, NamedKey},
+    window::{Window, WindowId},
+};
+
+pub mod buffer;
+pub mod device;
+pub mod error;
+pub mod frame_command_pools;
+pub mod global_descriptor_set;
+#[cfg(feature = "gpu-vertices")]
+pub mod gpu_vertices;
+pub mod image;
+pub mod mesh;
+pub mod multi_window;
+pub mod render_pass;
+pub mod render_pipeline;
+pub mod renderer;
+#[cfg(feature = "hot-reload")]
+pub mod shader_watcher;
+pub mod single_command_buffer;
+pub mod swapchain;
+#[cfg(feature = "texture")]
+pub mod texture;
+
+/// How many frames the CPU may record ahead of the GPU. Use 1 to always wait for the previous frame to finish.
+pub const FRAMES_IN_FLIGHT: usize = 2;
+
+/// How long to block after submitting a frame, toggled at runtime by pressing `T`. See [`ThrottleMode`].
+pub const THROTTLE: ThrottleMode = ThrottleMode::None;
+
+/// How to pass the `ShaderConstants` to the shaders, see [`ConstantsMode`].
+pub const CONSTANTS_MODE: ConstantsMode = ConstantsMode::PushConstants;
+
+/// Whether to allocate a depth buffer and enable depth testing. Our triangle doesn't need it.
+pub const DEPTH_TEST: bool = false;
+
+/// The MSAA sample count, one of 1 (disabled), 2, 4 or 8.
+pub const MSAA_SAMPLES: u32 = 1;
+
+/// The minimum fraction of samples to shade individually with MSAA, `Some(1.)` shades every sample. `None` shades
+/// once per pixel. See [`render_pipeline::MyRenderPipelineManager::set_sample_shading`].
+pub const SAMPLE_SHADING: Option<f32> = None;
+
+/// Whether to count the samples passing the fragment tests with occlusion queries and log them once per second, see
+/// [`MyRenderer::set_occlusion_query`].
+pub const OCCLUSION_QUERY: bool = false;
+
+/// The initial present mode, toggled at runtime by pressing `V`. Falls back to vsync if unsupported.
+pub const PRESENT_MODE: PresentMode = PresentMode::Mailbox;
+
+/// The number of swapchain images to request, `None` for one more than the minimum of the surface. Clamped to the
+/// range the surface supports, see [`MySwapchainManager::image_count`] for the actual count.
+pub const SWAPCHAIN_IMAGE_COUNT: Option<u32> = None;
+
+/// The preferred surface format, falls back to an 8-bit sRGB format if `None` or unsupported.
+pub const SURFACE_FORMAT: Option<vk::Format> = None;
+
+/// Whether to draw a quad from a vertex and index buffer, instead of the vertexless triangle.
+pub const DRAW_QUAD: bool = false;
+
+/// How many instances of the vertexless triangle to draw, arranged in a grid.
+pub const INSTANCE_COUNT: u32 = 1;
+
+pub fn main() -> anyhow::Result<()> {
+    run(AppConfig::from_args("Rust GPU - ash"))
+}
+
+/// Like [`main`], but with a custom title, size or icon for the window
+pub fn run(config: AppConfig) -> anyhow::Result<()> {
+    env_logger::init();
+    if let Some(path) = &config.capture {
+        return capture(&config, path);
+    }
+    let event_loop = EventLoop::new()?;
+    let mut app = App::new(config);
+    event_loop.run_app(&mut app)?;
+    app.error.map_or(Ok(()), Err)
+}
+
+/// Render a single frame of the size in `config` without a window and save it to `path`
+pub fn capture(config: &AppConfig, path: &Path) -> anyhow::Result<()> {
+    let device = MyDevice::new_headless(config.validation, &config.device)?;
+    let attachments = AttachmentConfig {
+        depth: DEPTH_TEST,
+        samples: device.sample_count(MSAA_SAMPLES)?,
+    };
+    let mut renderer = MyRenderer::new(
+        device.clone(),
+        vk::Format::R8G8B8A8_SRGB,
+        1,
+        attachments,
+        CONSTANTS_MODE,
+    )?;
+    if let Some(shader) = &config.shader {
+        renderer
+            .pipeline
+            .set_shader_code(get_shaders_from_path(shader)?);
+    }
+    renderer.pipeline.set_sample_shading(SAMPLE_SHADING);
+    if DRAW_QUAD {
+        renderer.set_mesh(MyMesh::new(&device, &QUAD_VERTICES, &QUAD_INDICES)?)?;
+    }
+    let extent = vk::Extent2D {
+        width: config.size.width,
+        height: config.size.height,
+    };
+    let image = renderer.capture_frame(
+        extent,
+        &ShaderConstants {
+            width: extent.width,
+            height: extent.height,
+            instance_count: INSTANCE_COUNT,
+            ..Zeroable::zeroed()
+        },
+    )?;
+    image.save(path)?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+pub struct App {
+    config: AppConfig,
+    limit: RunLimit,
+    state: Option<State>,
+    /// the error that made the app exit, returned by [`run`] to report it with the exit code of `main`
+    error: Option<anyhow::Error>,
+}
+
+impl App {
+    pub fn new(config: AppConfig) -> Self {
+        Self {
+            limit: RunLimit::new(&config),
+            config,
+            state: None,
+            error: None,
+        }
+    }
+}
+
+impl App {
+    /// Tear down the lost device and everything created from it, then recreate it all for the same window. Exits if
+    /// that fails, e.g. because the GPU is gone for good.
+    fn recover_device_lost(&mut self, event_loop: &ActiveEventLoop) {
+        log::error!("Device lost, recreating the device, swapchain and renderer");
+        let window = self.state.take().unwrap().window;
+        match State::with_window(window, &self.config) {
+            Ok(state) => self.state = Some(state),
+            Err(e) => {
+                log::error!("Failed to recreate the device after it was lost, exiting");
+                self.error = Some(e);
+                event_loop.exit();
+            }
+        }
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        match &mut self.state {
+            None => match State::new(event_loop, &self.config) {
+                Ok(state) => self.state = Some(state),
+                Err(e) => {
+                    self.error = Some(e);
+                    event_loop.exit();
+                }
+            },
+            // the device, renderer and pipelines survive suspension, only the surface has to be recreated
+            Some(state) => {
+                if let Err(e) = state.swapchain.resume() {
+                    self.error.get_or_insert(e.into());
+                    event_loop.exit();
+                }
+            }
+        }
+    }
+
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        // the cached framebuffers reference the image views of the swapchain
+        if let Some(state) = &mut self.state
+            && let Err(e) = state
+                .renderer
+                .pipeline
+                .clear_framebuffers()
+                .and_then(|()| state.swapchain.suspend())
+        {
+            self.error.get_or_insert(e.into());
+            event_loop.exit();
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        // gone if creating it failed and the app is exiting
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        let redraw = matches!(event, WindowEvent::RedrawRequested);
+        match state.window_event(event_loop, id, event) {
+            Ok(()) => (),
+            Err(e) if matches!(e.downcast_ref(), Some(RendererError::DeviceLost)) => {
+                self.recover_device_lost(event_loop);
+                return;
+            }
+            Err(e) => {
+                // keep the first error, events may still arrive until the event loop exits
+                self.error.get_or_insert(e);
+                event_loop.exit();
+                return;
+            }
+        }
+        if redraw {
+            self.limit.frame_rendered();
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.limit.reached(Instant::now()) {
+            event_loop.exit();
+            return;
+        }
+        // continuously redraw
+        if let Some(state) = &self.state {
+            state.window.request_redraw();
+        }
+    }
+}
+
+struct State {
+    start: Instant,
+    clock: ShaderClock,
+    mouse: MouseState,
+    camera: CameraState,
+    fullscreen: FullscreenState,
+    present_mode: PresentMode,
+    /// index into [`SHADER_MODULES`]
+    shader_module: usize,
+    window: Arc<Window>,
+    renderer: MyRenderer,
+    swapchain: MySwapchainManager,
+    #[cfg(feature = "hot-reload")]
+    shader_watcher: shader_watcher::ShaderWatcher,
+}
+
+impl State {
+    fn new(event_loop: &ActiveEventLoop, config: &AppConfig) -> anyhow::Result<Self> {
+        let window = Arc::new(event_loop.create_window(config.window_attributes()?)?);
+        Self::with_window(window, config)
+    }
+
+    /// Create the device, swapchain and renderer for an existing `window`, which must not have a swapchain anymore
+    fn with_window(window: Arc<Window>, config: &AppConfig) -> anyhow::Result<Self> {
+        let extensions =
+            ash_window::enumerate_required_extensions(window.display_handle()?.as_raw())?;
+        let device = MyDevice::new(extensions, config.validation, &config.device)?;
+        let attachments = AttachmentConfig {
+            depth: DEPTH_TEST,
+            samples: device.sample_count(MSAA_SAMPLES)?,
+        };
+        let swapchain = MySwapchainManager::new(
+            device.clone(),
+            window.clone(),
+            FRAMES_IN_FLIGHT,
+            attachments,
+            config.present_mode.unwrap_or(PRESENT_MODE),
+            SURFACE_FORMAT,
+            SWAPCHAIN_IMAGE_COUNT,
+        )?;
+        log::info!("Output color space {:?}", swapchain.color_space());
+        let mut renderer = MyRenderer::new(
+            device.clone(),
+            swapchain.surface_format.format,
+            FRAMES_IN_FLIGHT,
+            attachments,
+            CONSTANTS_MODE,
+        )?;
+        renderer.set_throttle(THROTTLE);
+        renderer.set_occlusion_query(OCCLUSION_QUERY)?;
+        if let Some(shader) = &config.shader {
+            renderer
+                .pipeline
+                .set_shader_code(get_shaders_from_path(shader)?);
+        }
+        renderer.pipeline.set_sample_shading(SAMPLE_SHADING);
+        if DRAW_QUAD {
+            renderer.set_mesh(MyMesh::new(&device, &QUAD_VERTICES, &QUAD_INDICES)?)?;
+        }
+        Ok(Self {
+            start: Instant::now(),
+            clock: ShaderClock::default(),
+            mouse: MouseState::default(),
+            camera: CameraState::default(),
+            fullscreen: FullscreenState::default(),
+            present_mode: config.present_mode.unwrap_or(PRESENT_MODE),
+            shader_module: 0,
+            window,
+            swapchain,
+            renderer,
+            #[cfg(feature = "hot-reload")]
+            shader_watcher: shader_watcher::ShaderWatcher::new(SHADER_MODULES[0].path)?,
+        })
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _id: WindowId,
+        event: WindowEvent,
+    ) -> anyhow::Result<()> {
+        match event {
+            WindowEvent::RedrawRequested => {
+                #[cfg(feature = "hot-reload")]
+                if let Some(shader_code) = self.shader_watcher.poll() {
+                    self.renderer.pipeline.set_shader_code(shader_code);
+                }
+                let color_space = self.swapchain.color_space();
+                let real_time = self.start.elapsed().as_secs_f32();
+                let result = self.swapchain.render(|frame| {
+                    let extend = frame.extent;
+                    let mut shader_constants = ShaderConstants {
+                        width: extend.width,
+                        height: extend.height,
+                        time: self.clock.update(real_time),
+                        instance_count: INSTANCE_COUNT,
+                        ..Zeroable::zeroed()
+                    };
+                    self.mouse.update_constants(&mut shader_constants);
+                    self.camera.update(real_time);
+                    self.camera.update_constants(&mut shader_constants);
+                    color_space.update_constants(&mut shader_constants);
+                    self.renderer.render_frame(frame, &shader_constants)
+                });
+                match result {
+                    // skip this frame, the swapchain is recreated on the next one
+                    Err(RendererError::SwapchainOutOfDate) => (),
+                    result => result?,
+                }
+                if let Some(gpu_time) = self.renderer.take_gpu_time() {
+                    self.swapchain.frame_stats().record_gpu_time(gpu_time);
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Escape),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            }
+            | WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if key.eq_ignore_ascii_case("v") => {
+                self.present_mode = self.present_mode.next();
+                self.swapchain.set_present_mode(self.present_mode);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if key.eq_ignore_ascii_case("t") => {
+                let throttle = self.renderer.throttle().next();
+                log::info!("Selected throttle mode {throttle:?}");
+                self.renderer.set_throttle(throttle);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if key.eq_ignore_ascii_case("s") => {
+                self.shader_module = (self.shader_module + 1) % SHADER_MODULES.len();
+                let module = &SHADER_MODULES[self.shader_module];
+                log::info!("Selected shader module {}", module.name);
+                self.renderer.pipeline.set_shader_code(shader_code(module)?);
+                #[cfg(feature = "hot-reload")]
+                {
+                    self.shader_watcher = shader_watcher::ShaderWatcher::new(module.path)?;
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if key.eq_ignore_ascii_case("w") => {
+                let pipeline = &mut self.renderer.pipeline;
+                pipeline.set_wireframe(!pipeline.wireframe());
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if key.eq_ignore_ascii_case("l") => {
+                let frame_pacing = !self.swapchain.frame_pacing();
+                self.swapchain.set_frame_pacing(frame_pacing);
+            }
+            WindowEvent::Resized(_) => self.swapchain.resized(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::F11),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => self.fullscreen.toggle(&self.window),
+            WindowEvent::KeyboardInput { .. } if self.clock.window_event(&event) => (),
+            WindowEvent::KeyboardInput { .. } => self.camera.window_event(&event),
+            WindowEvent::CursorMoved { .. } | WindowEvent::MouseInput { .. } => {
+                self.mouse.window_event(&event, self.clock.accumulated);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+pub fn get_shaders() -> anyhow::Result<Vec<u32>> {
+    // set in the build script
+    const SPV_BYTES: &[u8] = include_bytes!(env!("SHADER_SPV_PATH"));
+    Ok(read_spv(&mut std::io::Cursor::new(SPV_BYTES))?)
+}
+
+/// Reads the SPIR-V module at `path` at runtime instead of using the one embedded by [`get_shaders`], e.g. for
+/// [`render_pipeline::MyRenderPipelineManager::set_shader_code`]
+pub fn get_shaders_from_path(path: &Path) -> anyhow::Result<Vec<u32>> {
+    Ok(read_spv(&mut std::io::Cursor::new(read_spirv_file(path)?))?)
+}
+
+/// Decodes `module` for [`render_pipeline::MyRenderPipelineManager::set_shader_code`]
+pub fn shader_code(module: &ShaderModule) -> anyhow::Result<Vec<u32>> {
+    Ok(read_spv(&mut std::io::Cursor::new(module.spv))?)
+}
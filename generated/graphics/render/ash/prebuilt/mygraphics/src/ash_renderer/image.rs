@@ -0,0 +1,113 @@
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use ash::vk;
+use gpu_allocator::MemoryLocation;
+use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc, AllocationScheme};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// A 2D image with a single mip level, together with its memory and an image view covering the entire image
+pub struct MyImage {
+    pub image: vk::Image,
+    pub image_view: vk::ImageView,
+    pub allocation: Allocation,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    pub name: String,
+    destroyed: bool,
+}
+
+#[derive(Clone)]
+pub struct ImageCreateInfo<'a> {
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    pub usage: vk::ImageUsageFlags,
+    pub aspect: vk::ImageAspectFlags,
+    pub samples: vk::SampleCountFlags,
+    pub name: Option<Cow<'a, str>>,
+}
+
+impl MyImage {
+    pub fn new(device: &Arc<MyDevice>, info: ImageCreateInfo<'_>) -> Result<Self, RendererError> {
+        unsafe {
+            let image = device.create_image(
+                &vk::ImageCreateInfo::default()
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .format(info.format)
+                    .extent(info.extent.into())
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .samples(info.samples)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(info.usage)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED),
+                None,
+            )?;
+            let name = info.name.map(|a| a.into_owned()).unwrap_or_default();
+            let allocation = device.borrow_allocator().allocate(&AllocationCreateDesc {
+                name: &name,
+                requirements: device.get_image_memory_requirements(image),
+                location: MemoryLocation::GpuOnly,
+                linear: false,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+            })?;
+            device.bind_image_memory(image, allocation.memory(), allocation.offset())?;
+            let image_view = device.create_image_view(
+                &vk::ImageViewCreateInfo::default()
+                    .image(image)
+                    .view_type(vk::ImageViewType::TYPE_2D)
+                    .format(info.format)
+                    .subresource_range(subresource_range(info.aspect)),
+                None,
+            )?;
+            device.set_object_name(image, &name);
+            device.set_object_name(image_view, &name);
+            Ok(Self {
+                image,
+                image_view,
+                allocation,
+                format: info.format,
+                extent: info.extent,
+                name,
+                destroyed: false,
+            })
+        }
+    }
+
+    /// Destroy this image
+    ///
+    /// # Safety
+    /// Image must not be in use
+    pub unsafe fn destroy(&mut self, device: &Arc<MyDevice>) {
+        if !self.destroyed {
+            self.destroyed = true;
+            unsafe {
+                device.destroy_image_view(self.image_view, None);
+                device.destroy_image(self.image, None);
+            }
+            device
+                .borrow_allocator()
+                .free(std::mem::take(&mut self.allocation))
+                .ok();
+        }
+    }
+}
+
+impl Drop for MyImage {
+    fn drop(&mut self) {
+        if !self.destroyed {
+            panic!("dropping Image {} without destroying it", &self.name);
+        }
+    }
+}
+
+/// The subresource range of an entire [`MyImage`]
+pub fn subresource_range(aspect: vk::ImageAspectFlags) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange::default()
+        .aspect_mask(aspect)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+}
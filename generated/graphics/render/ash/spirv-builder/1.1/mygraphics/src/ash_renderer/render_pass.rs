@@ -0,0 +1,175 @@
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use crate::ash_renderer::render_pipeline::RenderAttachments;
+use crate::ash_renderer::swapchain::{AttachmentConfig, DEPTH_FORMAT};
+use ash::vk;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The views and size of [`RenderAttachments`] a framebuffer was created for
+type FramebufferKey = (
+    vk::Extent2D,
+    vk::ImageView,
+    Option<vk::ImageView>,
+    Option<vk::ImageView>,
+);
+
+/// The [`vk::RenderPass`] pipelines are created for if the device doesn't support
+/// [`MyDevice::dynamic_rendering`], and the [`vk::Framebuffer`]s to draw into with it
+///
+/// A framebuffer binds a fixed set of image views, so one is created and cached per set of [`RenderAttachments`]. When
+/// drawing to a swapchain, that's one per swapchain image, which stay valid until the swapchain is recreated and must
+/// be cleared with [`Self::clear_framebuffers`].
+pub struct RenderPassManager {
+    device: Arc<MyDevice>,
+    pub render_pass: vk::RenderPass,
+    framebuffers: HashMap<FramebufferKey, vk::Framebuffer>,
+}
+
+impl RenderPassManager {
+    pub fn new(
+        device: Arc<MyDevice>,
+        color_format: vk::Format,
+        attachments: AttachmentConfig,
+    ) -> Result<Self, RendererError> {
+        let render_pass = unsafe { create_render_pass(&device, color_format, attachments)? };
+        Ok(Self {
+            device,
+            render_pass,
+            framebuffers: HashMap::new(),
+        })
+    }
+
+    /// The framebuffer of `out`, created the first time it is drawn into
+    pub fn framebuffer(
+        &mut self,
+        out: &RenderAttachments,
+    ) -> Result<vk::Framebuffer, RendererError> {
+        let key = (out.extent, out.color, out.depth, out.msaa_color);
+        if let Some(framebuffer) = self.framebuffers.get(&key) {
+            return Ok(*framebuffer);
+        }
+        // in the order of the attachments of `create_render_pass`
+        let views = out
+            .msaa_color
+            .into_iter()
+            .chain([out.color])
+            .chain(out.depth)
+            .collect::<Vec<_>>();
+        let framebuffer = unsafe {
+            self.device.create_framebuffer(
+                &vk::FramebufferCreateInfo::default()
+                    .render_pass(self.render_pass)
+                    .attachments(&views)
+                    .width(out.extent.width)
+                    .height(out.extent.height)
+                    .layers(1),
+                None,
+            )?
+        };
+        log::debug!(
+            "Created framebuffer {} of {:?}",
+            self.framebuffers.len(),
+            out.extent
+        );
+        self.framebuffers.insert(key, framebuffer);
+        Ok(framebuffer)
+    }
+
+    /// Destroys all framebuffers, which must be called before any of their image views are destroyed or reused, e.g.
+    /// when the swapchain is recreated
+    pub fn clear_framebuffers(&mut self) -> Result<(), RendererError> {
+        if self.framebuffers.is_empty() {
+            return Ok(());
+        }
+        unsafe {
+            // like pipelines, figuring out when the framebuffers stop being used is hard, so we take this shortcut
+            self.device.device_wait_idle()?;
+            for (_, framebuffer) in self.framebuffers.drain() {
+                self.device.destroy_framebuffer(framebuffer, None);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RenderPassManager {
+    fn drop(&mut self) {
+        self.clear_framebuffers().ok();
+        unsafe {
+            self.device.destroy_render_pass(self.render_pass, None);
+        }
+    }
+}
+
+/// Creates a render pass with a single subpass, which loads and stores the attachments like
+/// [`MyRenderPipeline::render`](crate::ash_renderer::render_pipeline::MyRenderPipeline::render) does with dynamic
+/// rendering. The attachments are the multisampled color image if `attachments` uses MSAA, the color
+/// image it is resolved into and the depth image if enabled, in that order.
+unsafe fn create_render_pass(
+    device: &MyDevice,
+    color_format: vk::Format,
+    attachments: AttachmentConfig,
+) -> Result<vk::RenderPass, RendererError> {
+    let color = vk::AttachmentDescription::default()
+        .format(color_format)
+        .samples(vk::SampleCountFlags::TYPE_1)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::STORE)
+        .initial_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+    let mut descriptions = if attachments.msaa() {
+        vec![
+            color
+                .samples(attachments.samples)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE),
+            color.load_op(vk::AttachmentLoadOp::DONT_CARE),
+        ]
+    } else {
+        vec![color]
+    };
+    let depth_ref = vk::AttachmentReference {
+        attachment: descriptions.len() as u32,
+        layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+    };
+    if attachments.depth {
+        descriptions.push(
+            vk::AttachmentDescription::default()
+                .format(DEPTH_FORMAT)
+                .samples(attachments.samples)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
+        );
+    }
+    let color_refs = [vk::AttachmentReference {
+        attachment: 0,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    }];
+    let resolve_refs = [vk::AttachmentReference {
+        attachment: 1,
+        layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    }];
+    let mut subpass = vk::SubpassDescription::default()
+        .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+        .color_attachments(&color_refs);
+    if attachments.msaa() {
+        subpass = subpass.resolve_attachments(&resolve_refs);
+    }
+    if attachments.depth {
+        subpass = subpass.depth_stencil_attachment(&depth_ref);
+    }
+    unsafe {
+        let render_pass = device.create_render_pass(
+            &vk::RenderPassCreateInfo::default()
+                .attachments(&descriptions)
+                .subpasses(&[subpass]),
+            None,
+        )?;
+        device.set_object_name(render_pass, "render pass");
+        Ok(render_pass)
+    }
+}
@@ -0,0 +1,1079 @@
+use crate::ash_renderer::buffer::{BufferCreateInfo, MyBuffer};
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use crate::ash_renderer::frame_command_pools::FrameCommandPools;
+use crate::ash_renderer::get_shaders;
+use crate::ash_renderer::global_descriptor_set::{GlobalDescriptorSet, GlobalDescriptorSetLayout};
+#[cfg(feature = "gpu-vertices")]
+use crate::ash_renderer::gpu_vertices::GpuVertices;
+use crate::ash_renderer::image::{ImageCreateInfo, MyImage, subresource_range};
+use crate::ash_renderer::mesh::MyMesh;
+use crate::ash_renderer::render_pipeline::{
+    ConstantsMode, MyRenderPipeline, MyRenderPipelineManager, PrimitiveConfig, RenderAttachments,
+    ShaderInputs,
+};
+use crate::ash_renderer::swapchain::{AttachmentConfig, DrawFrame};
+#[cfg(feature = "texture")]
+use crate::ash_renderer::texture::MyTexture;
+use crate::util::Renderer;
+use anyhow::{Context, anyhow};
+use ash::vk;
+use bytemuck::Zeroable;
+use gpu_allocator::MemoryLocation;
+use image::RgbaImage;
+use mygraphics_shaders::{ShaderConstants, UniformConstants};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The renderer manages our command buffers and submits the commands, using [`MyRenderPipeline`] for drawing.
+///
+/// Every frame in flight has its own [`FrameData`] and command pool in [`FrameCommandPools`], which are indexed by
+/// [`DrawFrame::frame_index`].
+pub struct MyRenderer {
+    pub device: Arc<MyDevice>,
+    pub global_descriptor_set_layout: Arc<GlobalDescriptorSetLayout>,
+    pub pipeline: MyRenderPipelineManager,
+    frames: Vec<FrameData>,
+    commands: FrameCommandPools,
+    clear_color: [f32; 4],
+    mesh: MyMesh,
+    gpu_timer: Option<GpuTimer>,
+    gpu_time: Option<Duration>,
+    occlusion: Option<OcclusionCounter>,
+    throttle: ThrottleMode,
+    #[cfg(feature = "texture")]
+    texture: MyTexture,
+    /// one storage buffer per [`TINTS`], bound to the bindless array if the layout has one
+    #[cfg(feature = "bindless")]
+    tints: Vec<MyBuffer>,
+    /// drawn instead of `mesh`, with its vertices generated by a compute shader every frame
+    #[cfg(feature = "gpu-vertices")]
+    gpu_vertices: GpuVertices,
+}
+
+/// The colors `main_fs_bindless` multiplies the output with, cycling through them once per second
+const TINTS: [[f32; 4]; 4] = [
+    [1., 1., 1., 1.],
+    [1., 0.4, 0.4, 1.],
+    [0.4, 1., 0.4, 1.],
+    [0.4, 0.4, 1., 1.],
+];
+
+/// The element of the bindless array to tint the output with at the time of `shader_constants`
+fn tint_index(shader_constants: &ShaderConstants) -> u32 {
+    shader_constants.time.max(0.) as u32 % TINTS.len() as u32
+}
+
+/// How long [`MyRenderer::render_frame`] blocks after submitting a frame, to experiment with CPU and GPU overlap
+///
+/// Independent of this, the swapchain waits for the fence of a frame slot before reusing it, so the CPU is never more
+/// than `frames_in_flight` frames ahead of the GPU.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ThrottleMode {
+    /// Wait for the device to be idle after every frame, the most conservative and slowest option
+    WaitIdle,
+    /// Wait for the fence of the submitted frame, so the CPU never records while the GPU draws
+    FenceOnly,
+    /// Don't wait at all and let the frames in flight overlap with the CPU
+    #[default]
+    None,
+}
+
+impl ThrottleMode {
+    /// The mode to switch to when toggling at runtime
+    pub fn next(self) -> Self {
+        match self {
+            Self::WaitIdle => Self::FenceOnly,
+            Self::FenceOnly => Self::None,
+            Self::None => Self::WaitIdle,
+        }
+    }
+}
+
+/// An image owned by the caller to draw into with [`MyRenderer::render_to`] instead of a swapchain image, e.g. to
+/// composite the output into a larger application
+///
+/// The image must have been created with [`vk::ImageUsageFlags::COLOR_ATTACHMENT`], its previous contents are
+/// discarded.
+#[derive(Copy, Clone, Debug)]
+pub struct RenderTarget {
+    pub image: vk::Image,
+    /// the view of `image` to draw into
+    pub image_view: vk::ImageView,
+    pub extent: vk::Extent2D,
+    /// the format of `image`, which must match the `out_format` of [`MyRenderer::new`]
+    pub format: vk::Format,
+    /// the layout `image` is left in after drawing, e.g. [`vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL`] to sample it
+    pub final_layout: vk::ImageLayout,
+}
+
+/// Resources used by a single frame in flight
+///
+/// We used to allocate a new `ShaderConstants` buffer and descriptor set every frame and destroy them once the frame
+/// finished. That churns through allocations for no reason, so instead every frame slot owns a persistently mapped
+/// buffer and a descriptor set pointing to it, which are created once and updated in place. We need one per frame in
+/// flight, as the GPU may still be reading the constants of the previous frame while we write the next ones. With
+/// [`ConstantsMode::PushConstants`], the constants are recorded into the command buffer and we don't need a buffer.
+/// With [`ConstantsMode::UniformBuffer`], the buffer holds [`UniformConstants`] instead of [`ShaderConstants`].
+struct FrameData {
+    shader_constants: Option<MyBuffer>,
+    constants: ConstantsMode,
+    descriptor_set: GlobalDescriptorSet,
+    /// whether the [`GpuTimer`] queries of this frame contain the timestamps of the last submission
+    timestamps_written: bool,
+    /// whether the [`OcclusionCounter`] query of this frame contains the samples of the last submission
+    occlusion_written: bool,
+}
+
+impl FrameData {
+    fn new(
+        device: &Arc<MyDevice>,
+        global_descriptor_set_layout: &Arc<GlobalDescriptorSetLayout>,
+        constants: ConstantsMode,
+        frame_index: usize,
+    ) -> Result<Self, RendererError> {
+        unsafe {
+            let shader_constants = match constants {
+                ConstantsMode::StorageBuffer => Some(MyBuffer::from_data(
+                    device,
+                    BufferCreateInfo {
+                        usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+                        location: MemoryLocation::CpuToGpu,
+                        name: Some(Cow::from(format!("frame {frame_index} ShaderConstants"))),
+                    },
+                    &ShaderConstants::zeroed(),
+                )?),
+                ConstantsMode::UniformBuffer => Some(MyBuffer::from_data(
+                    device,
+                    BufferCreateInfo {
+                        usage: vk::BufferUsageFlags::UNIFORM_BUFFER,
+                        location: MemoryLocation::CpuToGpu,
+                        name: Some(Cow::from(format!("frame {frame_index} UniformConstants"))),
+                    },
+                    &UniformConstants::zeroed(),
+                )?),
+                ConstantsMode::PushConstants => None,
+            };
+            let descriptor_set = GlobalDescriptorSet::new(
+                global_descriptor_set_layout,
+                shader_constants.as_ref().map(|buffer| buffer.buffer),
+            )?;
+            device.set_object_name(
+                descriptor_set.set,
+                &format!("frame {frame_index} descriptor set"),
+            );
+            Ok(Self {
+                shader_constants,
+                constants,
+                descriptor_set,
+                timestamps_written: false,
+                occlusion_written: false,
+            })
+        }
+    }
+
+    /// Writes `shader_constants` into the buffer of this frame, if it has one
+    fn write_constants(&mut self, shader_constants: &ShaderConstants) -> Result<(), RendererError> {
+        match (&mut self.shader_constants, self.constants) {
+            (Some(buffer), ConstantsMode::UniformBuffer) => {
+                buffer.write_data(&UniformConstants::from(*shader_constants))
+            }
+            (Some(buffer), _) => buffer.write_data(shader_constants),
+            (None, _) => Ok(()),
+        }
+    }
+
+    /// # Safety
+    /// The resources of this frame must not be in use by the GPU
+    unsafe fn destroy(&mut self, device: &Arc<MyDevice>) {
+        unsafe {
+            self.descriptor_set.destroy();
+            if let Some(shader_constants) = &mut self.shader_constants {
+                shader_constants.destroy(device);
+            }
+        }
+    }
+}
+
+impl MyRenderer {
+    /// Passing a `frames_in_flight` of 1 makes the CPU wait for the GPU to finish the previous frame before recording
+    /// the next one. It must match the `frames_in_flight` of the swapchain providing the [`DrawFrame`]s. With multiple
+    /// swapchains, pass the sum of theirs and give each its own range of [`DrawFrame::frame_index`], see
+    /// [`multi_window`](crate::ash_renderer::multi_window).
+    pub fn new(
+        device: Arc<MyDevice>,
+        out_format: vk::Format,
+        frames_in_flight: usize,
+        attachments: AttachmentConfig,
+        constants: ConstantsMode,
+    ) -> Result<Self, RendererError> {
+        let global_descriptor_set_layout =
+            GlobalDescriptorSetLayout::new(device.clone(), constants.descriptor_type())?;
+        let pipeline = MyRenderPipelineManager::new(
+            device.clone(),
+            global_descriptor_set_layout.clone(),
+            out_format,
+            attachments,
+            constants,
+            PrimitiveConfig::default(),
+            get_shaders()?,
+        )?;
+        let frames = (0..frames_in_flight)
+            .map(|frame_index| {
+                FrameData::new(
+                    &device,
+                    &global_descriptor_set_layout,
+                    constants,
+                    frame_index,
+                )
+            })
+            .collect::<Result<Vec<_>, RendererError>>()?;
+        #[cfg(feature = "texture")]
+        let texture = {
+            let texture = MyTexture::new(&device, &crate::util::load_texture()?, "texture")?;
+            for frame in &frames {
+                unsafe { frame.descriptor_set.write_texture(&texture) };
+            }
+            texture
+        };
+        #[cfg(feature = "bindless")]
+        let tints = if global_descriptor_set_layout.bindless {
+            let tints = TINTS
+                .iter()
+                .enumerate()
+                .map(|(i, tint)| {
+                    MyBuffer::from_data(
+                        &device,
+                        BufferCreateInfo {
+                            usage: vk::BufferUsageFlags::STORAGE_BUFFER,
+                            location: MemoryLocation::CpuToGpu,
+                            name: Some(Cow::from(format!("tint {i}"))),
+                        },
+                        tint,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for frame in &frames {
+                for (i, tint) in tints.iter().enumerate() {
+                    unsafe { frame.descriptor_set.write_bindless(i as u32, tint.buffer) };
+                }
+            }
+            tints
+        } else {
+            Vec::new()
+        };
+        #[cfg(feature = "gpu-vertices")]
+        let gpu_vertices = GpuVertices::new(device.clone())?;
+        let commands = FrameCommandPools::new(&device, frames_in_flight)?;
+        let gpu_timer = unsafe { GpuTimer::new(&device, frames_in_flight)? };
+        let mut this = Self {
+            device,
+            global_descriptor_set_layout,
+            pipeline,
+            frames,
+            commands,
+            clear_color: [0., 0., 0., 1.],
+            mesh: MyMesh::vertexless_triangle(),
+            gpu_timer,
+            gpu_time: None,
+            occlusion: None,
+            throttle: ThrottleMode::default(),
+            #[cfg(feature = "texture")]
+            texture,
+            #[cfg(feature = "bindless")]
+            tints,
+            #[cfg(feature = "gpu-vertices")]
+            gpu_vertices,
+        };
+        this.pipeline
+            .set_vertex_input(this.drawn_mesh().vertex_input());
+        Ok(this)
+    }
+
+    /// The mesh to draw, which is [`GpuVertices::mesh`] with the `gpu-vertices` feature
+    fn drawn_mesh(&self) -> &MyMesh {
+        #[cfg(feature = "gpu-vertices")]
+        return &self.gpu_vertices.mesh;
+        #[cfg(not(feature = "gpu-vertices"))]
+        &self.mesh
+    }
+
+    /// Draw `mesh` instead of the current one, which defaults to [`MyMesh::vertexless_triangle`]. With the
+    /// `gpu-vertices` feature, the mesh generated by the compute shader is drawn instead.
+    pub fn set_mesh(&mut self, mesh: MyMesh) -> Result<(), RendererError> {
+        unsafe {
+            // the previous mesh may still be in use by frames in flight
+            self.device.device_wait_idle()?;
+            std::mem::replace(&mut self.mesh, mesh).destroy(&self.device);
+        }
+        self.pipeline
+            .set_vertex_input(self.drawn_mesh().vertex_input());
+        Ok(())
+    }
+
+    /// Set the color the image is cleared to before drawing, in linear RGBA. Defaults to black.
+    #[inline]
+    pub fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        self.clear_color = clear_color;
+    }
+
+    /// Set how long [`Self::render_frame`] blocks after submitting, defaults to [`ThrottleMode::None`]
+    #[inline]
+    pub fn set_throttle(&mut self, throttle: ThrottleMode) {
+        self.throttle = throttle;
+    }
+
+    #[inline]
+    pub fn throttle(&self) -> ThrottleMode {
+        self.throttle
+    }
+
+    /// Enable or disable counting the samples passing the fragment tests with an occlusion query around every frame,
+    /// logged once per second. Disabled by default.
+    pub fn set_occlusion_query(&mut self, enabled: bool) -> Result<(), RendererError> {
+        match (enabled, &self.occlusion) {
+            (true, None) => {
+                let occlusion = unsafe { OcclusionCounter::new(&self.device, self.frames.len())? };
+                // the queries of the new pool have no results yet
+                for frame in &mut self.frames {
+                    frame.occlusion_written = false;
+                }
+                self.occlusion = Some(occlusion);
+            }
+            (false, Some(occlusion)) => unsafe {
+                // the queries may still be in use by frames in flight
+                self.device.device_wait_idle()?;
+                occlusion.destroy(&self.device);
+                self.occlusion = None;
+            },
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// The GPU time of the most recently finished frame, if it wasn't taken yet and the device supports timestamps
+    #[inline]
+    pub fn take_gpu_time(&mut self) -> Option<Duration> {
+        self.gpu_time.take()
+    }
+
+    pub fn render_frame(
+        &mut self,
+        frame: DrawFrame,
+        shader_constants: &ShaderConstants,
+    ) -> Result<(), RendererError> {
+        unsafe {
+            let device = self.device.clone();
+            if frame.swapchain_recreated {
+                self.pipeline.clear_framebuffers()?;
+            }
+            let frame_data = &mut self.frames[frame.frame_index];
+
+            // the swapchain waited for the fence of this frame slot, so the previous frame's resources are unused
+            frame_data.write_constants(shader_constants)?;
+            if let Some(gpu_timer) = &self.gpu_timer
+                && frame_data.timestamps_written
+            {
+                // this also means the timestamps of the previous frame are available without stalling
+                self.gpu_time = Some(gpu_timer.read(&device, frame.frame_index)?);
+            }
+            if let Some(occlusion) = &mut self.occlusion
+                && frame_data.occlusion_written
+            {
+                occlusion.read(&device, frame.frame_index)?;
+            }
+
+            let cmd = self.commands.begin(frame.frame_index)?;
+            {
+                profile_span!("record");
+                if let Some(gpu_timer) = &self.gpu_timer {
+                    gpu_timer.cmd_begin(&device, cmd, frame.frame_index);
+                }
+                if let Some(occlusion) = &self.occlusion {
+                    occlusion.cmd_begin(&device, cmd, frame.frame_index);
+                }
+                self.record_draw(
+                    cmd,
+                    frame.frame_index,
+                    shader_constants,
+                    &DrawTarget {
+                        extent: frame.extent,
+                        color: (frame.image, frame.image_view),
+                        depth: frame.depth_image,
+                        msaa: frame.msaa_image,
+                        framebuffer: None,
+                    },
+                )?;
+                let frame_data = &mut self.frames[frame.frame_index];
+                if let Some(occlusion) = &self.occlusion {
+                    occlusion.cmd_end(&device, cmd, frame.frame_index);
+                    frame_data.occlusion_written = true;
+                }
+                if let Some(gpu_timer) = &self.gpu_timer {
+                    gpu_timer.cmd_end(&device, cmd, frame.frame_index);
+                    frame_data.timestamps_written = true;
+                }
+                cmd_color_barrier(
+                    &device,
+                    cmd,
+                    frame.image,
+                    vk::ImageLayout::PRESENT_SRC_KHR,
+                    vk::PipelineStageFlags2::ALL_COMMANDS,
+                    vk::AccessFlags2::NONE,
+                );
+                device.end_command_buffer(cmd)?;
+            }
+
+            profile_span!("submit");
+            device.queue_submit2(
+                device.main_queue,
+                &[vk::SubmitInfo2::default()
+                    .wait_semaphore_infos(&[vk::SemaphoreSubmitInfo::default()
+                        .semaphore(frame.acquire_semaphore)
+                        .stage_mask(vk::PipelineStageFlags2::TOP_OF_PIPE)])
+                    .command_buffer_infos(&[
+                        vk::CommandBufferSubmitInfo::default().command_buffer(cmd)
+                    ])
+                    .signal_semaphore_infos(&[vk::SemaphoreSubmitInfo::default()
+                        .semaphore(frame.present_semaphore)
+                        .stage_mask(vk::PipelineStageFlags2::BOTTOM_OF_PIPE)])],
+                frame.draw_finished_fence,
+            )?;
+            match self.throttle {
+                ThrottleMode::WaitIdle => device.device_wait_idle()?,
+                ThrottleMode::FenceOnly => {
+                    device.wait_for_fences(&[frame.draw_finished_fence], true, !0)?;
+                }
+                ThrottleMode::None => (),
+            }
+            Ok(())
+        }
+    }
+
+    /// Renders a single frame into `target`, an image owned by the caller instead of the swapchain, and waits for it
+    /// to finish
+    ///
+    /// The depth and MSAA attachments are created for every call and the device has to be idle, so this is meant for
+    /// occasionally rendering to a texture, not for every frame.
+    pub fn render_to(
+        &mut self,
+        target: &RenderTarget,
+        shader_constants: &ShaderConstants,
+    ) -> Result<(), RendererError> {
+        let format = self.pipeline.color_out_format();
+        if target.format != format {
+            return Err(anyhow!(
+                "Render target has format {:?}, but the renderer draws {format:?}",
+                target.format
+            )
+            .into());
+        }
+        unsafe {
+            let device = self.device.clone();
+            let (mut depth_image, mut msaa_image) =
+                self.pipeline
+                    .attachments()
+                    .create_images(&device, format, target.extent)?;
+
+            let result = (|| {
+                // reuse the resources of the first frame slot, which requires all frames in flight to have finished
+                device.device_wait_idle()?;
+                // framebuffers cached for views that were destroyed since may share their handles with ours
+                self.pipeline.clear_framebuffers()?;
+                self.frames[0].write_constants(shader_constants)?;
+                let cmd = self.commands.begin(0)?;
+                self.record_draw(
+                    cmd,
+                    0,
+                    shader_constants,
+                    &DrawTarget {
+                        extent: target.extent,
+                        color: (target.image, target.image_view),
+                        depth: depth_image.as_ref().map(|i| (i.image, i.image_view)),
+                        msaa: msaa_image.as_ref().map(|i| (i.image, i.image_view)),
+                        framebuffer: None,
+                    },
+                )?;
+                cmd_color_barrier(
+                    &device,
+                    cmd,
+                    target.image,
+                    target.final_layout,
+                    vk::PipelineStageFlags2::ALL_COMMANDS,
+                    vk::AccessFlags2::MEMORY_READ | vk::AccessFlags2::MEMORY_WRITE,
+                );
+                device.end_command_buffer(cmd)?;
+                device.queue_submit2(
+                    device.main_queue,
+                    &[vk::SubmitInfo2::default().command_buffer_infos(&[
+                        vk::CommandBufferSubmitInfo::default().command_buffer(cmd),
+                    ])],
+                    vk::Fence::null(),
+                )?;
+                device.queue_wait_idle(device.main_queue)?;
+                Ok(())
+            })();
+
+            device.device_wait_idle().ok();
+            // the cached framebuffers must not outlive the views of the transient images
+            self.pipeline.clear_framebuffers().ok();
+            for image in [&mut depth_image, &mut msaa_image].into_iter().flatten() {
+                image.destroy(&device);
+            }
+            result
+        }
+    }
+
+    /// Renders a single frame of `extent` into an offscreen image and reads it back to the CPU
+    ///
+    /// This waits for the device to be idle, so it's meant for screenshots and tests, not for every frame.
+    pub fn capture_frame(
+        &mut self,
+        extent: vk::Extent2D,
+        shader_constants: &ShaderConstants,
+    ) -> Result<RgbaImage, RendererError> {
+        unsafe {
+            let device = self.device.clone();
+            let format = self.pipeline.color_out_format();
+            let (mut depth_image, mut msaa_image) = self
+                .pipeline
+                .attachments()
+                .create_images(&device, format, extent)?;
+            let mut color_image = MyImage::new(
+                &device,
+                ImageCreateInfo {
+                    format,
+                    extent,
+                    usage: vk::ImageUsageFlags::COLOR_ATTACHMENT
+                        | vk::ImageUsageFlags::TRANSFER_SRC,
+                    aspect: vk::ImageAspectFlags::COLOR,
+                    samples: vk::SampleCountFlags::TYPE_1,
+                    name: Some("capture color".into()),
+                },
+            )?;
+            let bytes_per_pixel = capture_bytes_per_pixel(format)?;
+            let mut readback = MyBuffer::new(
+                &device,
+                BufferCreateInfo {
+                    usage: vk::BufferUsageFlags::TRANSFER_DST,
+                    location: MemoryLocation::GpuToCpu,
+                    name: Some(Cow::from("capture readback")),
+                },
+                u64::from(extent.width) * u64::from(extent.height) * bytes_per_pixel as u64,
+            )?;
+
+            let result = (|| {
+                // reuse the resources of the first frame slot, which requires all frames in flight to have finished
+                device.device_wait_idle()?;
+                // framebuffers cached for views that were destroyed since may share their handles with ours
+                self.pipeline.clear_framebuffers()?;
+                self.frames[0].write_constants(shader_constants)?;
+                let cmd = self.commands.begin(0)?;
+                self.record_draw(
+                    cmd,
+                    0,
+                    shader_constants,
+                    &DrawTarget {
+                        extent,
+                        color: (color_image.image, color_image.image_view),
+                        depth: depth_image.as_ref().map(|i| (i.image, i.image_view)),
+                        msaa: msaa_image.as_ref().map(|i| (i.image, i.image_view)),
+                        framebuffer: None,
+                    },
+                )?;
+                cmd_color_barrier(
+                    &device,
+                    cmd,
+                    color_image.image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::PipelineStageFlags2::COPY,
+                    vk::AccessFlags2::TRANSFER_READ,
+                );
+                device.cmd_copy_image_to_buffer(
+                    cmd,
+                    color_image.image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    readback.buffer,
+                    &[vk::BufferImageCopy::default()
+                        .image_subresource(
+                            vk::ImageSubresourceLayers::default()
+                                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                                .layer_count(1),
+                        )
+                        .image_extent(extent.into())],
+                );
+                device.cmd_pipeline_barrier2(
+                    cmd,
+                    &vk::DependencyInfo::default().buffer_memory_barriers(&[
+                        vk::BufferMemoryBarrier2::default()
+                            .buffer(readback.buffer)
+                            .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                            .src_stage_mask(vk::PipelineStageFlags2::COPY)
+                            .dst_access_mask(vk::AccessFlags2::HOST_READ)
+                            .dst_stage_mask(vk::PipelineStageFlags2::HOST)
+                            .size(vk::WHOLE_SIZE),
+                    ]),
+                );
+                device.end_command_buffer(cmd)?;
+
+                device.queue_submit2(
+                    device.main_queue,
+                    &[vk::SubmitInfo2::default().command_buffer_infos(&[
+                        vk::CommandBufferSubmitInfo::default().command_buffer(cmd),
+                    ])],
+                    vk::Fence::null(),
+                )?;
+                device.queue_wait_idle(device.main_queue)?;
+                // The viewport is flipped to make y point up, so the first row of the image is already the top row
+                // and doesn't need to be flipped.
+                decode_capture(format, extent, readback.read_bytes()?)
+            })();
+
+            device.device_wait_idle().ok();
+            // the cached framebuffers must not outlive the views of the transient images
+            self.pipeline.clear_framebuffers().ok();
+            readback.destroy(&device);
+            color_image.destroy(&device);
+            for image in [&mut depth_image, &mut msaa_image].into_iter().flatten() {
+                image.destroy(&device);
+            }
+            result
+        }
+    }
+
+    /// Records drawing a frame with the resources of `frame_index` into `target`, see [`cmd_draw`]. Shared by all ways
+    /// of rendering, which only differ in where `target` comes from and what happens with it afterwards.
+    ///
+    /// # Safety
+    /// `cmd` must be recording and the constants of `frame_index` must have been written
+    unsafe fn record_draw(
+        &mut self,
+        cmd: vk::CommandBuffer,
+        frame_index: usize,
+        shader_constants: &ShaderConstants,
+        target: &DrawTarget,
+    ) -> Result<(), RendererError> {
+        unsafe {
+            let target = DrawTarget {
+                framebuffer: self.pipeline.framebuffer(&target.attachments())?,
+                ..*target
+            };
+            let pipeline = self.pipeline.get_pipeline()?;
+            #[cfg(feature = "gpu-vertices")]
+            let mesh = &self.gpu_vertices.mesh;
+            #[cfg(not(feature = "gpu-vertices"))]
+            let mesh = &self.mesh;
+            #[cfg(feature = "gpu-vertices")]
+            self.gpu_vertices.cmd_dispatch(cmd, shader_constants);
+            cmd_draw(
+                &self.device,
+                cmd,
+                pipeline,
+                ShaderInputs {
+                    descriptor_set: &self.frames[frame_index].descriptor_set,
+                    constants: shader_constants,
+                    tint_index: tint_index(shader_constants),
+                },
+                self.clear_color,
+                mesh,
+                &target,
+            )
+        }
+    }
+}
+
+/// Measures the GPU time of drawing a frame with a pair of timestamp queries per frame in flight
+struct GpuTimer {
+    pool: vk::QueryPool,
+    /// nanoseconds per timestamp tick
+    period: f64,
+    /// the bits of a timestamp that are valid, the others are undefined
+    mask: u64,
+}
+
+impl GpuTimer {
+    /// Returns `None` with a warning if the main queue doesn't support timestamps
+    unsafe fn new(
+        device: &MyDevice,
+        frames_in_flight: usize,
+    ) -> Result<Option<Self>, RendererError> {
+        unsafe {
+            let valid_bits = device
+                .instance
+                .get_physical_device_queue_family_properties(device.physical_device)
+                [device.main_queue_family as usize]
+                .timestamp_valid_bits;
+            if valid_bits == 0 {
+                log::warn!("Main queue doesn't support timestamps, GPU time is not measured");
+                return Ok(None);
+            }
+            let period = device
+                .instance
+                .get_physical_device_properties(device.physical_device)
+                .limits
+                .timestamp_period;
+            let pool = device.create_query_pool(
+                &vk::QueryPoolCreateInfo::default()
+                    .query_type(vk::QueryType::TIMESTAMP)
+                    .query_count(2 * frames_in_flight as u32),
+                None,
+            )?;
+            device.set_object_name(pool, "gpu timer");
+            Ok(Some(Self {
+                pool,
+                period: f64::from(period),
+                mask: u64::MAX >> (64 - valid_bits),
+            }))
+        }
+    }
+
+    unsafe fn cmd_begin(&self, device: &MyDevice, cmd: vk::CommandBuffer, frame_index: usize) {
+        unsafe {
+            let first = 2 * frame_index as u32;
+            device.cmd_reset_query_pool(cmd, self.pool, first, 2);
+            device.cmd_write_timestamp2(
+                cmd,
+                vk::PipelineStageFlags2::TOP_OF_PIPE,
+                self.pool,
+                first,
+            );
+        }
+    }
+
+    unsafe fn cmd_end(&self, device: &MyDevice, cmd: vk::CommandBuffer, frame_index: usize) {
+        unsafe {
+            let last = 2 * frame_index as u32 + 1;
+            device.cmd_write_timestamp2(
+                cmd,
+                vk::PipelineStageFlags2::BOTTOM_OF_PIPE,
+                self.pool,
+                last,
+            );
+        }
+    }
+
+    /// # Safety
+    /// The submission writing the timestamps of `frame_index` must have finished
+    unsafe fn read(
+        &self,
+        device: &MyDevice,
+        frame_index: usize,
+    ) -> Result<Duration, RendererError> {
+        unsafe {
+            let mut timestamps = [0u64; 2];
+            device.get_query_pool_results(
+                self.pool,
+                2 * frame_index as u32,
+                &mut timestamps,
+                vk::QueryResultFlags::TYPE_64,
+            )?;
+            let ticks = timestamps[1].wrapping_sub(timestamps[0]) & self.mask;
+            Ok(Duration::from_nanos((ticks as f64 * self.period) as u64))
+        }
+    }
+
+    unsafe fn destroy(&self, device: &MyDevice) {
+        unsafe { device.destroy_query_pool(self.pool, None) }
+    }
+}
+
+/// Counts the samples passing the fragment tests with an occlusion query per frame in flight, and logs their average
+/// per frame once per second
+///
+/// The query is active for the whole frame, it must begin and end outside of the render pass instance.
+struct OcclusionCounter {
+    pool: vk::QueryPool,
+    /// without `occlusionQueryPrecise`, the result is only guaranteed to be non-zero if any sample passed
+    flags: vk::QueryControlFlags,
+    samples: u64,
+    frames: u64,
+    last_log: Instant,
+}
+
+impl OcclusionCounter {
+    unsafe fn new(device: &MyDevice, frames_in_flight: usize) -> Result<Self, RendererError> {
+        unsafe {
+            let flags = if device.occlusion_query_precise {
+                vk::QueryControlFlags::PRECISE
+            } else {
+                log::warn!(
+                    "Device doesn't support `occlusionQueryPrecise`, occlusion queries only report whether any \
+                     samples passed"
+                );
+                vk::QueryControlFlags::empty()
+            };
+            let pool = device.create_query_pool(
+                &vk::QueryPoolCreateInfo::default()
+                    .query_type(vk::QueryType::OCCLUSION)
+                    .query_count(frames_in_flight as u32),
+                None,
+            )?;
+            device.set_object_name(pool, "occlusion counter");
+            Ok(Self {
+                pool,
+                flags,
+                samples: 0,
+                frames: 0,
+                last_log: Instant::now(),
+            })
+        }
+    }
+
+    unsafe fn cmd_begin(&self, device: &MyDevice, cmd: vk::CommandBuffer, frame_index: usize) {
+        unsafe {
+            let query = frame_index as u32;
+            device.cmd_reset_query_pool(cmd, self.pool, query, 1);
+            device.cmd_begin_query(cmd, self.pool, query, self.flags);
+        }
+    }
+
+    unsafe fn cmd_end(&self, device: &MyDevice, cmd: vk::CommandBuffer, frame_index: usize) {
+        unsafe { device.cmd_end_query(cmd, self.pool, frame_index as u32) }
+    }
+
+    /// Adds the samples of `frame_index` to the average and logs it if a second passed since the last time
+    ///
+    /// # Safety
+    /// The submission writing the query of `frame_index` must have finished
+    unsafe fn read(&mut self, device: &MyDevice, frame_index: usize) -> Result<(), RendererError> {
+        unsafe {
+            let mut samples = [0u64];
+            device.get_query_pool_results(
+                self.pool,
+                frame_index as u32,
+                &mut samples,
+                vk::QueryResultFlags::TYPE_64,
+            )?;
+            self.samples += samples[0];
+            self.frames += 1;
+            if self.last_log.elapsed() >= Duration::from_secs(1) {
+                log::info!(
+                    "Occlusion query: {} samples passed per frame",
+                    self.samples / self.frames
+                );
+                self.samples = 0;
+                self.frames = 0;
+                self.last_log = Instant::now();
+            }
+            Ok(())
+        }
+    }
+
+    unsafe fn destroy(&self, device: &MyDevice) {
+        unsafe { device.destroy_query_pool(self.pool, None) }
+    }
+}
+
+/// The images to draw into, their previous contents are discarded
+#[derive(Copy, Clone)]
+struct DrawTarget {
+    extent: vk::Extent2D,
+    color: (vk::Image, vk::ImageView),
+    depth: Option<(vk::Image, vk::ImageView)>,
+    msaa: Option<(vk::Image, vk::ImageView)>,
+    /// set by [`MyRenderer::record_draw`] if the pipeline draws with a render pass
+    framebuffer: Option<vk::Framebuffer>,
+}
+
+impl DrawTarget {
+    fn attachments(&self) -> RenderAttachments {
+        RenderAttachments {
+            extent: self.extent,
+            color: self.color.1,
+            depth: self.depth.map(|(_, view)| view),
+            msaa_color: self.msaa.map(|(_, view)| view),
+            framebuffer: self.framebuffer,
+        }
+    }
+}
+
+/// Transitions the color image of a drawn frame from [`vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL`] to `new_layout`,
+/// making the writes available to `dst_stage` and `dst_access`
+unsafe fn cmd_color_barrier(
+    device: &MyDevice,
+    cmd: vk::CommandBuffer,
+    image: vk::Image,
+    new_layout: vk::ImageLayout,
+    dst_stage: vk::PipelineStageFlags2,
+    dst_access: vk::AccessFlags2,
+) {
+    unsafe {
+        device.cmd_pipeline_barrier2(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&[
+                vk::ImageMemoryBarrier2::default()
+                    .image(image)
+                    .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                    .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                    .old_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .dst_access_mask(dst_access)
+                    .dst_stage_mask(dst_stage)
+                    .new_layout(new_layout)
+                    .subresource_range(subresource_range(vk::ImageAspectFlags::COLOR)),
+            ]),
+        );
+    }
+}
+
+/// Records drawing into `target`, leaving its color image in [`vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL`]
+unsafe fn cmd_draw(
+    device: &MyDevice,
+    cmd: vk::CommandBuffer,
+    pipeline: &MyRenderPipeline,
+    inputs: ShaderInputs<'_>,
+    clear_color: [f32; 4],
+    mesh: &MyMesh,
+    target: &DrawTarget,
+) -> Result<(), RendererError> {
+    unsafe {
+        let _label = device.debug_label(cmd, "main draw");
+        let mut barriers = vec![
+            vk::ImageMemoryBarrier2::default()
+                .image(target.color.0)
+                .src_access_mask(vk::AccessFlags2::NONE)
+                .src_stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .dst_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .subresource_range(subresource_range(vk::ImageAspectFlags::COLOR)),
+        ];
+        if let Some((depth_image, _)) = target.depth {
+            // The depth image is shared by all frames in flight, so we must wait for the previous frame to
+            // finish writing it. Its previous content is irrelevant, as we clear it anyway.
+            // `DEPTH_ATTACHMENT_OPTIMAL` would require Vulkan 1.2, so we use the combined depth stencil layout.
+            barriers.push(
+                vk::ImageMemoryBarrier2::default()
+                    .image(depth_image)
+                    .src_access_mask(vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE)
+                    .src_stage_mask(vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .dst_access_mask(
+                        vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ
+                            | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                    )
+                    .dst_stage_mask(
+                        vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                            | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+                    )
+                    .new_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                    .subresource_range(subresource_range(vk::ImageAspectFlags::DEPTH)),
+            );
+        }
+        if let Some((msaa_image, _)) = target.msaa {
+            // like the depth image, the msaa image is shared by all frames in flight
+            barriers.push(
+                vk::ImageMemoryBarrier2::default()
+                    .image(msaa_image)
+                    .src_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                    .src_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .dst_access_mask(vk::AccessFlags2::COLOR_ATTACHMENT_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT)
+                    .new_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                    .subresource_range(subresource_range(vk::ImageAspectFlags::COLOR)),
+            );
+        }
+        device.cmd_pipeline_barrier2(
+            cmd,
+            &vk::DependencyInfo::default().image_memory_barriers(&barriers),
+        );
+        pipeline.render(device, cmd, target.attachments(), inputs, clear_color, mesh)
+    }
+}
+
+/// The size of a pixel of the swapchain formats we may capture
+fn capture_bytes_per_pixel(format: vk::Format) -> Result<usize, RendererError> {
+    Ok(match format {
+        vk::Format::R8G8B8A8_SRGB
+        | vk::Format::R8G8B8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::A8B8G8R8_SRGB_PACK32
+        | vk::Format::A8B8G8R8_UNORM_PACK32 => 4,
+        vk::Format::R8G8B8_SRGB
+        | vk::Format::R8G8B8_UNORM
+        | vk::Format::B8G8R8_SRGB
+        | vk::Format::B8G8R8_UNORM => 3,
+        _ => return Err(anyhow!("Capturing images of format {format:?} is not supported").into()),
+    })
+}
+
+/// Convert tightly packed pixels of `format` to RGBA
+fn decode_capture(
+    format: vk::Format,
+    extent: vk::Extent2D,
+    bytes: &[u8],
+) -> Result<RgbaImage, RendererError> {
+    let bytes_per_pixel = capture_bytes_per_pixel(format)?;
+    // the swapchain prefers BGRA on most platforms, while `RgbaImage` is always RGBA
+    let bgr = matches!(
+        format,
+        vk::Format::B8G8R8A8_SRGB
+            | vk::Format::B8G8R8A8_UNORM
+            | vk::Format::B8G8R8_SRGB
+            | vk::Format::B8G8R8_UNORM
+    );
+    let pixel_count = extent.width as usize * extent.height as usize;
+    let mut rgba = Vec::with_capacity(pixel_count * 4);
+    for pixel in bytes[..pixel_count * bytes_per_pixel].chunks_exact(bytes_per_pixel) {
+        let [r, g, b] = if bgr {
+            [pixel[2], pixel[1], pixel[0]]
+        } else {
+            [pixel[0], pixel[1], pixel[2]]
+        };
+        // swapchain images are opaque, alpha is meaningless
+        rgba.extend_from_slice(&[r, g, b, u8::MAX]);
+    }
+    Ok(RgbaImage::from_raw(extent.width, extent.height, rgba)
+        .context("capture has an unexpected size")?)
+}
+
+impl Renderer for MyRenderer {
+    type Target<'a> = DrawFrame;
+
+    fn render(
+        &mut self,
+        shader_constants: &ShaderConstants,
+        target: DrawFrame,
+    ) -> anyhow::Result<()> {
+        Ok(self.render_frame(target, shader_constants)?)
+    }
+
+    fn capture(&mut self, shader_constants: &ShaderConstants) -> anyhow::Result<RgbaImage> {
+        let extent = vk::Extent2D {
+            width: shader_constants.width,
+            height: shader_constants.height,
+        };
+        Ok(self.capture_frame(extent, shader_constants)?)
+    }
+
+    fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        MyRenderer::set_clear_color(self, clear_color);
+    }
+}
+
+impl Drop for MyRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.device_wait_idle().ok();
+            for frame in &mut self.frames {
+                frame.destroy(&self.device);
+            }
+            self.mesh.destroy(&self.device);
+            if let Some(gpu_timer) = &self.gpu_timer {
+                gpu_timer.destroy(&self.device);
+            }
+            if let Some(occlusion) = &self.occlusion {
+                occlusion.destroy(&self.device);
+            }
+            #[cfg(feature = "texture")]
+            self.texture.destroy(&self.device);
+            #[cfg(feature = "bindless")]
+            for tint in &mut self.tints {
+                tint.destroy(&self.device);
+            }
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Renders frames offscreen in a tight loop, without a window or surface, and prints their min/avg/max frame time
+//!
+//! The number of measured frames can be passed as the first argument.
+
+use ash::vk;
+use bytemuck::Zeroable;
+use mygraphics::ash_renderer::device::MyDevice;
+use mygraphics::ash_renderer::render_pipeline::ConstantsMode;
+use mygraphics::ash_renderer::renderer::MyRenderer;
+use mygraphics::ash_renderer::swapchain::AttachmentConfig;
+use mygraphics::util::{DevicePreference, bench, enable_debug_layer};
+use mygraphics_shaders::ShaderConstants;
+
+const DEFAULT_FRAMES: u32 = 100;
+
+pub fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let frames = match std::env::args().nth(1) {
+        Some(frames) => frames.parse()?,
+        None => DEFAULT_FRAMES,
+    };
+    let device = MyDevice::new_headless(enable_debug_layer(), &DevicePreference::from_env())?;
+    let mut renderer = MyRenderer::new(
+        device,
+        vk::Format::R8G8B8A8_SRGB,
+        1,
+        AttachmentConfig::default(),
+        ConstantsMode::default(),
+    )?;
+
+    let (width, height) = (1280, 720);
+    let shader_constants = ShaderConstants {
+        width,
+        height,
+        ..Zeroable::zeroed()
+    };
+    println!("{}", bench(&mut renderer, &shader_constants, frames)?);
+    Ok(())
+}
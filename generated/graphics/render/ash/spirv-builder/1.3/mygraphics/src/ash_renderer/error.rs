@@ -0,0 +1,127 @@
+use crate::util::{EXIT_DEVICE, EXIT_FAILURE, EXIT_SHADER, EXIT_SWAPCHAIN};
+use ash::vk;
+use gpu_allocator::AllocationError;
+
+/// The error returned by the device, swapchain, pipeline and renderer
+///
+/// Unlike an [`anyhow::Error`], it can be matched on to recover from some errors, like recreating the swapchain after
+/// [`RendererError::SwapchainOutOfDate`] or everything after [`RendererError::DeviceLost`]. It converts into an
+/// [`anyhow::Error`] with `?`, so code that only reports errors can keep using [`anyhow::Result`].
+#[derive(Debug, thiserror::Error)]
+pub enum RendererError {
+    /// Creating the instance or device failed, e.g. because there is no Vulkan driver or no suitable GPU
+    #[error("Failed to create the device")]
+    DeviceCreation(#[source] Box<RendererError>),
+    /// The swapchain no longer matches the surface and could not be recreated, e.g. while the window is resized
+    /// continuously. Rendering the next frame recreates it again.
+    #[error("Swapchain is out of date")]
+    SwapchainOutOfDate,
+    /// The surface is no longer usable, e.g. because its window was destroyed. It must be recreated along with the
+    /// swapchain.
+    #[error("Surface lost")]
+    SurfaceLost,
+    /// The device was lost, e.g. due to a driver crash or GPU timeout. It must be recreated along with everything
+    /// created from it.
+    #[error("Device lost")]
+    DeviceLost,
+    /// Running out of host or device memory, or any other allocator failure
+    #[error("Allocation failed: {0}")]
+    AllocationFailed(#[from] AllocationError),
+    /// Creating a pipeline failed, e.g. because of a shader the driver couldn't compile
+    #[error("Failed to create pipeline: {0}")]
+    PipelineCreation(vk::Result),
+    /// Any other Vulkan error
+    #[error("Vulkan error: {0}")]
+    Vulkan(vk::Result),
+    /// Everything else, like invalid arguments or errors of the other modules
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl RendererError {
+    /// The process exit code reporting this error, see [`exit_code`]
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::DeviceCreation(_) | Self::DeviceLost => EXIT_DEVICE,
+            Self::SwapchainOutOfDate | Self::SurfaceLost => EXIT_SWAPCHAIN,
+            Self::PipelineCreation(_) => EXIT_SHADER,
+            Self::AllocationFailed(_) | Self::Vulkan(_) | Self::Other(_) => EXIT_FAILURE,
+        }
+    }
+}
+
+/// The process exit code reporting `error`, for [`exit_with`](crate::util::exit_with). Picks the code of the outermost
+/// [`RendererError`] in its chain of causes, or [`EXIT_FAILURE`] if there is none.
+pub fn exit_code(error: &anyhow::Error) -> u8 {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<RendererError>())
+        .map_or(EXIT_FAILURE, RendererError::exit_code)
+}
+
+impl From<vk::Result> for RendererError {
+    fn from(result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_OUT_OF_DATE_KHR => Self::SwapchainOutOfDate,
+            vk::Result::ERROR_SURFACE_LOST_KHR => Self::SurfaceLost,
+            vk::Result::ERROR_DEVICE_LOST => Self::DeviceLost,
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY | vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => {
+                Self::AllocationFailed(AllocationError::OutOfMemory)
+            }
+            result => Self::Vulkan(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn from_vk_result() {
+        assert!(matches!(
+            RendererError::from(vk::Result::ERROR_OUT_OF_DATE_KHR),
+            RendererError::SwapchainOutOfDate
+        ));
+        assert!(matches!(
+            RendererError::from(vk::Result::ERROR_DEVICE_LOST),
+            RendererError::DeviceLost
+        ));
+        assert!(matches!(
+            RendererError::from(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY),
+            RendererError::AllocationFailed(AllocationError::OutOfMemory)
+        ));
+        assert!(matches!(
+            RendererError::from(vk::Result::ERROR_INITIALIZATION_FAILED),
+            RendererError::Vulkan(vk::Result::ERROR_INITIALIZATION_FAILED)
+        ));
+
+        // survives the round trip through anyhow, for callers that want to recover
+        let err = anyhow::Error::from(RendererError::from(vk::Result::ERROR_DEVICE_LOST));
+        assert!(matches!(
+            err.downcast_ref::<RendererError>(),
+            Some(RendererError::DeviceLost)
+        ));
+    }
+
+    #[test]
+    pub fn exit_codes() {
+        let creation =
+            RendererError::DeviceCreation(Box::new(vk::Result::ERROR_INCOMPATIBLE_DRIVER.into()));
+        assert_eq!(exit_code(&creation.into()), EXIT_DEVICE);
+        assert_eq!(
+            exit_code(&RendererError::SurfaceLost.into()),
+            EXIT_SWAPCHAIN
+        );
+        let pipeline = RendererError::PipelineCreation(vk::Result::ERROR_UNKNOWN);
+        assert_eq!(exit_code(&pipeline.into()), EXIT_SHADER);
+        assert_eq!(
+            exit_code(&anyhow::anyhow!("no renderer error")),
+            EXIT_FAILURE
+        );
+
+        // found behind context added on the way up to `main`
+        let err = anyhow::Error::from(RendererError::DeviceLost).context("Failed to render");
+        assert_eq!(exit_code(&err), EXIT_DEVICE);
+    }
+}
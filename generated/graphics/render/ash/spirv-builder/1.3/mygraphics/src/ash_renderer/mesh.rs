@@ -0,0 +1,195 @@
+use crate::ash_renderer::buffer::{BufferCreateInfo, MyBuffer};
+use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use ash::vk;
+use gpu_allocator::MemoryLocation;
+use mygraphics_shaders::{DrawIndirectCommand, Vertex};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// What [`MyRenderer`](crate::ash_renderer::renderer::MyRenderer) draws: either a vertex and index buffer, drawn using
+/// the `main_vs_mesh` vertex shader, or the vertexless triangle of `main_vs` without any buffers
+#[derive(Default)]
+pub struct MyMesh {
+    pub buffers: Option<MeshBuffers>,
+    /// draws the vertexless triangle with the commands in this buffer instead of a single direct draw, ignored if
+    /// [`Self::buffers`] is set
+    pub indirect: Option<IndirectDraw>,
+}
+
+pub struct MeshBuffers {
+    pub vertices: MyBuffer,
+    pub indices: MyBuffer,
+    pub index_count: u32,
+}
+
+/// A buffer of [`DrawIndirectCommand`]s the GPU reads the vertex and instance counts of its draws from, which may
+/// also be written by a compute shader
+///
+/// Drawing more than one command with a single call requires the `multiDrawIndirect` feature. Without it, see
+/// [`MyDevice::multi_draw_indirect`], each command is recorded as its own indirect draw.
+pub struct IndirectDraw {
+    pub commands: MyBuffer,
+    pub draw_count: u32,
+}
+
+impl IndirectDraw {
+    /// Upload `commands` from the CPU into an indirect buffer
+    pub fn new(
+        device: &Arc<MyDevice>,
+        commands: &[DrawIndirectCommand],
+    ) -> Result<Self, RendererError> {
+        let buffer = MyBuffer::from_slice_staged(
+            device,
+            BufferCreateInfo {
+                usage: vk::BufferUsageFlags::INDIRECT_BUFFER,
+                location: MemoryLocation::GpuOnly,
+                name: Some(Cow::from("indirect commands")),
+            },
+            commands,
+        )?;
+        Ok(Self {
+            commands: buffer,
+            draw_count: commands.len() as u32,
+        })
+    }
+
+    /// Record the indirect draws of all commands
+    ///
+    /// # Safety
+    /// `cmd` must be recording within a render pass, with a pipeline bound
+    pub unsafe fn cmd_draw(&self, device: &MyDevice, cmd: vk::CommandBuffer) {
+        const STRIDE: u32 = size_of::<DrawIndirectCommand>() as u32;
+        unsafe {
+            let buffer = self.commands.buffer;
+            if device.multi_draw_indirect || self.draw_count <= 1 {
+                device.cmd_draw_indirect(cmd, buffer, 0, self.draw_count, STRIDE);
+            } else {
+                for i in 0..self.draw_count {
+                    device.cmd_draw_indirect(cmd, buffer, u64::from(i * STRIDE), 1, STRIDE);
+                }
+            }
+        }
+    }
+}
+
+impl MyMesh {
+    /// The layout of [`Vertex`] in the vertex buffer, matching the inputs of `main_vs_mesh`
+    pub const VERTEX_BINDINGS: [vk::VertexInputBindingDescription; 1] =
+        [vk::VertexInputBindingDescription {
+            binding: 0,
+            stride: size_of::<Vertex>() as u32,
+            input_rate: vk::VertexInputRate::VERTEX,
+        }];
+
+    pub const VERTEX_ATTRIBUTES: [vk::VertexInputAttributeDescription; 2] = [
+        vk::VertexInputAttributeDescription {
+            location: 0,
+            binding: 0,
+            format: vk::Format::R32G32_SFLOAT,
+            offset: std::mem::offset_of!(Vertex, position) as u32,
+        },
+        vk::VertexInputAttributeDescription {
+            location: 1,
+            binding: 0,
+            format: vk::Format::R32G32B32_SFLOAT,
+            offset: std::mem::offset_of!(Vertex, color) as u32,
+        },
+    ];
+
+    /// The triangle `main_vs` generates from the vertex index, the default
+    #[inline]
+    pub fn vertexless_triangle() -> Self {
+        Self::default()
+    }
+
+    /// The vertexless triangle, drawn with `commands` read from an indirect buffer
+    pub fn vertexless_indirect(
+        device: &Arc<MyDevice>,
+        commands: &[DrawIndirectCommand],
+    ) -> Result<Self, RendererError> {
+        Ok(Self {
+            buffers: None,
+            indirect: Some(IndirectDraw::new(device, commands)?),
+        })
+    }
+
+    /// Upload `vertices` and `indices` into a vertex and index buffer
+    pub fn new(
+        device: &Arc<MyDevice>,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) -> Result<Self, RendererError> {
+        let vertex_buffer = MyBuffer::from_slice_staged(
+            device,
+            BufferCreateInfo {
+                usage: vk::BufferUsageFlags::VERTEX_BUFFER,
+                location: MemoryLocation::GpuOnly,
+                name: Some(Cow::from("vertices")),
+            },
+            vertices,
+        )?;
+        let index_buffer = MyBuffer::from_slice_staged(
+            device,
+            BufferCreateInfo {
+                usage: vk::BufferUsageFlags::INDEX_BUFFER,
+                location: MemoryLocation::GpuOnly,
+                name: Some(Cow::from("indices")),
+            },
+            indices,
+        )?;
+        Ok(Self {
+            buffers: Some(MeshBuffers {
+                vertices: vertex_buffer,
+                indices: index_buffer,
+                index_count: indices.len() as u32,
+            }),
+            indirect: None,
+        })
+    }
+
+    /// Whether this mesh is drawn from vertex buffers, which the pipeline must be created for
+    #[inline]
+    pub fn vertex_input(&self) -> bool {
+        self.buffers.is_some()
+    }
+
+    /// Bind the buffers of this mesh, if any, and record the draw. The vertexless triangle is drawn with
+    /// `instance_count` instances, unless it's drawn indirectly.
+    ///
+    /// # Safety
+    /// `cmd` must be recording within a render pass, with a pipeline bound that matches [`Self::vertex_input`]
+    pub unsafe fn cmd_draw(&self, device: &MyDevice, cmd: vk::CommandBuffer, instance_count: u32) {
+        unsafe {
+            match &self.buffers {
+                Some(buffers) => {
+                    device.cmd_bind_vertex_buffers(cmd, 0, &[buffers.vertices.buffer], &[0]);
+                    let index_buffer = buffers.indices.buffer;
+                    device.cmd_bind_index_buffer(cmd, index_buffer, 0, vk::IndexType::UINT32);
+                    device.cmd_draw_indexed(cmd, buffers.index_count, 1, 0, 0, 0);
+                }
+                None => match &self.indirect {
+                    Some(indirect) => indirect.cmd_draw(device, cmd),
+                    // the vertexless triangle generates its vertices from the vertex index
+                    None => device.cmd_draw(cmd, 3, instance_count, 0, 0),
+                },
+            }
+        }
+    }
+
+    /// Destroy this mesh
+    ///
+    /// # Safety
+    /// Mesh must not be in use
+    pub unsafe fn destroy(&mut self, device: &Arc<MyDevice>) {
+        if let Some(buffers) = &mut self.buffers {
+            unsafe {
+                buffers.vertices.destroy(device);
+                buffers.indices.destroy(device);
+            }
+        }
+        if let Some(indirect) = &mut self.indirect {
+            unsafe { indirect.commands.destroy(device) };
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod cpu_renderer;
+pub mod util;
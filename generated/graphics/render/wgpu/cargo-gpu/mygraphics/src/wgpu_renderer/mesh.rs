@@ -0,0 +1,48 @@
+use mygraphics_shaders::Vertex;
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+use wgpu::{
+    Buffer, BufferAddress, BufferUsages, Device, IndexFormat, RenderPass, VertexAttribute,
+    VertexBufferLayout, VertexStepMode, vertex_attr_array,
+};
+
+/// A vertex and index buffer, drawn using the `main_vs_mesh` vertex shader instead of the vertexless triangle
+#[derive(Debug, Clone)]
+pub struct MyMesh {
+    pub vertices: Buffer,
+    pub indices: Buffer,
+    pub index_count: u32,
+}
+
+impl MyMesh {
+    const VERTEX_ATTRIBUTES: [VertexAttribute; 2] =
+        vertex_attr_array![0 => Float32x2, 1 => Float32x3];
+
+    /// The layout of [`Vertex`] in the vertex buffer, matching the inputs of `main_vs_mesh`
+    pub const VERTEX_LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+        array_stride: size_of::<Vertex>() as BufferAddress,
+        step_mode: VertexStepMode::Vertex,
+        attributes: &Self::VERTEX_ATTRIBUTES,
+    };
+
+    pub fn new(device: &Device, vertices: &[Vertex], indices: &[u32]) -> Self {
+        Self {
+            vertices: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("vertices"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: BufferUsages::VERTEX,
+            }),
+            indices: device.create_buffer_init(&BufferInitDescriptor {
+                label: Some("indices"),
+                contents: bytemuck::cast_slice(indices),
+                usage: BufferUsages::INDEX,
+            }),
+            index_count: indices.len() as u32,
+        }
+    }
+
+    pub fn draw(&self, rpass: &mut RenderPass<'_>) {
+        rpass.set_vertex_buffer(0, self.vertices.slice(..));
+        rpass.set_index_buffer(self.indices.slice(..), IndexFormat::Uint32);
+        rpass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+}
@@ -0,0 +1,40 @@
+//! Renders frames offscreen in a tight loop, without a window or surface, and prints their min/avg/max frame time
+//!
+//! The number of measured frames can be passed as the first argument.
+
+use bytemuck::Zeroable;
+use mygraphics::util::{DevicePreference, bench};
+use mygraphics::wgpu_renderer::renderer::{MyRenderer, validate_sample_count};
+use mygraphics::wgpu_renderer::{MSAA_SAMPLES, request_device, select_adapter};
+use mygraphics_shaders::ShaderConstants;
+use pollster::block_on;
+
+const DEFAULT_FRAMES: u32 = 100;
+
+pub fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let frames = match std::env::args().nth(1) {
+        Some(frames) => frames.parse()?,
+        None => DEFAULT_FRAMES,
+    };
+    let instance =
+        wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+    let adapter = block_on(select_adapter(
+        &instance,
+        None,
+        &DevicePreference::from_env(),
+    ))?;
+    let (device, queue) = block_on(request_device(&adapter))?;
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let sample_count = validate_sample_count(&adapter, &device, format, MSAA_SAMPLES)?;
+    let mut renderer = MyRenderer::new(device, queue, format, sample_count)?;
+
+    let (width, height) = (1280, 720);
+    let shader_constants = ShaderConstants {
+        width,
+        height,
+        ..Zeroable::zeroed()
+    };
+    println!("{}", bench(&mut renderer, &shader_constants, frames)?);
+    Ok(())
+}
@@ -0,0 +1,37 @@
+//! Renders a single frame without a window or surface and writes it to `out.png`
+
+use bytemuck::Zeroable;
+use mygraphics::util::DevicePreference;
+use mygraphics::wgpu_renderer::renderer::{MyRenderer, validate_sample_count};
+use mygraphics::wgpu_renderer::{MSAA_SAMPLES, request_device, select_adapter};
+use mygraphics_shaders::ShaderConstants;
+use pollster::block_on;
+
+pub fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let instance =
+        wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+    let adapter = block_on(select_adapter(
+        &instance,
+        None,
+        &DevicePreference::from_env(),
+    ))?;
+    let (device, queue) = block_on(request_device(&adapter))?;
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let sample_count = validate_sample_count(&adapter, &device, format, MSAA_SAMPLES)?;
+    let mut renderer = MyRenderer::new(device, queue, format, sample_count)?;
+
+    let (width, height) = (1280, 720);
+    let image = renderer.capture_frame(
+        width,
+        height,
+        &ShaderConstants {
+            width,
+            height,
+            ..Zeroable::zeroed()
+        },
+    )?;
+    image.save("out.png")?;
+    println!("wrote out.png");
+    Ok(())
+}
@@ -0,0 +1,10 @@
+//! Opens two windows that share a single device and renderer, each presenting with its own swapchain
+
+use mygraphics::util::AppConfig;
+use mygraphics::wgpu_renderer::multi_window::run;
+
+const WINDOWS: usize = 2;
+
+pub fn main() -> anyhow::Result<()> {
+    run(AppConfig::from_args("Rust GPU - multi window"), WINDOWS)
+}
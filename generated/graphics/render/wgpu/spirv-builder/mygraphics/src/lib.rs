@@ -0,0 +1,11 @@
+/// Enters a `tracing` span named `$name` until the end of the enclosing block if the `profiling` feature is enabled,
+/// otherwise expands to nothing
+macro_rules! profile_span {
+    ($name:literal) => {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!($name).entered();
+    };
+}
+
+pub mod util;
+pub mod wgpu_renderer;
@@ -0,0 +1,3 @@
+pub fn main() -> anyhow::Result<()> {
+    mygraphics::wgpu_renderer::main()
+}
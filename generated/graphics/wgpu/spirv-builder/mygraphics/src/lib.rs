@@ -1,2 +0,0 @@
-pub mod util;
-pub mod wgpu_renderer;
@@ -2,10 +2,12 @@
 
 use bytemuck::{Pod, Zeroable};
 use core::f32::consts::PI;
-use glam::{Vec3, Vec4, vec2, vec3};
+use glam::{UVec3, Vec2, Vec3, Vec4, vec2, vec3, vec4};
 #[cfg(target_arch = "spirv")]
 use spirv_std::num_traits::Float;
-use spirv_std::spirv;
+use spirv_std::{Image, Sampler, spirv};
+#[cfg(feature = "bindless")]
+use spirv_std::{RuntimeArray, TypedBuffer};
 
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(C)]
@@ -13,16 +15,260 @@ pub struct ShaderConstants {
     pub width: u32,
     pub height: u32,
     pub time: f32,
+
+    pub cursor_x: f32,
+    pub cursor_y: f32,
+    pub drag_start_x: f32,
+    pub drag_start_y: f32,
+    pub drag_end_x: f32,
+    pub drag_end_y: f32,
+
+    /// Bit mask of the pressed buttons (0 = Left, 1 = Middle, 2 = Right).
+    pub mouse_button_pressed: u32,
+
+    /// The last time each mouse button (Left, Middle or Right) was pressed,
+    /// or `f32::NEG_INFINITY` for buttons which haven't been pressed yet.
+    ///
+    /// If this is the first frame after the press of some button, that button's
+    /// entry in `mouse_button_press_time` will exactly equal `time`.
+    pub mouse_button_press_time: [f32; 3],
+
+    /// The maximum luminance the output can display, relative to SDR white. Greater than 1 when rendering to an HDR
+    /// surface, 0 is treated as 1 (SDR).
+    pub max_luminance: f32,
+    /// Nonzero if the surface format is not sRGB, so the fragment shaders must encode their linear output to sRGB
+    /// themselves with [`linear_to_srgb`].
+    pub encode_srgb: u32,
+
+    /// The position of the camera, moved with the keyboard. The demo shaders are 2D and ignore `z`.
+    pub camera_pos: [f32; 3],
+    /// How far the camera is zoomed in, 0 is treated as 1.
+    pub zoom: f32,
+
+    /// How many instances of the triangle are drawn, arranged in a grid by [`instance_transform`]. 0 is treated as 1,
+    /// see [`Self::instances`].
+    pub instance_count: u32,
+}
+
+impl ShaderConstants {
+    /// The number of instances to draw, [`Self::instance_count`] but at least 1
+    pub fn instances(&self) -> u32 {
+        self.instance_count.max(1)
+    }
+}
+
+/// The size of push constants in bytes every Vulkan implementation supports, which [`ShaderConstants`] and
+/// [`BindlessPushConstants`] must fit in
+pub const PUSH_CONSTANT_BUDGET: usize = 128;
+
+// `ShaderConstants` are passed as push constants, whose size must also be a multiple of 4. Adding a field that
+// exceeds the budget fails here instead of corrupting the constants at runtime.
+const _: () = assert!(size_of::<ShaderConstants>() <= PUSH_CONSTANT_BUDGET);
+const _: () = assert!(size_of::<ShaderConstants>().is_multiple_of(4));
+
+/// The [`ShaderConstants`] as read from a uniform buffer by the `_uniform` entry points
+///
+/// Uniform buffers must follow the std140 layout, which gives arrays a stride of 16 bytes and rounds the size of structs
+/// up to 16 bytes. [`ShaderConstants`] doesn't, so this stores its arrays as individual scalars and pads the end.
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct UniformConstants {
+    pub width: u32,
+    pub height: u32,
+    pub time: f32,
+    pub cursor_x: f32,
+    pub cursor_y: f32,
+    pub drag_start_x: f32,
+    pub drag_start_y: f32,
+    pub drag_end_x: f32,
+    pub drag_end_y: f32,
+    pub mouse_button_pressed: u32,
+    pub mouse_button_press_time_left: f32,
+    pub mouse_button_press_time_middle: f32,
+    pub mouse_button_press_time_right: f32,
+    pub max_luminance: f32,
+    pub encode_srgb: u32,
+    pub camera_x: f32,
+    pub camera_y: f32,
+    pub camera_z: f32,
+    pub zoom: f32,
+    pub instance_count: u32,
+}
+
+const _: () = assert!(size_of::<UniformConstants>().is_multiple_of(16));
+
+impl From<ShaderConstants> for UniformConstants {
+    fn from(c: ShaderConstants) -> Self {
+        Self {
+            width: c.width,
+            height: c.height,
+            time: c.time,
+            cursor_x: c.cursor_x,
+            cursor_y: c.cursor_y,
+            drag_start_x: c.drag_start_x,
+            drag_start_y: c.drag_start_y,
+            drag_end_x: c.drag_end_x,
+            drag_end_y: c.drag_end_y,
+            mouse_button_pressed: c.mouse_button_pressed,
+            mouse_button_press_time_left: c.mouse_button_press_time[0],
+            mouse_button_press_time_middle: c.mouse_button_press_time[1],
+            mouse_button_press_time_right: c.mouse_button_press_time[2],
+            max_luminance: c.max_luminance,
+            encode_srgb: c.encode_srgb,
+            camera_x: c.camera_pos[0],
+            camera_y: c.camera_pos[1],
+            camera_z: c.camera_pos[2],
+            zoom: c.zoom,
+            instance_count: c.instance_count,
+        }
+    }
+}
+
+impl From<UniformConstants> for ShaderConstants {
+    fn from(c: UniformConstants) -> Self {
+        Self {
+            width: c.width,
+            height: c.height,
+            time: c.time,
+            cursor_x: c.cursor_x,
+            cursor_y: c.cursor_y,
+            drag_start_x: c.drag_start_x,
+            drag_start_y: c.drag_start_y,
+            drag_end_x: c.drag_end_x,
+            drag_end_y: c.drag_end_y,
+            mouse_button_pressed: c.mouse_button_pressed,
+            mouse_button_press_time: [
+                c.mouse_button_press_time_left,
+                c.mouse_button_press_time_middle,
+                c.mouse_button_press_time_right,
+            ],
+            max_luminance: c.max_luminance,
+            encode_srgb: c.encode_srgb,
+            camera_pos: [c.camera_x, c.camera_y, c.camera_z],
+            zoom: c.zoom,
+            instance_count: c.instance_count,
+        }
+    }
+}
+
+/// Scales a position in clip space so shapes keep their proportions on non-square outputs, shrinking the longer axis
+/// so the unit square stays fully visible
+fn aspect_correction(position: Vec2, constants: &ShaderConstants) -> Vec2 {
+    if constants.width == 0 || constants.height == 0 {
+        return position;
+    }
+    let aspect = constants.width as f32 / constants.height as f32;
+    if aspect > 1. {
+        vec2(position.x / aspect, position.y)
+    } else {
+        vec2(position.x, position.y * aspect)
+    }
+}
+
+/// Moves a position in clip space to be relative to the camera of `constants`, correcting for the aspect ratio
+fn camera_transform(position: Vec2, constants: &ShaderConstants) -> Vec2 {
+    let zoom = if constants.zoom > 0. {
+        constants.zoom
+    } else {
+        1.
+    };
+    let position = (position - vec2(constants.camera_pos[0], constants.camera_pos[1])) * zoom;
+    aspect_correction(position, constants)
+}
+
+/// Shrinks a position in clip space into the cell of instance `instance_id` in a square grid covering the unit
+/// square, filled left to right and top to bottom. A single instance is left as is.
+fn instance_transform(position: Vec2, instance_id: u32, constants: &ShaderConstants) -> Vec2 {
+    let instances = constants.instances();
+    if instances == 1 {
+        return position;
+    }
+    let columns = f32::ceil(f32::sqrt(instances as f32)) as u32;
+    let cell_size = 2. / columns as f32;
+    let cell = vec2(
+        (instance_id % columns) as f32,
+        (instance_id / columns) as f32,
+    );
+    let center = vec2(-1., 1.) + vec2(cell.x + 0.5, -(cell.y + 0.5)) * cell_size;
+    center + position * (cell_size * 0.5)
+}
+
+/// Encodes a linear color channel with the sRGB transfer function, what sRGB formats do when they are written to
+pub fn linear_to_srgb(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Decodes an sRGB encoded color channel to linear, the inverse of [`linear_to_srgb`]
+pub fn srgb_to_linear(srgb: f32) -> f32 {
+    if srgb <= 0.04045 {
+        srgb / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Writes the linear `color` to the output, encoding it to sRGB if [`ShaderConstants::encode_srgb`] is set
+fn write_output(color: Vec4, constants: &ShaderConstants, output: &mut Vec4) {
+    *output = if constants.encode_srgb != 0 {
+        vec4(
+            linear_to_srgb(color.x),
+            linear_to_srgb(color.y),
+            linear_to_srgb(color.z),
+            color.w,
+        )
+    } else {
+        color
+    };
+}
+
+/// A vertex of a mesh, as read by [`main_vs_mesh`]
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct Vertex {
+    pub position: [f32; 2],
+    pub color: [f32; 3],
+}
+
+/// The parameters of an indirect draw, laid out like `VkDrawIndirectCommand`
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct DrawIndirectCommand {
+    pub vertex_count: u32,
+    pub instance_count: u32,
+    pub first_vertex: u32,
+    pub first_instance: u32,
 }
 
 #[spirv(fragment)]
-pub fn main_fs(vtx_color: Vec3, output: &mut Vec4) {
-    *output = Vec4::from((vtx_color, 1.));
+pub fn main_fs(
+    vtx_color: Vec3,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)] constants: &ShaderConstants,
+    output: &mut Vec4,
+) {
+    // tint towards white while the left mouse button is held down
+    let mut color = vtx_color;
+    if constants.mouse_button_pressed & 1 != 0 {
+        color = color * 0.5 + 0.5;
+    }
+    // tonemap into the displayable range, a simple clamp as our colors never exceed SDR white
+    let max_luminance = if constants.max_luminance > 0. {
+        constants.max_luminance
+    } else {
+        1.
+    };
+    color = color.min(Vec3::splat(max_luminance));
+    write_output(Vec4::from((color, 1.)), constants, output);
 }
 
+/// Generates a spinning triangle from the vertex index, with one copy per instance arranged in a grid
 #[spirv(vertex)]
 pub fn main_vs(
     #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(instance_index)] instance_id: u32,
     #[spirv(descriptor_set = 0, binding = 0, storage_buffer)] constants: &ShaderConstants,
     #[spirv(position)] vtx_pos: &mut Vec4,
     vtx_color: &mut Vec3,
@@ -30,7 +276,355 @@ pub fn main_vs(
     let speed = 0.4;
     let time = constants.time * speed + vert_id as f32 * (2. * PI * 120. / 360.);
     let position = vec2(f32::sin(time), f32::cos(time));
-    *vtx_pos = Vec4::from((position, 0.0, 1.0));
+    let position = instance_transform(position, instance_id, constants);
+    *vtx_pos = Vec4::from((camera_transform(position, constants), 0.0, 1.0));
 
     *vtx_color = [vec3(1., 0., 0.), vec3(0., 1., 0.), vec3(0., 0., 1.)][vert_id as usize % 3];
 }
+
+/// Like [`main_fs`], but runs once per sample instead of once per pixel with MSAA, as reading the sample index
+/// requires sample rate shading. wgpu has no pipeline state to enable it, so this is how it's requested there. Only
+/// compiled with the `sample-shading` feature, which enables the `SampleRateShading` capability.
+#[cfg(feature = "sample-shading")]
+#[spirv(fragment)]
+pub fn main_fs_per_sample(
+    vtx_color: Vec3,
+    #[spirv(sample_id, flat)] _sample_id: u32,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)] constants: &ShaderConstants,
+    output: &mut Vec4,
+) {
+    main_fs(vtx_color, constants, output);
+}
+
+/// Like [`main_fs`], but reads the [`ShaderConstants`] from push constants instead of a storage buffer
+#[spirv(fragment)]
+pub fn main_fs_push(
+    vtx_color: Vec3,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    output: &mut Vec4,
+) {
+    main_fs(vtx_color, constants, output);
+}
+
+/// The push constants of [`main_fs_bindless`], the [`ShaderConstants`] followed by the index of the tint to apply
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct BindlessPushConstants {
+    pub constants: ShaderConstants,
+    pub tint_index: u32,
+}
+
+const _: () = assert!(size_of::<BindlessPushConstants>() <= PUSH_CONSTANT_BUDGET);
+const _: () = assert!(size_of::<BindlessPushConstants>().is_multiple_of(4));
+
+/// Like [`main_fs_push`], but multiplies the color with a tint read from an array of storage buffers, indexed by
+/// [`BindlessPushConstants::tint_index`]. The array is bound with a variable descriptor count, so it's only compiled
+/// with the `bindless` feature, which enables the `RuntimeDescriptorArray` capability.
+#[cfg(feature = "bindless")]
+#[spirv(fragment)]
+pub fn main_fs_bindless(
+    vtx_color: Vec3,
+    #[spirv(push_constant)] push: &BindlessPushConstants,
+    #[spirv(descriptor_set = 0, binding = 3, storage_buffer)] tints: &RuntimeArray<
+        TypedBuffer<Vec4>,
+    >,
+    output: &mut Vec4,
+) {
+    // the index is the same for the whole draw, so it doesn't need to be marked `NonUniform`
+    let tint = unsafe { **tints.index(push.tint_index as usize) };
+    main_fs(vtx_color * tint.truncate(), &push.constants, output);
+}
+
+/// Like [`main_vs`], but reads the [`ShaderConstants`] from push constants instead of a storage buffer
+#[spirv(vertex)]
+pub fn main_vs_push(
+    #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(instance_index)] instance_id: u32,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(position)] vtx_pos: &mut Vec4,
+    vtx_color: &mut Vec3,
+) {
+    main_vs(vert_id, instance_id, constants, vtx_pos, vtx_color);
+}
+
+/// Like [`main_fs`], but reads the constants from a uniform buffer instead of a storage buffer
+#[spirv(fragment)]
+pub fn main_fs_uniform(
+    vtx_color: Vec3,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] constants: &UniformConstants,
+    output: &mut Vec4,
+) {
+    main_fs(vtx_color, &ShaderConstants::from(*constants), output);
+}
+
+/// Like [`main_vs`], but reads the constants from a uniform buffer instead of a storage buffer
+#[spirv(vertex)]
+pub fn main_vs_uniform(
+    #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(instance_index)] instance_id: u32,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] constants: &UniformConstants,
+    #[spirv(position)] vtx_pos: &mut Vec4,
+    vtx_color: &mut Vec3,
+) {
+    main_vs(
+        vert_id,
+        instance_id,
+        &ShaderConstants::from(*constants),
+        vtx_pos,
+        vtx_color,
+    );
+}
+
+/// Alternative to [`main_vs`] for sampling a texture in [`main_fs_texture`], with UVs derived from the vertex index
+#[spirv(vertex)]
+pub fn main_vs_texture(
+    #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(instance_index)] instance_id: u32,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)] constants: &ShaderConstants,
+    #[spirv(position)] vtx_pos: &mut Vec4,
+    vtx_uv: &mut Vec2,
+) {
+    let speed = 0.4;
+    let time = constants.time * speed + vert_id as f32 * (2. * PI * 120. / 360.);
+    let position = vec2(f32::sin(time), f32::cos(time));
+    let instance_position = instance_transform(position, instance_id, constants);
+    *vtx_pos = Vec4::from((camera_transform(instance_position, constants), 0.0, 1.0));
+
+    // the texture rotates along with the triangle, mapping the circle it's inscribed in to the unit square
+    *vtx_uv = position * vec2(0.5, -0.5) + 0.5;
+}
+
+/// Like [`main_vs_texture`], but reads the [`ShaderConstants`] from push constants instead of a storage buffer
+#[spirv(vertex)]
+pub fn main_vs_texture_push(
+    #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(instance_index)] instance_id: u32,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(position)] vtx_pos: &mut Vec4,
+    vtx_uv: &mut Vec2,
+) {
+    main_vs_texture(vert_id, instance_id, constants, vtx_pos, vtx_uv);
+}
+
+/// Like [`main_vs_texture`], but reads the constants from a uniform buffer instead of a storage buffer
+#[spirv(vertex)]
+pub fn main_vs_texture_uniform(
+    #[spirv(vertex_index)] vert_id: i32,
+    #[spirv(instance_index)] instance_id: u32,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] constants: &UniformConstants,
+    #[spirv(position)] vtx_pos: &mut Vec4,
+    vtx_uv: &mut Vec2,
+) {
+    let constants = ShaderConstants::from(*constants);
+    main_vs_texture(vert_id, instance_id, &constants, vtx_pos, vtx_uv);
+}
+
+/// Alternative to [`main_fs`] sampling a texture, used with [`main_vs_texture`]
+#[spirv(fragment)]
+pub fn main_fs_texture(
+    vtx_uv: Vec2,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)] constants: &ShaderConstants,
+    #[spirv(descriptor_set = 0, binding = 1)] texture: &Image!(2D, type=f32, sampled),
+    #[spirv(descriptor_set = 0, binding = 2)] sampler: &Sampler,
+    output: &mut Vec4,
+) {
+    write_output(texture.sample(*sampler, vtx_uv), constants, output);
+}
+
+/// Like [`main_fs_texture`], but reads the [`ShaderConstants`] from push constants instead of a storage buffer
+#[spirv(fragment)]
+pub fn main_fs_texture_push(
+    vtx_uv: Vec2,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(descriptor_set = 0, binding = 1)] texture: &Image!(2D, type=f32, sampled),
+    #[spirv(descriptor_set = 0, binding = 2)] sampler: &Sampler,
+    output: &mut Vec4,
+) {
+    main_fs_texture(vtx_uv, constants, texture, sampler, output);
+}
+
+/// Like [`main_fs_texture`], but reads the constants from a uniform buffer instead of a storage buffer
+#[spirv(fragment)]
+pub fn main_fs_texture_uniform(
+    vtx_uv: Vec2,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] constants: &UniformConstants,
+    #[spirv(descriptor_set = 0, binding = 1)] texture: &Image!(2D, type=f32, sampled),
+    #[spirv(descriptor_set = 0, binding = 2)] sampler: &Sampler,
+    output: &mut Vec4,
+) {
+    let constants = ShaderConstants::from(*constants);
+    main_fs_texture(vtx_uv, &constants, texture, sampler, output);
+}
+
+/// Alternative to [`main_vs`] for drawing meshes from a vertex buffer of [`Vertex`], correcting their positions for
+/// the aspect ratio of the output
+#[spirv(vertex)]
+pub fn main_vs_mesh(
+    position: Vec2,
+    color: Vec3,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)] constants: &ShaderConstants,
+    #[spirv(position)] vtx_pos: &mut Vec4,
+    vtx_color: &mut Vec3,
+) {
+    *vtx_pos = Vec4::from((aspect_correction(position, constants), 0.0, 1.0));
+    *vtx_color = color;
+}
+
+/// Like [`main_vs_mesh`], but reads the [`ShaderConstants`] from push constants instead of a storage buffer
+#[spirv(vertex)]
+pub fn main_vs_mesh_push(
+    position: Vec2,
+    color: Vec3,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(position)] vtx_pos: &mut Vec4,
+    vtx_color: &mut Vec3,
+) {
+    main_vs_mesh(position, color, constants, vtx_pos, vtx_color);
+}
+
+/// Like [`main_vs_mesh`], but reads the constants from a uniform buffer instead of a storage buffer
+#[spirv(vertex)]
+pub fn main_vs_mesh_uniform(
+    position: Vec2,
+    color: Vec3,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] constants: &UniformConstants,
+    #[spirv(position)] vtx_pos: &mut Vec4,
+    vtx_color: &mut Vec3,
+) {
+    main_vs_mesh(
+        position,
+        color,
+        &ShaderConstants::from(*constants),
+        vtx_pos,
+        vtx_color,
+    );
+}
+
+/// The number of [`Vertex`]s [`main_cs_vertices`] generates, three per triangle of a ring of triangles
+pub const GPU_VERTEX_COUNT: u32 = 3 * 16;
+
+/// The workgroup size of [`main_cs_vertices`], must match its `threads` attribute
+pub const MAIN_CS_VERTICES_THREADS: u32 = 64;
+
+/// Generates a ring of spinning triangles into `vertices`, which are then drawn from a vertex buffer by
+/// [`main_vs_mesh`]. Only compiled with the `gpu-vertices` feature.
+#[cfg(feature = "gpu-vertices")]
+#[spirv(compute(threads(64)))]
+pub fn main_cs_vertices(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(push_constant)] constants: &ShaderConstants,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)] vertices: &mut [Vertex],
+) {
+    let index = id.x as usize;
+    // the last workgroup may extend past the end of the buffer
+    if index >= vertices.len() {
+        return;
+    }
+    let triangles = vertices.len() / 3;
+    let (triangle, corner) = (index / 3, index % 3);
+    let ring_angle = constants.time * 0.2 + triangle as f32 * (2. * PI / triangles as f32);
+    let center = vec2(f32::sin(ring_angle), f32::cos(ring_angle)) * 0.6;
+    let corner_angle = constants.time * 1.5 + corner as f32 * (2. * PI / 3.);
+    let position = center + vec2(f32::sin(corner_angle), f32::cos(corner_angle)) * 0.12;
+
+    let hue = triangle as f32 / triangles as f32 * 2. * PI;
+    let color = vec3(
+        f32::cos(hue) * 0.5 + 0.5,
+        f32::cos(hue - 2. * PI / 3.) * 0.5 + 0.5,
+        f32::cos(hue + 2. * PI / 3.) * 0.5 + 0.5,
+    );
+    vertices[index] = Vertex {
+        position: camera_transform(position, constants).to_array(),
+        color: color.to_array(),
+    };
+}
+
+/// The push constants of [`overlay_vs`] and [`overlay_fs`]
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(C)]
+pub struct OverlayConstants {
+    /// The size of the output in logical points, the unit of the vertex positions
+    pub screen_size: [f32; 2],
+    /// Nonzero if the output is not sRGB, like [`ShaderConstants::encode_srgb`]
+    pub encode_srgb: u32,
+    pub _pad: u32,
+}
+
+/// Draws the meshes of a GUI overlay like egui, with positions in points from the top left corner and sRGB encoded,
+/// premultiplied vertex colors
+#[spirv(vertex)]
+pub fn overlay_vs(
+    position: Vec2,
+    uv: Vec2,
+    color: Vec4,
+    #[spirv(push_constant)] constants: &OverlayConstants,
+    #[spirv(position)] vtx_pos: &mut Vec4,
+    vtx_uv: &mut Vec2,
+    vtx_color: &mut Vec4,
+) {
+    let size = Vec2::from(constants.screen_size);
+    *vtx_pos = vec4(
+        2. * position.x / size.x - 1.,
+        1. - 2. * position.y / size.y,
+        0.,
+        1.,
+    );
+    *vtx_uv = uv;
+    *vtx_color = vec4(
+        srgb_to_linear(color.x),
+        srgb_to_linear(color.y),
+        srgb_to_linear(color.z),
+        color.w,
+    );
+}
+
+/// Multiplies the vertex color of [`overlay_vs`] with the texture, which holds the font glyphs and images
+#[spirv(fragment)]
+pub fn overlay_fs(
+    vtx_uv: Vec2,
+    vtx_color: Vec4,
+    #[spirv(push_constant)] constants: &OverlayConstants,
+    #[spirv(descriptor_set = 0, binding = 0)] texture: &Image!(2D, type=f32, sampled),
+    #[spirv(descriptor_set = 0, binding = 1)] sampler: &Sampler,
+    output: &mut Vec4,
+) {
+    let color: Vec4 = vtx_color * texture.sample(*sampler, vtx_uv);
+    *output = if constants.encode_srgb != 0 {
+        vec4(
+            linear_to_srgb(color.x),
+            linear_to_srgb(color.y),
+            linear_to_srgb(color.z),
+            color.w,
+        )
+    } else {
+        color
+    };
+}
+
+/// The scalar `a` of the saxpy `y = a * x + y` computed by [`main_cs`]
+pub const SAXPY_A: f32 = 2.;
+
+/// The workgroup size of [`main_cs`], must match its `threads` attribute
+pub const MAIN_CS_THREADS: u32 = 64;
+
+/// An element of the buffer processed by [`main_cs`]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+#[repr(C)]
+pub struct SaxpyElement {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Computes `y = a * x + y` for every element, in place
+#[spirv(compute(threads(64)))]
+pub fn main_cs(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)] data: &mut [SaxpyElement],
+) {
+    let index = id.x as usize;
+    // the last workgroup may extend past the end of the buffer
+    if index < data.len() {
+        let element = &mut data[index];
+        element.y += SAXPY_A * element.x;
+    }
+}
@@ -0,0 +1,81 @@
+//! Renders frames offscreen in a tight loop, without a window or surface, and prints their min/avg/max frame time
+//!
+//! Every enabled API is benchmarked, select one with e.g. `--no-default-features --features ash`. The number of
+//! measured frames can be passed as the first argument.
+
+use anyhow::bail;
+use bytemuck::Zeroable;
+use mygraphics::util::FrameTimes;
+use mygraphics_shaders::ShaderConstants;
+
+const DEFAULT_FRAMES: u32 = 100;
+
+#[cfg(feature = "ash")]
+fn bench_ash(shader_constants: &ShaderConstants, frames: u32) -> anyhow::Result<FrameTimes> {
+    use ash::vk;
+    use mygraphics::ash_renderer::device::MyDevice;
+    use mygraphics::ash_renderer::render_pipeline::ConstantsMode;
+    use mygraphics::ash_renderer::renderer::MyRenderer;
+    use mygraphics::ash_renderer::swapchain::AttachmentConfig;
+    use mygraphics::util::{DevicePreference, bench, enable_debug_layer};
+
+    let device = MyDevice::new_headless(enable_debug_layer(), &DevicePreference::from_env())?;
+    let mut renderer = MyRenderer::new(
+        device,
+        vk::Format::R8G8B8A8_SRGB,
+        1,
+        AttachmentConfig::default(),
+        ConstantsMode::default(),
+    )?;
+    bench(&mut renderer, shader_constants, frames)
+}
+
+#[cfg(feature = "wgpu")]
+fn bench_wgpu(shader_constants: &ShaderConstants, frames: u32) -> anyhow::Result<FrameTimes> {
+    use mygraphics::util::{DevicePreference, bench};
+    use mygraphics::wgpu_renderer::renderer::{MyRenderer, validate_sample_count};
+    use mygraphics::wgpu_renderer::{MSAA_SAMPLES, request_device, select_adapter};
+    use pollster::block_on;
+
+    let instance =
+        wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle_from_env());
+    let adapter = block_on(select_adapter(
+        &instance,
+        None,
+        &DevicePreference::from_env(),
+    ))?;
+    let (device, queue) = block_on(request_device(&adapter))?;
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let sample_count = validate_sample_count(&adapter, &device, format, MSAA_SAMPLES)?;
+    let mut renderer = MyRenderer::new(device, queue, format, sample_count)?;
+    bench(&mut renderer, shader_constants, frames)
+}
+
+type Bench = fn(&ShaderConstants, u32) -> anyhow::Result<FrameTimes>;
+
+pub fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let benches: &[(&str, Bench)] = &[
+        #[cfg(feature = "ash")]
+        ("ash", bench_ash),
+        #[cfg(feature = "wgpu")]
+        ("wgpu", bench_wgpu),
+    ];
+    if benches.is_empty() {
+        bail!("Built without the `ash` or `wgpu` feature");
+    }
+    let frames = match std::env::args().nth(1) {
+        Some(frames) => frames.parse()?,
+        None => DEFAULT_FRAMES,
+    };
+    let (width, height) = (1280, 720);
+    let shader_constants = ShaderConstants {
+        width,
+        height,
+        ..Zeroable::zeroed()
+    };
+    for (api, bench) in benches {
+        println!("{api}: {}", bench(&shader_constants, frames)?);
+    }
+    Ok(())
+}
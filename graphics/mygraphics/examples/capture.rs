@@ -0,0 +1,36 @@
+//! Renders a single frame without opening a window and writes it to `out.png`
+
+use ash::vk;
+use bytemuck::Zeroable;
+use mygraphics::ash_renderer::device::MyDevice;
+use mygraphics::ash_renderer::render_pipeline::ConstantsMode;
+use mygraphics::ash_renderer::renderer::MyRenderer;
+use mygraphics::ash_renderer::swapchain::AttachmentConfig;
+use mygraphics::util::{DevicePreference, enable_debug_layer};
+use mygraphics_shaders::ShaderConstants;
+
+pub fn main() -> anyhow::Result<()> {
+    let device = MyDevice::new_headless(enable_debug_layer(), &DevicePreference::from_env())?;
+    let mut renderer = MyRenderer::new(
+        device,
+        vk::Format::R8G8B8A8_SRGB,
+        1,
+        AttachmentConfig::default(),
+        ConstantsMode::default(),
+    )?;
+    let extent = vk::Extent2D {
+        width: 1280,
+        height: 720,
+    };
+    let image = renderer.capture_frame(
+        extent,
+        &ShaderConstants {
+            width: extent.width,
+            height: extent.height,
+            ..Zeroable::zeroed()
+        },
+    )?;
+    image.save("out.png")?;
+    println!("wrote out.png");
+    Ok(())
+}
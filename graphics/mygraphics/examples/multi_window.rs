@@ -0,0 +1,19 @@
+//! Opens two windows that share a single device and renderer, each presenting with its own swapchain
+//!
+//! Uses the ash renderer if its feature is enabled, otherwise wgpu.
+
+use mygraphics::util::AppConfig;
+
+const WINDOWS: usize = 2;
+
+pub fn main() -> anyhow::Result<()> {
+    let config = AppConfig::from_args("Rust GPU - multi window");
+    #[cfg(feature = "ash")]
+    return mygraphics::ash_renderer::multi_window::run(config, WINDOWS);
+    #[cfg(all(feature = "wgpu", not(feature = "ash")))]
+    return mygraphics::wgpu_renderer::multi_window::run(config, WINDOWS);
+    #[cfg(not(any(feature = "ash", feature = "wgpu")))]
+    anyhow::bail!(
+        "Built without the `ash` or `wgpu` feature, can't open {WINDOWS} windows with {config:?}"
+    )
+}
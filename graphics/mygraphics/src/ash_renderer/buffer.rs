@@ -1,4 +1,7 @@
 use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
+use crate::ash_renderer::single_command_buffer::SingleCommandBuffer;
+use anyhow::Context;
 use ash::vk;
 use bytemuck::NoUninit;
 use gpu_allocator::MemoryLocation;
@@ -9,6 +12,8 @@ use std::sync::Arc;
 pub struct MyBuffer {
     pub buffer: vk::Buffer,
     pub allocation: Allocation,
+    pub usage: vk::BufferUsageFlags,
+    pub location: MemoryLocation,
     pub name: String,
     destroyed: bool,
 }
@@ -21,46 +26,137 @@ pub struct BufferCreateInfo<'a> {
 }
 
 impl MyBuffer {
-    pub fn from_data<T: NoUninit>(
-        device: &Arc<MyDevice>,
-        info: BufferCreateInfo<'_>,
-        data: &T,
-    ) -> anyhow::Result<Self> {
-        Self::from_slice(device, info, bytemuck::bytes_of(data))
-    }
-
-    pub fn from_slice<T: NoUninit>(
+    /// Create a buffer of `size` bytes with uninitialized contents
+    pub fn new(
         device: &Arc<MyDevice>,
         info: BufferCreateInfo<'_>,
-        data: &[T],
-    ) -> anyhow::Result<Self> {
+        size: u64,
+    ) -> Result<Self, RendererError> {
         unsafe {
             let buffer = device.create_buffer(
-                &vk::BufferCreateInfo::default()
-                    .size(size_of_val(data) as u64)
-                    .usage(vk::BufferUsageFlags::STORAGE_BUFFER),
+                &vk::BufferCreateInfo::default().size(size).usage(info.usage),
                 None,
             )?;
             let name = info.name.map(|a| a.into_owned()).unwrap_or_default();
-            let mut allocation = device.borrow_allocator().allocate(&AllocationCreateDesc {
+            let allocation = device.borrow_allocator().allocate(&AllocationCreateDesc {
                 name: &name,
                 requirements: device.get_buffer_memory_requirements(buffer),
-                location: MemoryLocation::CpuToGpu,
+                location: info.location,
                 linear: true,
                 allocation_scheme: AllocationScheme::GpuAllocatorManaged,
             })?;
             device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset())?;
-            let mapped = &mut allocation.mapped_slice_mut().unwrap()[..size_of_val(data)];
-            mapped.copy_from_slice(bytemuck::cast_slice(data));
+            device.set_object_name(buffer, &name);
             Ok(Self {
                 buffer,
                 allocation,
+                usage: info.usage,
+                location: info.location,
                 name,
                 destroyed: false,
             })
         }
     }
 
+    /// Create a buffer containing `data`, requires `info.location` to be host visible
+    pub fn from_data<T: NoUninit>(
+        device: &Arc<MyDevice>,
+        info: BufferCreateInfo<'_>,
+        data: &T,
+    ) -> Result<Self, RendererError> {
+        Self::from_slice(device, info, bytemuck::bytes_of(data))
+    }
+
+    /// Create a buffer containing `data`, requires `info.location` to be host visible
+    pub fn from_slice<T: NoUninit>(
+        device: &Arc<MyDevice>,
+        info: BufferCreateInfo<'_>,
+        data: &[T],
+    ) -> Result<Self, RendererError> {
+        let mut buffer = Self::new(device, info, size_of_val(data) as u64)?;
+        if let Err(e) = buffer.write_slice(data) {
+            unsafe { buffer.destroy(device) };
+            return Err(e);
+        }
+        Ok(buffer)
+    }
+
+    /// Create a device local buffer containing `data`, uploaded through a temporary staging buffer. Blocks until the
+    /// upload has finished, so it's meant for static data like meshes, not for data changing every frame.
+    ///
+    /// The buffer is always allocated in [`MemoryLocation::GpuOnly`] ignoring `info.location`, and
+    /// [`vk::BufferUsageFlags::TRANSFER_DST`] is added to `info.usage`.
+    pub fn from_slice_staged<T: NoUninit>(
+        device: &Arc<MyDevice>,
+        info: BufferCreateInfo<'_>,
+        data: &[T],
+    ) -> Result<Self, RendererError> {
+        let size = size_of_val(data) as u64;
+        let staging_name = format!("{} staging", info.name.as_deref().unwrap_or_default());
+        let mut staging = Self::from_slice(
+            device,
+            BufferCreateInfo {
+                usage: vk::BufferUsageFlags::TRANSFER_SRC,
+                location: MemoryLocation::CpuToGpu,
+                name: Some(Cow::from(staging_name)),
+            },
+            data,
+        )?;
+        let result = Self::new(
+            device,
+            BufferCreateInfo {
+                usage: info.usage | vk::BufferUsageFlags::TRANSFER_DST,
+                location: MemoryLocation::GpuOnly,
+                name: info.name,
+            },
+            size,
+        )
+        .and_then(|mut buffer| {
+            match unsafe { copy_buffer(device, &staging, &buffer, size) } {
+                Ok(()) => Ok(buffer),
+                Err(e) => {
+                    unsafe { buffer.destroy(device) };
+                    Err(e)
+                }
+            }
+        });
+        unsafe { staging.destroy(device) };
+        result
+    }
+
+    /// Overwrite the start of this buffer with `data`, requires the buffer to be host visible
+    ///
+    /// You must ensure the buffer is not in use by the GPU while writing to it.
+    pub fn write_data<T: NoUninit>(&mut self, data: &T) -> Result<(), RendererError> {
+        self.write_slice(bytemuck::bytes_of(data))
+    }
+
+    /// Overwrite the start of this buffer with `data`, requires the buffer to be host visible
+    ///
+    /// You must ensure the buffer is not in use by the GPU while writing to it.
+    pub fn write_slice<T: NoUninit>(&mut self, data: &[T]) -> Result<(), RendererError> {
+        let mapped = self.allocation.mapped_slice_mut().with_context(|| {
+            format!(
+                "Buffer {} in {:?} memory is not host visible",
+                self.name, self.location
+            )
+        })?;
+        mapped[..size_of_val(data)].copy_from_slice(bytemuck::cast_slice(data));
+        Ok(())
+    }
+
+    /// The contents of this buffer, requires the buffer to be host visible
+    ///
+    /// You must ensure the GPU has finished writing to the buffer before reading it.
+    pub fn read_bytes(&self) -> Result<&[u8], RendererError> {
+        Ok(self.allocation.mapped_slice().with_context(|| {
+            format!(
+                "Buffer {} in {:?} memory is not host visible",
+                self.name, self.location
+            )
+        })?)
+    }
+
     /// Destroy this buffer
     ///
     /// # Safety
@@ -71,7 +167,117 @@ impl MyBuffer {
             unsafe {
                 device.destroy_buffer(self.buffer, None);
             }
+            device
+                .borrow_allocator()
+                .free(std::mem::take(&mut self.allocation))
+                .ok();
+        }
+    }
+}
+
+/// Copies the first `size` bytes of `src` into `dst` on the [`MyDevice::transfer_queue`], blocking until the copy
+/// has finished and `dst` is ready to be used on the [`MyDevice::main_queue`]
+///
+/// Our buffers use exclusive sharing, so they are owned by a single queue family at a time. If the transfer queue is
+/// of another family than the main queue, `dst` is owned by the transfer family after the copy and its contents are
+/// undefined when used on the main queue. The ownership must be handed over explicitly with a pair of matching
+/// barriers, both naming the same source and destination queue families:
+/// * a release barrier recorded on the transfer queue after the copy, its destination stage and access are ignored
+/// * an acquire barrier recorded on the main queue, its source stage and access are ignored
+///
+/// The acquire must happen after the release, which we ensure with a semaphore between the two submissions.
+/// Forgetting either barrier tends to work on some drivers and silently corrupt the buffer on others.
+unsafe fn copy_buffer(
+    device: &Arc<MyDevice>,
+    src: &MyBuffer,
+    dst: &MyBuffer,
+    size: u64,
+) -> Result<(), RendererError> {
+    unsafe {
+        let ownership_transfer = device.transfer_queue_family != device.main_queue_family;
+        let ownership_barrier = vk::BufferMemoryBarrier2::default()
+            .buffer(dst.buffer)
+            .size(vk::WHOLE_SIZE)
+            .src_queue_family_index(device.transfer_queue_family)
+            .dst_queue_family_index(device.main_queue_family);
+
+        let command =
+            SingleCommandBuffer::with_queue_family(device.clone(), device.transfer_queue_family)?;
+        let cmd = command.cmd;
+        device.begin_command_buffer(
+            cmd,
+            &vk::CommandBufferBeginInfo::default()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+        )?;
+        device.cmd_copy_buffer(
+            cmd,
+            src.buffer,
+            dst.buffer,
+            &[vk::BufferCopy::default().size(size)],
+        );
+        if ownership_transfer {
+            // release
+            device.cmd_pipeline_barrier2(
+                cmd,
+                &vk::DependencyInfo::default().buffer_memory_barriers(&[ownership_barrier
+                    .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                    .src_stage_mask(vk::PipelineStageFlags2::COPY)]),
+            );
+        }
+        device.end_command_buffer(cmd)?;
+        let command_buffer_infos = [vk::CommandBufferSubmitInfo::default().command_buffer(cmd)];
+        let submit = vk::SubmitInfo2::default().command_buffer_infos(&command_buffer_infos);
+        if !ownership_transfer {
+            device.queue_submit2(device.transfer_queue, &[submit], vk::Fence::null())?;
+            device.queue_wait_idle(device.transfer_queue)?;
+            return Ok(());
+        }
+
+        let acquire = SingleCommandBuffer::new(device.clone())?;
+        let semaphore = device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None)?;
+        let result = (|| {
+            device.queue_submit2(
+                device.transfer_queue,
+                &[
+                    submit.signal_semaphore_infos(&[vk::SemaphoreSubmitInfo::default()
+                        .semaphore(semaphore)
+                        // also means the transfer submission finished once the main queue is idle
+                        .stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)]),
+                ],
+                vk::Fence::null(),
+            )?;
+            device.begin_command_buffer(
+                acquire.cmd,
+                &vk::CommandBufferBeginInfo::default()
+                    .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT),
+            )?;
+            // we don't know how the buffer is used, so make it visible to every later command
+            device.cmd_pipeline_barrier2(
+                acquire.cmd,
+                &vk::DependencyInfo::default().buffer_memory_barriers(&[ownership_barrier
+                    .dst_access_mask(vk::AccessFlags2::MEMORY_READ)
+                    .dst_stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)]),
+            );
+            device.end_command_buffer(acquire.cmd)?;
+            device.queue_submit2(
+                device.main_queue,
+                &[vk::SubmitInfo2::default()
+                    .wait_semaphore_infos(&[vk::SemaphoreSubmitInfo::default()
+                        .semaphore(semaphore)
+                        .stage_mask(vk::PipelineStageFlags2::ALL_COMMANDS)])
+                    .command_buffer_infos(&[
+                        vk::CommandBufferSubmitInfo::default().command_buffer(acquire.cmd)
+                    ])],
+                vk::Fence::null(),
+            )?;
+            device.queue_wait_idle(device.main_queue)
+        })();
+        // if a submission failed, the other one may still use the semaphore and command buffers
+        if result.is_err() {
+            device.device_wait_idle().ok();
         }
+        device.destroy_semaphore(semaphore, None);
+        Ok(result?)
     }
 }
 
@@ -82,3 +288,85 @@ impl Drop for MyBuffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::DevicePreference;
+
+    /// These tests need a Vulkan device and are skipped if none is available
+    fn device() -> Option<Arc<MyDevice>> {
+        match MyDevice::new_headless(false, &DevicePreference::from_env()) {
+            Ok(device) => Some(device),
+            Err(e) => {
+                eprintln!("skipping test, no Vulkan device available: {e:?}");
+                None
+            }
+        }
+    }
+
+    fn info(usage: vk::BufferUsageFlags, location: MemoryLocation) -> BufferCreateInfo<'static> {
+        BufferCreateInfo {
+            usage,
+            location,
+            name: Some(Cow::from("test")),
+        }
+    }
+
+    #[test]
+    pub fn buffer_from_slice_usage() {
+        let Some(device) = device() else { return };
+        let data = [1u32, 2, 3, 4];
+        let mut buffer = MyBuffer::from_slice(
+            &device,
+            info(
+                vk::BufferUsageFlags::VERTEX_BUFFER,
+                MemoryLocation::CpuToGpu,
+            ),
+            &data,
+        )
+        .unwrap();
+        assert_eq!(buffer.usage, vk::BufferUsageFlags::VERTEX_BUFFER);
+        assert_eq!(buffer.location, MemoryLocation::CpuToGpu);
+        assert_eq!(
+            &buffer.read_bytes().unwrap()[..size_of_val(&data)],
+            bytemuck::cast_slice::<u32, u8>(&data)
+        );
+        unsafe { buffer.destroy(&device) };
+    }
+
+    #[test]
+    pub fn buffer_from_slice_gpu_only() {
+        let Some(device) = device() else { return };
+        let result = MyBuffer::from_slice(
+            &device,
+            info(vk::BufferUsageFlags::VERTEX_BUFFER, MemoryLocation::GpuOnly),
+            &[1u32, 2, 3, 4],
+        );
+        match result {
+            // with unified memory, device local memory may be host visible as well
+            Ok(mut buffer) => {
+                assert_eq!(buffer.location, MemoryLocation::GpuOnly);
+                unsafe { buffer.destroy(&device) };
+            }
+            Err(e) => assert!(e.to_string().contains("not host visible"), "{e:?}"),
+        }
+    }
+
+    #[test]
+    pub fn buffer_from_slice_staged_usage() {
+        let Some(device) = device() else { return };
+        let mut buffer = MyBuffer::from_slice_staged(
+            &device,
+            info(vk::BufferUsageFlags::INDEX_BUFFER, MemoryLocation::CpuToGpu),
+            &[0u32, 1, 2],
+        )
+        .unwrap();
+        assert_eq!(
+            buffer.usage,
+            vk::BufferUsageFlags::INDEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST
+        );
+        assert_eq!(buffer.location, MemoryLocation::GpuOnly);
+        unsafe { buffer.destroy(&device) };
+    }
+}
@@ -1,11 +1,23 @@
+use crate::ash_renderer::error::RendererError;
+use crate::util::{DeviceInfo, DevicePreference, DeviceType, select_device};
 use anyhow::{Context, anyhow};
 use ash::{ext, khr, vk};
 use gpu_allocator::vulkan::{Allocator, AllocatorCreateDesc};
 use std::borrow::Cow;
-use std::ffi::{CStr, c_char};
+use std::ffi::{CStr, CString, c_char, c_void};
 use std::ops::Deref;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex, MutexGuard};
 
+/// The Vulkan version of the instance and device, which the build script compiled the shaders for. Below 1.3,
+/// `synchronization2` and `dynamic_rendering` are used through their extensions.
+pub const VULKAN_VERSION: u32 = match env!("VULKAN_VERSION").as_bytes() {
+    b"1.1" => vk::API_VERSION_1_1,
+    b"1.2" => vk::API_VERSION_1_2,
+    b"1.3" => vk::API_VERSION_1_3,
+    _ => panic!("VULKAN_VERSION must be 1.1, 1.2 or 1.3"),
+};
+
 /// Central struct containing the Vulkan instance and device, among others
 pub struct MyDevice {
     pub entry: ash::Entry,
@@ -14,14 +26,64 @@ pub struct MyDevice {
     pub device: ash::Device,
     pub main_queue_family: u32,
     pub main_queue: vk::Queue,
+    /// A queue family dedicated to transfers, so uploads can run concurrently with rendering. Same as
+    /// [`Self::main_queue_family`] if the device has no such family.
+    pub transfer_queue_family: u32,
+    /// The first queue of [`Self::transfer_queue_family`], or [`Self::main_queue`] if they are the same family
+    pub transfer_queue: vk::Queue,
     allocator: Option<Mutex<Allocator>>,
     pub debug_ext_instance: ext::debug_utils::Instance,
     pub debug_ext_device: ext::debug_utils::Device,
+    /// only usable if not [`Self::headless`]
     pub surface_ext: khr::surface::Instance,
+    /// only usable if not [`Self::headless`]
     pub swapchain_ext: khr::swapchain::Device,
+    /// created by [`Self::new_headless`], without the surface and swapchain extensions
+    pub headless: bool,
+    /// whether the validation layer is enabled, objects are only named if it is
+    pub debug_layer: bool,
+    /// whether the `fillModeNonSolid` feature is enabled, required to draw wireframes with [`vk::PolygonMode::LINE`]
+    pub fill_mode_non_solid: bool,
+    /// whether the `sampleRateShading` feature is enabled, required to shade every sample with MSAA
+    pub sample_rate_shading: bool,
+    /// whether the `occlusionQueryPrecise` feature is enabled, required for occlusion queries to count the exact
+    /// number of samples passing instead of just reporting whether any did
+    pub occlusion_query_precise: bool,
+    /// whether the `multiDrawIndirect` feature is enabled, required to draw more than one indirect command per call
+    pub multi_draw_indirect: bool,
+    /// whether the `VK_KHR_present_id` and `VK_KHR_present_wait` extensions are enabled, allowing the swapchain to
+    /// wait for frames to be presented, see [`Self::present_wait_ext`]. Never enabled if [`Self::headless`].
+    pub present_wait: bool,
+    /// only usable if [`Self::present_wait`]
+    pub present_wait_ext: khr::present_wait::Device,
+    /// whether the descriptor indexing features for a variable sized, partially bound and update-after-bind array of
+    /// storage buffers are enabled. Only requested with the `bindless` feature.
+    pub descriptor_indexing: bool,
+    /// whether pipelines draw with dynamic rendering, core in Vulkan 1.3 and optional through `VK_KHR_dynamic_rendering`
+    /// below. Otherwise they are created for a [`vk::RenderPass`] and draw into a [`vk::Framebuffer`], see
+    /// [`RenderPassManager`](crate::ash_renderer::render_pass::RenderPassManager).
+    pub dynamic_rendering: bool,
+    /// Shared by all pipelines, loaded from and written back to disk, see `pipeline_cache_path()`
+    pub pipeline_cache: vk::PipelineCache,
+    pipeline_cache_path: Option<PathBuf>,
     debug_callback: vk::DebugUtilsMessengerEXT,
 }
 
+/// Ends the labeled region begun by [`MyDevice::debug_label`] when dropped
+pub struct DebugLabel<'a> {
+    device: &'a MyDevice,
+    /// `None` if no region was begun
+    cmd: Option<vk::CommandBuffer>,
+}
+
+impl Drop for DebugLabel<'_> {
+    fn drop(&mut self) {
+        if let Some(cmd) = self.cmd {
+            unsafe { self.device.debug_ext_device.cmd_end_debug_utils_label(cmd) }
+        }
+    }
+}
+
 impl Deref for MyDevice {
     type Target = ash::Device;
 
@@ -31,9 +93,34 @@ impl Deref for MyDevice {
 }
 
 impl MyDevice {
-    pub fn new(extension_names: &[*const c_char], debug_layer: bool) -> anyhow::Result<Arc<Self>> {
+    /// Create a device able to present to surfaces, `extension_names` are the instance extensions required by the
+    /// window system, see [`ash_window::enumerate_required_extensions`]
+    pub fn new(
+        extension_names: &[*const c_char],
+        debug_layer: bool,
+        preference: &DevicePreference,
+    ) -> Result<Arc<Self>, RendererError> {
+        Self::create(extension_names, debug_layer, preference, false)
+            .map_err(|e| RendererError::DeviceCreation(Box::new(e)))
+    }
+
+    /// Create a device without any surface or swapchain support, for compute or offscreen rendering without a window
+    pub fn new_headless(
+        debug_layer: bool,
+        preference: &DevicePreference,
+    ) -> Result<Arc<Self>, RendererError> {
+        Self::create(&[], debug_layer, preference, true)
+            .map_err(|e| RendererError::DeviceCreation(Box::new(e)))
+    }
+
+    fn create(
+        extension_names: &[*const c_char],
+        debug_layer: bool,
+        preference: &DevicePreference,
+        headless: bool,
+    ) -> Result<Arc<Self>, RendererError> {
         unsafe {
-            let entry = ash::Entry::load()?;
+            let entry = ash::Entry::load().context("Failed to load the Vulkan library")?;
 
             let instance = {
                 let layer_names: &'static [_] = if debug_layer {
@@ -46,33 +133,27 @@ impl MyDevice {
                 extension_names_raw.push(ext::debug_utils::NAME.as_ptr());
 
                 let app_name = c"VulkanTriangle";
-                entry
-                    .create_instance(
-                        &vk::InstanceCreateInfo::default()
-                            .application_info(
-                                &vk::ApplicationInfo::default()
-                                    .application_name(app_name)
-                                    .application_version(0)
-                                    .engine_name(app_name)
-                                    .engine_version(0)
-                                    .api_version(vk::make_api_version(0, 1, 3, 0)),
-                            )
-                            .enabled_layer_names(layer_names)
-                            .enabled_extension_names(&extension_names_raw),
-                        None,
-                    )
-                    .context("create_instance")?
+                entry.create_instance(
+                    &vk::InstanceCreateInfo::default()
+                        .application_info(
+                            &vk::ApplicationInfo::default()
+                                .application_name(app_name)
+                                .application_version(0)
+                                .engine_name(app_name)
+                                .engine_version(0)
+                                .api_version(VULKAN_VERSION),
+                        )
+                        .enabled_layer_names(layer_names)
+                        .enabled_extension_names(&extension_names_raw),
+                    None,
+                )?
             };
 
             let debug_instance = ext::debug_utils::Instance::new(&entry, &instance);
             let debug_callback = {
                 debug_instance.create_debug_utils_messenger(
                     &vk::DebugUtilsMessengerCreateInfoEXT::default()
-                        .message_severity(
-                            vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
-                                | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
-                                | vk::DebugUtilsMessageSeverityFlagsEXT::INFO,
-                        )
+                        .message_severity(debug_message_severity())
                         .message_type(
                             vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
                                 | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
@@ -84,57 +165,233 @@ impl MyDevice {
             };
 
             let physical_device = {
-                instance
-                    .enumerate_physical_devices()?
-                    .into_iter()
-                    .min_by_key(|phy| {
-                        match instance.get_physical_device_properties(*phy).device_type {
-                            vk::PhysicalDeviceType::DISCRETE_GPU => 1,
-                            vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
-                            vk::PhysicalDeviceType::INTEGRATED_GPU => 3,
-                            vk::PhysicalDeviceType::CPU => 4,
-                            _ => 5,
-                        }
-                    })
-                    .ok_or(anyhow!("No physical devices available"))?
+                let physical_devices = instance.enumerate_physical_devices()?;
+                if physical_devices.is_empty() {
+                    return Err(anyhow!("No physical devices available").into());
+                }
+                let infos = physical_devices
+                    .iter()
+                    .map(|phy| physical_device_info(&instance, *phy))
+                    .collect::<Vec<_>>();
+                let selected = select_device(&infos, preference)?;
+                log::info!("Selected device {}", infos[selected]);
+                physical_devices[selected]
             };
+            let api_version = instance
+                .get_physical_device_properties(physical_device)
+                .api_version;
+            if api_version < VULKAN_VERSION {
+                return Err(anyhow!(
+                    "Device only supports Vulkan {}.{}, but the shaders require {}",
+                    vk::api_version_major(api_version),
+                    vk::api_version_minor(api_version),
+                    env!("VULKAN_VERSION")
+                )
+                .into());
+            }
 
+            let queue_families =
+                instance.get_physical_device_queue_family_properties(physical_device);
             let main_queue_family = {
-                instance
-                    .get_physical_device_queue_family_properties(physical_device)
-                    .into_iter()
+                queue_families
+                    .iter()
                     .enumerate()
-                    .find(|(_, prop)| prop.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+                    // presentation support is checked by the swapchain, if there is one
+                    .find(|(_, prop)| {
+                        prop.queue_flags
+                            .contains(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+                    })
                     .ok_or(anyhow!(
                         "No graphics + compute queues on physical device available"
                     ))?
                     .0 as u32
             };
+            // families with transfer but without graphics or compute support are usually backed by DMA engines
+            let transfer_queue_family = queue_families
+                .iter()
+                .position(|prop| {
+                    prop.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                        && !prop
+                            .queue_flags
+                            .intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+                })
+                .map_or(main_queue_family, |family| family as u32);
+            if transfer_queue_family != main_queue_family {
+                log::info!("Using queue family {transfer_queue_family} for transfers");
+            }
+            let queue_priorities = [1.0];
+            let mut queue_create_infos = vec![
+                vk::DeviceQueueCreateInfo::default()
+                    .queue_family_index(main_queue_family)
+                    .queue_priorities(&queue_priorities),
+            ];
+            if transfer_queue_family != main_queue_family {
+                queue_create_infos.push(
+                    vk::DeviceQueueCreateInfo::default()
+                        .queue_family_index(transfer_queue_family)
+                        .queue_priorities(&queue_priorities),
+                );
+            }
 
-            let device = instance
-                .create_device(
-                    physical_device,
-                    &vk::DeviceCreateInfo::default()
-                        .push_next(
-                            &mut vk::PhysicalDeviceVulkan12Features::default()
-                                .vulkan_memory_model(true),
-                        )
-                        .push_next(
-                            &mut vk::PhysicalDeviceVulkan13Features::default()
-                                .synchronization2(true)
-                                .dynamic_rendering(true),
-                        )
-                        .queue_create_infos(&[vk::DeviceQueueCreateInfo::default()
-                            .queue_family_index(main_queue_family)
-                            .queue_priorities(&[1.0])])
-                        .enabled_extension_names(&[
-                            khr::swapchain::NAME.as_ptr(),
-                            khr::shader_non_semantic_info::NAME.as_ptr(),
-                        ]),
+            let mut device_extension_names = vec![khr::shader_non_semantic_info::NAME.as_ptr()];
+            if !headless {
+                device_extension_names.push(khr::swapchain::NAME.as_ptr());
+            }
+            // promoted to core in the version of the feature structs chained below
+            if VULKAN_VERSION < vk::API_VERSION_1_3 {
+                device_extension_names.push(khr::synchronization2::NAME.as_ptr());
+            }
+            if VULKAN_VERSION < vk::API_VERSION_1_2 {
+                device_extension_names.push(khr::vulkan_memory_model::NAME.as_ptr());
+            }
+            let extensions = instance.enumerate_device_extension_properties(physical_device)?;
+            let has_extension = |name: &CStr| {
+                extensions
+                    .iter()
+                    .any(|ext| ext.extension_name_as_c_str() == Ok(name))
+            };
+            let supported_features = instance.get_physical_device_features(physical_device);
+            // optional, only used for debugging with wireframes
+            let fill_mode_non_solid = supported_features.fill_mode_non_solid == vk::TRUE;
+            // optional, only used for higher quality MSAA
+            let sample_rate_shading = supported_features.sample_rate_shading == vk::TRUE;
+            // optional, only used to count the samples passing with occlusion queries
+            let occlusion_query_precise = supported_features.occlusion_query_precise == vk::TRUE;
+            // optional, without it indirect draws are recorded one command at a time
+            let multi_draw_indirect = supported_features.multi_draw_indirect == vk::TRUE;
+            let descriptor_indexing = cfg!(feature = "bindless")
+                && if VULKAN_VERSION < vk::API_VERSION_1_2 {
+                    log::warn!(
+                        "Descriptor indexing requires Vulkan 1.2, drawing without bindless tints"
+                    );
+                    false
+                } else {
+                    let mut vulkan12 = vk::PhysicalDeviceVulkan12Features::default();
+                    instance.get_physical_device_features2(
+                        physical_device,
+                        &mut vk::PhysicalDeviceFeatures2::default().push_next(&mut vulkan12),
+                    );
+                    let supported = vulkan12.runtime_descriptor_array == vk::TRUE
+                        && vulkan12.descriptor_binding_partially_bound == vk::TRUE
+                        && vulkan12.descriptor_binding_variable_descriptor_count == vk::TRUE
+                        && vulkan12.descriptor_binding_storage_buffer_update_after_bind == vk::TRUE;
+                    if !supported {
+                        log::warn!(
+                            "Descriptor indexing is not supported by the device, drawing without bindless tints"
+                        );
+                    }
+                    supported
+                };
+            // required by Vulkan 1.3 and optional below, without it pipelines draw with render passes
+            let dynamic_rendering = (VULKAN_VERSION >= vk::API_VERSION_1_3
+                || has_extension(khr::dynamic_rendering::NAME))
+                && {
+                    let mut dynamic_rendering =
+                        vk::PhysicalDeviceDynamicRenderingFeatures::default();
+                    instance.get_physical_device_features2(
+                        physical_device,
+                        &mut vk::PhysicalDeviceFeatures2::default()
+                            .push_next(&mut dynamic_rendering),
+                    );
+                    dynamic_rendering.dynamic_rendering == vk::TRUE
+                };
+            if dynamic_rendering {
+                log::info!("Drawing with dynamic rendering");
+            } else {
+                log::info!("Dynamic rendering is not supported, drawing with render passes");
+            }
+            if dynamic_rendering && VULKAN_VERSION < vk::API_VERSION_1_3 {
+                device_extension_names.push(khr::dynamic_rendering::NAME.as_ptr());
+            }
+            // optional, only used for frame pacing by the swapchain
+            let present_wait = !headless && {
+                let mut present_id = vk::PhysicalDevicePresentIdFeaturesKHR::default();
+                let mut present_wait = vk::PhysicalDevicePresentWaitFeaturesKHR::default();
+                has_extension(khr::present_id::NAME) && has_extension(khr::present_wait::NAME) && {
+                    instance.get_physical_device_features2(
+                        physical_device,
+                        &mut vk::PhysicalDeviceFeatures2::default()
+                            .push_next(&mut present_id)
+                            .push_next(&mut present_wait),
+                    );
+                    present_id.present_id == vk::TRUE && present_wait.present_wait == vk::TRUE
+                }
+            };
+            if present_wait {
+                device_extension_names.push(khr::present_id::NAME.as_ptr());
+                device_extension_names.push(khr::present_wait::NAME.as_ptr());
+            }
+
+            let features = vk::PhysicalDeviceFeatures::default()
+                .fill_mode_non_solid(fill_mode_non_solid)
+                .sample_rate_shading(sample_rate_shading)
+                .occlusion_query_precise(occlusion_query_precise)
+                .multi_draw_indirect(multi_draw_indirect);
+            let mut vulkan12 = vk::PhysicalDeviceVulkan12Features::default()
+                .vulkan_memory_model(true)
+                .runtime_descriptor_array(descriptor_indexing)
+                .descriptor_binding_partially_bound(descriptor_indexing)
+                .descriptor_binding_variable_descriptor_count(descriptor_indexing)
+                .descriptor_binding_storage_buffer_update_after_bind(descriptor_indexing);
+            let mut vulkan13 = vk::PhysicalDeviceVulkan13Features::default()
+                .synchronization2(true)
+                .dynamic_rendering(dynamic_rendering);
+            let mut memory_model_features =
+                vk::PhysicalDeviceVulkanMemoryModelFeatures::default().vulkan_memory_model(true);
+            let mut synchronization2_features =
+                vk::PhysicalDeviceSynchronization2Features::default().synchronization2(true);
+            let mut dynamic_rendering_features =
+                vk::PhysicalDeviceDynamicRenderingFeatures::default().dynamic_rendering(true);
+            let mut present_id_features =
+                vk::PhysicalDevicePresentIdFeaturesKHR::default().present_id(true);
+            let mut present_wait_features =
+                vk::PhysicalDevicePresentWaitFeaturesKHR::default().present_wait(true);
+            let mut create_info = vk::DeviceCreateInfo::default()
+                .enabled_features(&features)
+                .queue_create_infos(&queue_create_infos)
+                .enabled_extension_names(&device_extension_names);
+            // the feature structs of a version may only be chained if the device is created with it
+            create_info = if VULKAN_VERSION >= vk::API_VERSION_1_2 {
+                create_info.push_next(&mut vulkan12)
+            } else {
+                create_info.push_next(&mut memory_model_features)
+            };
+            create_info = if VULKAN_VERSION >= vk::API_VERSION_1_3 {
+                create_info.push_next(&mut vulkan13)
+            } else {
+                create_info.push_next(&mut synchronization2_features)
+            };
+            // the feature structs of extensions may only be chained if they are enabled
+            if dynamic_rendering && VULKAN_VERSION < vk::API_VERSION_1_3 {
+                create_info = create_info.push_next(&mut dynamic_rendering_features);
+            }
+            if present_wait {
+                create_info = create_info
+                    .push_next(&mut present_id_features)
+                    .push_next(&mut present_wait_features);
+            }
+            let device = instance.create_device(physical_device, &create_info, None)?;
+            let device = if VULKAN_VERSION < vk::API_VERSION_1_3 {
+                load_device_with_khr_fallback(&instance, device.handle())
+            } else {
+                device
+            };
+            let main_queue = device.get_device_queue(main_queue_family, 0);
+            let transfer_queue = device.get_device_queue(transfer_queue_family, 0);
+
+            let pipeline_cache_path =
+                pipeline_cache_path(&instance.get_physical_device_properties(physical_device));
+            let pipeline_cache_data = pipeline_cache_path
+                .as_ref()
+                .and_then(|path| std::fs::read(path).ok())
+                .unwrap_or_default();
+            let pipeline_cache = device
+                .create_pipeline_cache(
+                    &vk::PipelineCacheCreateInfo::default().initial_data(&pipeline_cache_data),
                     None,
                 )
-                .context("create_device")?;
-            let main_queue = device.get_device_queue(main_queue_family, 0);
+                // the driver may reject a corrupted cache file, start over with an empty cache
+                .or_else(|_| device.create_pipeline_cache(&Default::default(), None))?;
 
             let allocator = Allocator::new(&AllocatorCreateDesc {
                 instance: instance.clone(),
@@ -149,28 +406,134 @@ impl MyDevice {
                 debug_ext_device: ext::debug_utils::Device::new(&instance, &device),
                 surface_ext: khr::surface::Instance::new(&entry, &instance),
                 swapchain_ext: khr::swapchain::Device::new(&instance, &device),
+                present_wait_ext: khr::present_wait::Device::new(&instance, &device),
+                present_wait,
+                headless,
+                debug_layer,
+                fill_mode_non_solid,
+                sample_rate_shading,
+                occlusion_query_precise,
+                multi_draw_indirect,
+                descriptor_indexing,
+                dynamic_rendering,
                 entry,
                 instance,
                 physical_device,
                 device,
                 main_queue_family,
                 main_queue,
+                transfer_queue_family,
+                transfer_queue,
                 allocator: Some(Mutex::new(allocator)),
+                pipeline_cache,
+                pipeline_cache_path,
                 debug_ext_instance: debug_instance,
                 debug_callback,
             }))
         }
     }
 
+    /// The name and type of the physical device
+    pub fn device_info(&self) -> DeviceInfo {
+        unsafe { physical_device_info(&self.instance, self.physical_device) }
+    }
+
+    /// Name a Vulkan object, making it show up by name in validation messages and debuggers like `RenderDoc`. Does
+    /// nothing unless the debug layer is enabled.
+    ///
+    /// # Safety
+    /// `handle` must be a valid object created from this device
+    pub unsafe fn set_object_name(&self, handle: impl vk::Handle, name: &str) {
+        if !self.debug_layer || name.is_empty() {
+            return;
+        }
+        let Ok(name) = CString::new(name) else {
+            return;
+        };
+        unsafe {
+            if let Err(e) = self.debug_ext_device.set_debug_utils_object_name(
+                &vk::DebugUtilsObjectNameInfoEXT::default()
+                    .object_handle(handle)
+                    .object_name(&name),
+            ) {
+                log::warn!("Failed to name object {name:?}: {e}");
+            }
+        }
+    }
+
+    /// Begin a labeled region of commands in `cmd`, which shows up as a group named `name` in debuggers like
+    /// `RenderDoc` and ends when the returned guard is dropped. Does nothing unless the debug layer is enabled.
+    ///
+    /// # Safety
+    /// `cmd` must be a command buffer of this device in the recording state, until the guard is dropped
+    pub unsafe fn debug_label(&self, cmd: vk::CommandBuffer, name: &str) -> DebugLabel<'_> {
+        let name = CString::new(name).ok().filter(|_| self.debug_layer);
+        if let Some(name) = &name {
+            unsafe {
+                self.debug_ext_device.cmd_begin_debug_utils_label(
+                    cmd,
+                    &vk::DebugUtilsLabelEXT::default().label_name(name),
+                );
+            }
+        }
+        DebugLabel {
+            device: self,
+            cmd: name.map(|_| cmd),
+        }
+    }
+
     pub fn borrow_allocator(&self) -> MutexGuard<'_, Allocator> {
         self.allocator.as_ref().unwrap().lock().unwrap()
     }
+
+    /// Converts a sample count of 1, 2, 4 or 8 to [`vk::SampleCountFlags`], checking that the device supports it for
+    /// both color and depth attachments.
+    pub fn sample_count(&self, samples: u32) -> Result<vk::SampleCountFlags, RendererError> {
+        let flags = match samples {
+            1 => vk::SampleCountFlags::TYPE_1,
+            2 => vk::SampleCountFlags::TYPE_2,
+            4 => vk::SampleCountFlags::TYPE_4,
+            8 => vk::SampleCountFlags::TYPE_8,
+            _ => {
+                return Err(anyhow!(
+                    "Unsupported sample count {samples}, must be one of 1, 2, 4 or 8"
+                )
+                .into());
+            }
+        };
+        let limits = unsafe {
+            self.instance
+                .get_physical_device_properties(self.physical_device)
+                .limits
+        };
+        let supported =
+            limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+        if !supported.contains(flags) {
+            return Err(anyhow!(
+                "Sample count {samples} is not supported by the device, supported: {supported:?}"
+            )
+            .into());
+        }
+        Ok(flags)
+    }
 }
 
 impl Drop for MyDevice {
     fn drop(&mut self) {
         unsafe {
             drop(self.allocator.take());
+            if let Some(path) = &self.pipeline_cache_path {
+                let result = self
+                    .device
+                    .get_pipeline_cache_data(self.pipeline_cache)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|data| Ok(std::fs::write(path, data)?));
+                if let Err(e) = result {
+                    log::warn!("Failed to write pipeline cache to {}: {e}", path.display());
+                }
+            }
+            self.device
+                .destroy_pipeline_cache(self.pipeline_cache, None);
             self.debug_ext_instance
                 .destroy_debug_utils_messenger(self.debug_callback, None);
             self.device.destroy_device(None);
@@ -179,9 +542,110 @@ impl Drop for MyDevice {
     }
 }
 
+/// Loads the functions of `device`, falling back to the `KHR` variant of each core function that is missing because
+/// it was promoted after [`VULKAN_VERSION`], e.g. `vkQueueSubmit2KHR` for `vkQueueSubmit2` below 1.3. Their signatures
+/// are identical, so the rest of the renderer can call the core functions regardless of the version.
+///
+/// # Safety
+/// `device` must have been created from `instance`
+unsafe fn load_device_with_khr_fallback(
+    instance: &ash::Instance,
+    device: vk::Device,
+) -> ash::Device {
+    unsafe {
+        ash::Device::load_with(
+            |name| {
+                instance
+                    .get_device_proc_addr(device, name.as_ptr())
+                    .or_else(|| {
+                        let khr_name = CString::new([name.to_bytes(), b"KHR"].concat()).ok()?;
+                        instance.get_device_proc_addr(device, khr_name.as_ptr())
+                    })
+                    .map_or(std::ptr::null(), |function| function as *const c_void)
+            },
+            device,
+        )
+    }
+}
+
+/// # Safety
+/// `physical_device` must have been enumerated from `instance`
+unsafe fn physical_device_info(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+) -> DeviceInfo {
+    let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+    DeviceInfo {
+        name: properties.device_name_as_c_str().map_or_else(
+            |_| "<invalid name>".into(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+        device_type: match properties.device_type {
+            vk::PhysicalDeviceType::DISCRETE_GPU => DeviceType::DiscreteGpu,
+            vk::PhysicalDeviceType::INTEGRATED_GPU => DeviceType::IntegratedGpu,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => DeviceType::VirtualGpu,
+            vk::PhysicalDeviceType::CPU => DeviceType::Cpu,
+            _ => DeviceType::Other,
+        },
+    }
+}
+
+/// The pipeline cache is stored next to the executable. Its name contains the pipeline cache UUID of the device, so
+/// caches from other GPUs or driver versions are ignored instead of being handed to the driver.
+fn pipeline_cache_path(properties: &vk::PhysicalDeviceProperties) -> Option<PathBuf> {
+    let uuid = properties
+        .pipeline_cache_uuid
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.with_file_name(format!(
+        "pipeline_cache_{:04x}_{:04x}_{uuid}.bin",
+        properties.vendor_id, properties.device_id
+    )))
+}
+
+/// The severities of validation messages to log, at least the one set by the `DEBUG_LAYER_SEVERITY` env var: `error`,
+/// `warning`, `info` (default) or `verbose`
+fn debug_message_severity() -> vk::DebugUtilsMessageSeverityFlagsEXT {
+    use vk::DebugUtilsMessageSeverityFlagsEXT as Severity;
+    let min = match std::env::var("DEBUG_LAYER_SEVERITY").as_deref() {
+        Ok("error") => Severity::ERROR,
+        Ok("warning") => Severity::WARNING,
+        Ok("info") | Err(_) => Severity::INFO,
+        Ok("verbose") => Severity::VERBOSE,
+        Ok(other) => {
+            log::warn!(
+                "Unknown DEBUG_LAYER_SEVERITY `{other}`, expected one of error, warning, info or verbose"
+            );
+            Severity::INFO
+        }
+    };
+    // each severity is a single bit, higher bits being more severe
+    [
+        Severity::VERBOSE,
+        Severity::INFO,
+        Severity::WARNING,
+        Severity::ERROR,
+    ]
+    .into_iter()
+    .filter(|severity| severity.as_raw() >= min.as_raw())
+    .fold(Severity::empty(), |a, b| a | b)
+}
+
+/// A slice from a pointer and length of some Vulkan struct, which may be null if the length is 0
+unsafe fn raw_slice<'a, T>(ptr: *const T, len: u32) -> &'a [T] {
+    if ptr.is_null() || len == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(ptr, len as usize) }
+    }
+}
+
+/// Forwards validation messages to the `log` crate with the target `vulkan`
 unsafe extern "system" fn vulkan_debug_callback(
     message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
-    _message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    message_type: vk::DebugUtilsMessageTypeFlagsEXT,
     p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT<'_>,
     _user_data: *mut std::os::raw::c_void,
 ) -> vk::Bool32 {
@@ -194,10 +658,51 @@ unsafe extern "system" fn vulkan_debug_callback(
             .message_as_c_str()
             .map_or(Cow::Borrowed(""), CStr::to_string_lossy);
 
-        println!("{message_severity:?}: [{message_id_name}] : {message}");
-        if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING)
-            | message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR)
-        {
+        let mut output = format!("{message_type:?} [{message_id_name}]: {message}");
+        for object in raw_slice(callback_data.p_objects, callback_data.object_count) {
+            let name = object
+                .object_name_as_c_str()
+                .map_or(Cow::Borrowed("unnamed"), CStr::to_string_lossy);
+            output += &format!(
+                "\n    object {:?} {:#x} `{name}`",
+                object.object_type, object.object_handle
+            );
+        }
+        for (kind, labels) in [
+            (
+                "queue",
+                raw_slice(
+                    callback_data.p_queue_labels,
+                    callback_data.queue_label_count,
+                ),
+            ),
+            (
+                "command buffer",
+                raw_slice(
+                    callback_data.p_cmd_buf_labels,
+                    callback_data.cmd_buf_label_count,
+                ),
+            ),
+        ] {
+            for label in labels {
+                let name = label
+                    .label_name_as_c_str()
+                    .map_or(Cow::Borrowed(""), CStr::to_string_lossy);
+                output += &format!("\n    {kind} label `{name}`");
+            }
+        }
+
+        let level = if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+            log::Level::Error
+        } else if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+            log::Level::Warn
+        } else if message_severity.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+            log::Level::Info
+        } else {
+            log::Level::Debug
+        };
+        log::log!(target: "vulkan", level, "{output}");
+        if level <= log::Level::Warn {
             vk::TRUE
         } else {
             vk::FALSE
@@ -1,27 +1,81 @@
 use crate::ash_renderer::device::MyDevice;
+#[cfg(feature = "texture")]
+use crate::ash_renderer::texture::MyTexture;
 use ash::vk;
 use std::sync::Arc;
 
+/// The number of storage buffers in the bindless array at binding 3, read by `main_fs_bindless`
+pub const BINDLESS_CAPACITY: u32 = 1024;
+
 pub struct GlobalDescriptorSetLayout {
     pub device: Arc<MyDevice>,
     pub layout: vk::DescriptorSetLayout,
+    /// the type of the buffer at binding 0, either [`vk::DescriptorType::STORAGE_BUFFER`] or
+    /// [`vk::DescriptorType::UNIFORM_BUFFER`]
+    pub constants_type: vk::DescriptorType,
+    /// whether binding 3 is a partially bound, update-after-bind array of [`BINDLESS_CAPACITY`] storage buffers, see
+    /// [`MyDevice::descriptor_indexing`]
+    pub bindless: bool,
 }
 
 impl GlobalDescriptorSetLayout {
-    pub fn new(device: Arc<MyDevice>) -> anyhow::Result<Arc<Self>> {
+    /// `constants_type` must match how the shaders declare binding 0, `storage_buffer` or `uniform`
+    pub fn new(
+        device: Arc<MyDevice>,
+        constants_type: vk::DescriptorType,
+    ) -> anyhow::Result<Arc<Self>> {
         unsafe {
+            let bindless = device.descriptor_indexing;
+            let mut bindings = vec![
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(0)
+                    .descriptor_type(constants_type)
+                    .stage_flags(vk::ShaderStageFlags::ALL_GRAPHICS | vk::ShaderStageFlags::COMPUTE)
+                    .descriptor_count(1),
+                #[cfg(feature = "texture")]
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(1)
+                    .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                    .descriptor_count(1),
+                #[cfg(feature = "texture")]
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(2)
+                    .descriptor_type(vk::DescriptorType::SAMPLER)
+                    .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                    .descriptor_count(1),
+            ];
+            let mut binding_flags = vec![vk::DescriptorBindingFlags::empty(); bindings.len()];
+            let mut create_info = vk::DescriptorSetLayoutCreateInfo::default();
+            if bindless {
+                // the variable sized array must be the binding with the highest number
+                bindings.push(
+                    vk::DescriptorSetLayoutBinding::default()
+                        .binding(3)
+                        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                        .descriptor_count(BINDLESS_CAPACITY),
+                );
+                binding_flags.push(
+                    vk::DescriptorBindingFlags::PARTIALLY_BOUND
+                        | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+                        | vk::DescriptorBindingFlags::VARIABLE_DESCRIPTOR_COUNT,
+                );
+                create_info =
+                    create_info.flags(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL);
+            }
+            let mut binding_flags_info = vk::DescriptorSetLayoutBindingFlagsCreateInfo::default()
+                .binding_flags(&binding_flags);
             Ok(Arc::new(Self {
                 layout: device.create_descriptor_set_layout(
-                    &vk::DescriptorSetLayoutCreateInfo::default().bindings(&[
-                        vk::DescriptorSetLayoutBinding::default()
-                            .binding(0)
-                            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-                            .stage_flags(vk::ShaderStageFlags::ALL_GRAPHICS)
-                            .descriptor_count(1),
-                    ]),
+                    &create_info
+                        .bindings(&bindings)
+                        .push_next(&mut binding_flags_info),
                     None,
                 )?,
                 device,
+                constants_type,
+                bindless,
             }))
         }
     }
@@ -45,46 +99,137 @@ pub struct GlobalDescriptorSet {
 }
 
 impl GlobalDescriptorSet {
+    /// Binding 0 is left unwritten if `shader_constants` is `None`, so shaders must not read from it. Otherwise it's
+    /// written as the [`GlobalDescriptorSetLayout::constants_type`] of `layout`, which the buffer must be usable as.
+    ///
     /// # Safety
     /// * `shader_constants` must not be dropped before `GlobalDescriptorSet` is dropped
     /// * you must only drop this `GlobalDescriptorSet` when it is unused, e.g. by GPU execution
     pub unsafe fn new(
         layout: &Arc<GlobalDescriptorSetLayout>,
-        shader_constants: vk::Buffer,
+        shader_constants: Option<vk::Buffer>,
     ) -> anyhow::Result<Self> {
         unsafe {
             let device = &layout.device;
+            let mut pool_sizes = vec![
+                vk::DescriptorPoolSize::default()
+                    .ty(layout.constants_type)
+                    .descriptor_count(1),
+                #[cfg(feature = "texture")]
+                vk::DescriptorPoolSize::default()
+                    .ty(vk::DescriptorType::SAMPLED_IMAGE)
+                    .descriptor_count(1),
+                #[cfg(feature = "texture")]
+                vk::DescriptorPoolSize::default()
+                    .ty(vk::DescriptorType::SAMPLER)
+                    .descriptor_count(1),
+            ];
+            let mut pool_flags = vk::DescriptorPoolCreateFlags::empty();
+            if layout.bindless {
+                pool_sizes.push(
+                    vk::DescriptorPoolSize::default()
+                        .ty(vk::DescriptorType::STORAGE_BUFFER)
+                        .descriptor_count(BINDLESS_CAPACITY),
+                );
+                pool_flags |= vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND;
+            }
             let pool = device.create_descriptor_pool(
                 &vk::DescriptorPoolCreateInfo::default()
-                    .pool_sizes(&[vk::DescriptorPoolSize::default()
-                        .ty(vk::DescriptorType::STORAGE_BUFFER)
-                        .descriptor_count(1)])
+                    .flags(pool_flags)
+                    .pool_sizes(&pool_sizes)
                     .max_sets(1),
                 None,
             )?;
+            let variable_counts = [if layout.bindless {
+                BINDLESS_CAPACITY
+            } else {
+                0
+            }];
             let set = device.allocate_descriptor_sets(
                 &vk::DescriptorSetAllocateInfo::default()
                     .descriptor_pool(pool)
-                    .set_layouts(&[layout.layout]),
+                    .set_layouts(&[layout.layout])
+                    .push_next(
+                        &mut vk::DescriptorSetVariableDescriptorCountAllocateInfo::default()
+                            .descriptor_counts(&variable_counts),
+                    ),
             )?[0];
-            device.update_descriptor_sets(
+            if let Some(shader_constants) = shader_constants {
+                device.update_descriptor_sets(
+                    &[vk::WriteDescriptorSet::default()
+                        .dst_set(set)
+                        .dst_binding(0)
+                        .descriptor_type(layout.constants_type)
+                        .descriptor_count(1)
+                        .buffer_info(&[vk::DescriptorBufferInfo::default()
+                            .buffer(shader_constants)
+                            .offset(0)
+                            .range(vk::WHOLE_SIZE)])],
+                    &[],
+                );
+            }
+            Ok(Self {
+                layout: layout.clone(),
+                pool,
+                set,
+                destroyed: false,
+            })
+        }
+    }
+
+    /// Binds `texture` to the sampled image and sampler of `main_fs_texture`
+    ///
+    /// # Safety
+    /// * `texture` must not be destroyed before `GlobalDescriptorSet` is dropped
+    /// * the descriptor set must not be in use, e.g. by GPU execution
+    #[cfg(feature = "texture")]
+    pub unsafe fn write_texture(&self, texture: &MyTexture) {
+        unsafe {
+            self.layout.device.update_descriptor_sets(
+                &[
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(self.set)
+                        .dst_binding(1)
+                        .descriptor_type(vk::DescriptorType::SAMPLED_IMAGE)
+                        .descriptor_count(1)
+                        .image_info(&[vk::DescriptorImageInfo::default()
+                            .image_view(texture.image.image_view)
+                            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)]),
+                    vk::WriteDescriptorSet::default()
+                        .dst_set(self.set)
+                        .dst_binding(2)
+                        .descriptor_type(vk::DescriptorType::SAMPLER)
+                        .descriptor_count(1)
+                        .image_info(&[vk::DescriptorImageInfo::default().sampler(texture.sampler)]),
+                ],
+                &[],
+            );
+        }
+    }
+
+    /// Binds `buffer` to element `index` of the bindless array read by `main_fs_bindless`. The other elements stay
+    /// unbound, which is allowed as long as the shaders don't read them.
+    ///
+    /// # Safety
+    /// * [`GlobalDescriptorSetLayout::bindless`] must be set and `index` less than [`BINDLESS_CAPACITY`]
+    /// * `buffer` must not be destroyed before `GlobalDescriptorSet` is dropped
+    /// * no command buffer using the descriptor set may be executing, though it may have been recorded already
+    #[cfg(feature = "bindless")]
+    pub unsafe fn write_bindless(&self, index: u32, buffer: vk::Buffer) {
+        unsafe {
+            self.layout.device.update_descriptor_sets(
                 &[vk::WriteDescriptorSet::default()
-                    .dst_set(set)
-                    .dst_binding(0)
+                    .dst_set(self.set)
+                    .dst_binding(3)
+                    .dst_array_element(index)
                     .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
                     .descriptor_count(1)
                     .buffer_info(&[vk::DescriptorBufferInfo::default()
-                        .buffer(shader_constants)
+                        .buffer(buffer)
                         .offset(0)
                         .range(vk::WHOLE_SIZE)])],
                 &[],
             );
-            Ok(Self {
-                layout: layout.clone(),
-                pool,
-                set,
-                destroyed: false,
-            })
         }
     }
 
@@ -1,15 +1,228 @@
 use crate::ash_renderer::device::MyDevice;
+use crate::ash_renderer::error::RendererError;
 use crate::ash_renderer::global_descriptor_set::{GlobalDescriptorSet, GlobalDescriptorSetLayout};
-use anyhow::Context;
+use crate::ash_renderer::mesh::MyMesh;
+use crate::ash_renderer::render_pass::RenderPassManager;
+use crate::ash_renderer::swapchain::{AttachmentConfig, DEPTH_FORMAT};
+use anyhow::anyhow;
 use ash::vk;
+use mygraphics_shaders::{BindlessPushConstants, PUSH_CONSTANT_BUDGET, ShaderConstants};
+use std::ffi::CStr;
 use std::sync::Arc;
 
+/// How the pipeline passes [`ShaderConstants`] to the shaders
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ConstantsMode {
+    /// Push the constants into the command buffer, the simplest option for small constants
+    #[default]
+    PushConstants,
+    /// Read the constants from a storage buffer bound in the [`GlobalDescriptorSet`], to demonstrate descriptor sets
+    StorageBuffer,
+    /// Read the constants from a uniform buffer bound in the [`GlobalDescriptorSet`]
+    ///
+    /// Uniform buffers are the conventional choice for small read-only data, some GPUs read them through a faster
+    /// path than storage buffers. In exchange, they are limited in size and must follow the std140 layout, so the
+    /// constants are written as [`mygraphics_shaders::UniformConstants`] instead.
+    UniformBuffer,
+}
+
+impl ConstantsMode {
+    /// The type of the descriptor at binding 0 of the [`GlobalDescriptorSet`]. It's left unwritten with
+    /// [`ConstantsMode::PushConstants`], which needs no buffer.
+    pub fn descriptor_type(self) -> vk::DescriptorType {
+        match self {
+            ConstantsMode::PushConstants | ConstantsMode::StorageBuffer => {
+                vk::DescriptorType::STORAGE_BUFFER
+            }
+            ConstantsMode::UniformBuffer => vk::DescriptorType::UNIFORM_BUFFER,
+        }
+    }
+
+    /// The vertex and fragment entry points reading the constants the way this mode passes them
+    fn entry_points(
+        self,
+        vertex_input: bool,
+        texture: bool,
+        bindless: bool,
+    ) -> (&'static CStr, &'static CStr) {
+        match (self, vertex_input, texture) {
+            // only implemented for push constants without texture, see `MyRenderPipelineManager::new`
+            (ConstantsMode::PushConstants, true, false) if bindless => {
+                (c"main_vs_mesh_push", c"main_fs_bindless")
+            }
+            (ConstantsMode::PushConstants, false, false) if bindless => {
+                (c"main_vs_push", c"main_fs_bindless")
+            }
+            // meshes bring their own vertex colors and aren't textured
+            (ConstantsMode::PushConstants, true, _) => (c"main_vs_mesh_push", c"main_fs_push"),
+            (ConstantsMode::StorageBuffer, true, _) => (c"main_vs_mesh", c"main_fs"),
+            (ConstantsMode::UniformBuffer, true, _) => {
+                (c"main_vs_mesh_uniform", c"main_fs_uniform")
+            }
+            (ConstantsMode::PushConstants, false, true) => {
+                (c"main_vs_texture_push", c"main_fs_texture_push")
+            }
+            (ConstantsMode::StorageBuffer, false, true) => (c"main_vs_texture", c"main_fs_texture"),
+            (ConstantsMode::UniformBuffer, false, true) => {
+                (c"main_vs_texture_uniform", c"main_fs_texture_uniform")
+            }
+            (ConstantsMode::PushConstants, false, false) => (c"main_vs_push", c"main_fs_push"),
+            (ConstantsMode::StorageBuffer, false, false) => (c"main_vs", c"main_fs"),
+            (ConstantsMode::UniformBuffer, false, false) => {
+                (c"main_vs_uniform", c"main_fs_uniform")
+            }
+        }
+    }
+}
+
+/// How the pipeline assembles and rasterizes primitives, defaults to an unculled triangle list
+///
+/// The winding of triangles is defined with y pointing up, as in clip space: [`MyRenderPipeline::render`] flips the
+/// viewport, so a triangle that is counter-clockwise in clip space is counter-clockwise on screen. This makes
+/// [`vk::FrontFace::COUNTER_CLOCKWISE`] cull the same triangles as `wgpu::FrontFace::Ccw` on the wgpu renderer.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PrimitiveConfig {
+    pub topology: vk::PrimitiveTopology,
+    pub front_face: vk::FrontFace,
+    /// whether to cull triangles facing away, only correct for closed meshes with consistent winding
+    pub cull_back_faces: bool,
+}
+
+impl Default for PrimitiveConfig {
+    fn default() -> Self {
+        Self {
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            cull_back_faces: false,
+        }
+    }
+}
+
+/// How the push constants of a pipeline are split into ranges at distinct offsets, each only visible to some shader
+/// stages
+///
+/// Vulkan only guarantees 128 bytes of push constants, see [`Self::MAX_SIZE`]. Once the constants grow, splitting them
+/// into e.g. a vertex-only and a fragment-only range lets each stage read only the data it needs. Each stage may only
+/// be part of a single range, and its shaders must only read the bytes of that range.
+#[derive(Clone, Debug)]
+pub struct PushConstantLayout {
+    ranges: Vec<vk::PushConstantRange>,
+}
+
+impl PushConstantLayout {
+    /// The minimum `maxPushConstantsSize` guaranteed by Vulkan
+    pub const MAX_SIZE: u32 = PUSH_CONSTANT_BUDGET as u32;
+
+    /// Ranges visible to the given stages and of the given sizes in bytes, placed one after another in that order
+    pub fn new(
+        ranges: impl IntoIterator<Item = (vk::ShaderStageFlags, u32)>,
+    ) -> Result<Self, RendererError> {
+        let mut offset = 0;
+        let mut all_stages = vk::ShaderStageFlags::empty();
+        let mut push_constant_ranges = Vec::new();
+        for (stages, size) in ranges {
+            if size == 0 || size % 4 != 0 {
+                return Err(anyhow!(
+                    "Push constant range of {stages:?} has size {size}, which must be a nonzero multiple of 4"
+                )
+                .into());
+            }
+            if all_stages.intersects(stages) {
+                return Err(anyhow!(
+                    "Push constant stages {stages:?} are part of more than one range"
+                )
+                .into());
+            }
+            all_stages |= stages;
+            push_constant_ranges.push(
+                vk::PushConstantRange::default()
+                    .stage_flags(stages)
+                    .offset(offset)
+                    .size(size),
+            );
+            offset += size;
+        }
+        if offset > Self::MAX_SIZE {
+            return Err(anyhow!(
+                "Push constants of {offset} bytes exceed the {} bytes Vulkan guarantees",
+                Self::MAX_SIZE
+            )
+            .into());
+        }
+        Ok(Self {
+            ranges: push_constant_ranges,
+        })
+    }
+
+    /// A single range of `size` bytes visible to all graphics stages, how [`ShaderConstants`] are pushed by default
+    pub fn shared(size: u32) -> Result<Self, RendererError> {
+        Self::new([(vk::ShaderStageFlags::ALL_GRAPHICS, size)])
+    }
+
+    /// `vertex` bytes only visible to the vertex stage, followed by `fragment` bytes only visible to the fragment stage
+    pub fn split(vertex: u32, fragment: u32) -> Result<Self, RendererError> {
+        Self::new([
+            (vk::ShaderStageFlags::VERTEX, vertex),
+            (vk::ShaderStageFlags::FRAGMENT, fragment),
+        ])
+    }
+
+    #[inline]
+    pub fn ranges(&self) -> &[vk::PushConstantRange] {
+        &self.ranges
+    }
+
+    /// The size of all ranges together in bytes
+    pub fn size(&self) -> u32 {
+        self.ranges.iter().map(|range| range.size).sum()
+    }
+
+    /// Push `data` with one `cmd_push_constants` per range, each only with the stages of its range. Fails if `data`
+    /// isn't [`Self::size`] bytes long, instead of pushing constants the shaders misinterpret.
+    ///
+    /// # Safety
+    /// `cmd` must be recording and `layout` must have been created with [`Self::ranges`]
+    pub unsafe fn cmd_push(
+        &self,
+        device: &MyDevice,
+        cmd: vk::CommandBuffer,
+        layout: vk::PipelineLayout,
+        data: &[u8],
+    ) -> Result<(), RendererError> {
+        if data.len() != self.size() as usize {
+            return Err(anyhow!(
+                "Pushing {} bytes of constants, but the pipeline layout has {} bytes",
+                data.len(),
+                self.size()
+            )
+            .into());
+        }
+        for range in &self.ranges {
+            let bytes = &data[range.offset as usize..(range.offset + range.size) as usize];
+            unsafe {
+                device.cmd_push_constants(cmd, layout, range.stage_flags, range.offset, bytes);
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Manages the creation and recreation of [`MyRenderPipeline`], whenever new shader code ([`Self::set_shader_code`])
 /// is submitted
 pub struct MyRenderPipelineManager {
     pub device: Arc<MyDevice>,
     global_descriptor_set_layout: Arc<GlobalDescriptorSetLayout>,
     color_out_format: vk::Format,
+    attachments: AttachmentConfig,
+    constants: ConstantsMode,
+    primitive: PrimitiveConfig,
+    vertex_input: bool,
+    wireframe: bool,
+    sample_shading: Option<f32>,
+    bindless: bool,
+    push_constants: PushConstantLayout,
+    /// only without [`MyDevice::dynamic_rendering`]
+    render_pass: Option<RenderPassManager>,
     shader_code: Vec<u32>,
     pipeline: Option<MyRenderPipeline>,
     should_recreate: bool,
@@ -18,6 +231,44 @@ pub struct MyRenderPipelineManager {
 pub struct MyRenderPipeline {
     pub pipeline: vk::Pipeline,
     pub pipeline_layout: vk::PipelineLayout,
+    pub attachments: AttachmentConfig,
+    pub constants: ConstantsMode,
+    /// whether this pipeline draws a [`MyMesh`] from buffers or the vertexless triangle
+    pub vertex_input: bool,
+    /// whether this pipeline uses `main_fs_bindless` and pushes [`BindlessPushConstants`]
+    pub bindless: bool,
+    /// how the constants are pushed, only used with [`ConstantsMode::PushConstants`]
+    pub push_constants: PushConstantLayout,
+    /// the render pass this pipeline was created for, owned by the [`RenderPassManager`] of its manager. `None` if
+    /// the device uses [`MyDevice::dynamic_rendering`].
+    pub render_pass: Option<vk::RenderPass>,
+}
+
+/// The image views to render into, which must match the [`AttachmentConfig`] of the pipeline
+#[derive(Copy, Clone, Debug)]
+pub struct RenderAttachments {
+    pub extent: vk::Extent2D,
+    /// the final color image
+    pub color: vk::ImageView,
+    /// the depth image
+    pub depth: Option<vk::ImageView>,
+    /// the multisampled color image, which is resolved into `color`
+    pub msaa_color: Option<vk::ImageView>,
+    /// the framebuffer of these views from [`MyRenderPipelineManager::framebuffer`], required if the pipeline draws
+    /// with a [`MyRenderPipeline::render_pass`]
+    pub framebuffer: Option<vk::Framebuffer>,
+}
+
+/// The resources read by the shaders while drawing
+#[derive(Copy, Clone)]
+pub struct ShaderInputs<'a> {
+    pub descriptor_set: &'a GlobalDescriptorSet,
+    /// pushed if the pipeline uses [`ConstantsMode::PushConstants`], otherwise they must already be in the buffer of
+    /// `descriptor_set`
+    pub constants: &'a ShaderConstants,
+    /// the element of the bindless array tinting the output, only read if the pipeline is
+    /// [`MyRenderPipeline::bindless`]
+    pub tint_index: u32,
 }
 
 impl MyRenderPipelineManager {
@@ -25,12 +276,42 @@ impl MyRenderPipelineManager {
         device: Arc<MyDevice>,
         global_descriptor_set_layout: Arc<GlobalDescriptorSetLayout>,
         color_out_format: vk::Format,
+        attachments: AttachmentConfig,
+        constants: ConstantsMode,
+        primitive: PrimitiveConfig,
         shader_code: Vec<u32>,
-    ) -> anyhow::Result<Self> {
+    ) -> Result<Self, RendererError> {
+        let bindless = global_descriptor_set_layout.bindless
+            && constants == ConstantsMode::PushConstants
+            && !cfg!(feature = "texture");
+        if global_descriptor_set_layout.bindless && !bindless {
+            log::warn!(
+                "Bindless tints are only implemented with push constants and without the `texture` feature"
+            );
+        }
+        let push_constants = PushConstantLayout::shared(pushed_size(bindless))?;
+        let render_pass = if device.dynamic_rendering {
+            None
+        } else {
+            Some(RenderPassManager::new(
+                device.clone(),
+                color_out_format,
+                attachments,
+            )?)
+        };
         Ok(Self {
             device,
             global_descriptor_set_layout,
             color_out_format,
+            attachments,
+            constants,
+            primitive,
+            vertex_input: false,
+            wireframe: false,
+            sample_shading: None,
+            bindless,
+            push_constants,
+            render_pass,
             shader_code,
             pipeline: None,
             should_recreate: true,
@@ -43,12 +324,140 @@ impl MyRenderPipelineManager {
         self.should_recreate();
     }
 
+    /// Switch between drawing a [`MyMesh`] from buffers and the vertexless triangle
+    #[inline]
+    pub fn set_vertex_input(&mut self, vertex_input: bool) {
+        if self.vertex_input != vertex_input {
+            self.vertex_input = vertex_input;
+            self.should_recreate();
+        }
+    }
+
+    /// Draw the outlines of triangles instead of filling them, for debugging geometry. Requires
+    /// [`MyDevice::fill_mode_non_solid`], without it a warning is logged and triangles stay filled.
+    pub fn set_wireframe(&mut self, wireframe: bool) {
+        if wireframe && !self.device.fill_mode_non_solid {
+            log::warn!(
+                "Wireframe requires the fillModeNonSolid feature, which the device does not support"
+            );
+            return;
+        }
+        if self.wireframe != wireframe {
+            self.wireframe = wireframe;
+            self.should_recreate();
+        }
+    }
+
+    #[inline]
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    /// Run the fragment shader for at least `min_sample_shading` of the samples of every pixel with MSAA instead of
+    /// once per pixel, `Some(1.)` shades every sample individually. This smooths high-frequency detail within
+    /// triangles, not only their edges, at the cost of running the fragment shader more often. Requires
+    /// [`MyDevice::sample_rate_shading`], without it a warning is logged and pixels are shaded once.
+    pub fn set_sample_shading(&mut self, min_sample_shading: Option<f32>) {
+        if min_sample_shading.is_some() && !self.device.sample_rate_shading {
+            log::warn!(
+                "Sample shading requires the sampleRateShading feature, which the device does not support"
+            );
+            return;
+        }
+        if min_sample_shading.is_some() && !self.attachments.msaa() {
+            log::warn!("Sample shading has no effect without MSAA");
+        }
+        if self.sample_shading != min_sample_shading {
+            self.sample_shading = min_sample_shading;
+            self.should_recreate();
+        }
+    }
+
+    #[inline]
+    pub fn sample_shading(&self) -> Option<f32> {
+        self.sample_shading
+    }
+
     #[inline]
     pub fn should_recreate(&mut self) {
         self.should_recreate = true;
     }
 
-    pub fn get_pipeline(&mut self) -> anyhow::Result<&MyRenderPipeline> {
+    #[inline]
+    pub fn color_out_format(&self) -> vk::Format {
+        self.color_out_format
+    }
+
+    #[inline]
+    pub fn attachments(&self) -> AttachmentConfig {
+        self.attachments
+    }
+
+    #[inline]
+    pub fn constants(&self) -> ConstantsMode {
+        self.constants
+    }
+
+    #[inline]
+    pub fn primitive(&self) -> PrimitiveConfig {
+        self.primitive
+    }
+
+    /// Split the pushed constants into the ranges of `layout` instead of a single range visible to all graphics stages.
+    /// Its size must match the size of the pushed constants, and the shaders must only read the range of their stage.
+    pub fn set_push_constant_layout(
+        &mut self,
+        layout: PushConstantLayout,
+    ) -> Result<(), RendererError> {
+        let size = pushed_size(self.bindless);
+        if layout.size() != size {
+            return Err(anyhow!(
+                "Push constant layout of {} bytes doesn't match the {size} bytes of pushed constants",
+                layout.size()
+            )
+            .into());
+        }
+        if self.constants != ConstantsMode::PushConstants {
+            log::warn!(
+                "Push constant layout has no effect with {:?}",
+                self.constants
+            );
+        }
+        self.push_constants = layout;
+        self.should_recreate();
+        Ok(())
+    }
+
+    /// Change how primitives are assembled and rasterized, e.g. to cull the back faces of a closed mesh
+    #[inline]
+    pub fn set_primitive(&mut self, primitive: PrimitiveConfig) {
+        if self.primitive != primitive {
+            self.primitive = primitive;
+            self.should_recreate();
+        }
+    }
+
+    /// The framebuffer to draw `out` with, if the pipeline draws with a render pass instead of dynamic rendering
+    pub fn framebuffer(
+        &mut self,
+        out: &RenderAttachments,
+    ) -> Result<Option<vk::Framebuffer>, RendererError> {
+        self.render_pass
+            .as_mut()
+            .map(|manager| manager.framebuffer(out))
+            .transpose()
+    }
+
+    /// Destroys the framebuffers of [`Self::framebuffer`], which must be called before the image views they were
+    /// created from are destroyed
+    pub fn clear_framebuffers(&mut self) -> Result<(), RendererError> {
+        match &mut self.render_pass {
+            Some(manager) => manager.clear_framebuffers(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_pipeline(&mut self) -> Result<&MyRenderPipeline, RendererError> {
         if self.should_recreate {
             self.rebuild_pipeline()?;
         }
@@ -56,7 +465,7 @@ impl MyRenderPipelineManager {
     }
 
     /// Update shaders and rebuild the pipeline
-    fn rebuild_pipeline(&mut self) -> anyhow::Result<()> {
+    fn rebuild_pipeline(&mut self) -> Result<(), RendererError> {
         unsafe {
             self.destroy_pipeline()?;
 
@@ -65,87 +474,136 @@ impl MyRenderPipelineManager {
                 None,
             )?;
 
+            let push_constant_ranges = match self.constants {
+                ConstantsMode::PushConstants => self.push_constants.ranges(),
+                ConstantsMode::StorageBuffer | ConstantsMode::UniformBuffer => &[],
+            };
             let pipeline_layout = self.device.create_pipeline_layout(
                 &vk::PipelineLayoutCreateInfo::default()
-                    .set_layouts(&[self.global_descriptor_set_layout.layout]),
+                    .set_layouts(&[self.global_descriptor_set_layout.layout])
+                    .push_constant_ranges(push_constant_ranges),
                 None,
             )?;
 
+            let (vs_entry_point, fs_entry_point) = self.constants.entry_points(
+                self.vertex_input,
+                cfg!(feature = "texture"),
+                self.bindless,
+            );
+            let vertex_input_state = if self.vertex_input {
+                vk::PipelineVertexInputStateCreateInfo::default()
+                    .vertex_binding_descriptions(&MyMesh::VERTEX_BINDINGS)
+                    .vertex_attribute_descriptions(&MyMesh::VERTEX_ATTRIBUTES)
+            } else {
+                vk::PipelineVertexInputStateCreateInfo::default()
+            };
+
+            let stages = [
+                vk::PipelineShaderStageCreateInfo {
+                    module: shader_module,
+                    p_name: vs_entry_point.as_ptr(),
+                    stage: vk::ShaderStageFlags::VERTEX,
+                    ..Default::default()
+                },
+                vk::PipelineShaderStageCreateInfo {
+                    module: shader_module,
+                    p_name: fs_entry_point.as_ptr(),
+                    stage: vk::ShaderStageFlags::FRAGMENT,
+
+                    ..Default::default()
+                },
+            ];
+            let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo {
+                topology: self.primitive.topology,
+                ..Default::default()
+            };
+            let rasterization_state = vk::PipelineRasterizationStateCreateInfo {
+                front_face: self.primitive.front_face,
+                cull_mode: if self.primitive.cull_back_faces {
+                    vk::CullModeFlags::BACK
+                } else {
+                    vk::CullModeFlags::NONE
+                },
+                polygon_mode: if self.wireframe {
+                    vk::PolygonMode::LINE
+                } else {
+                    vk::PolygonMode::FILL
+                },
+                line_width: 1.0,
+                ..Default::default()
+            };
+            let multisample_state = vk::PipelineMultisampleStateCreateInfo {
+                rasterization_samples: self.attachments.samples,
+                sample_shading_enable: self.sample_shading.is_some().into(),
+                min_sample_shading: self.sample_shading.unwrap_or_default(),
+                ..Default::default()
+            };
+            let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::default()
+                .depth_test_enable(self.attachments.depth)
+                .depth_write_enable(self.attachments.depth)
+                .depth_compare_op(vk::CompareOp::LESS);
+            let color_blend_attachments = [vk::PipelineColorBlendAttachmentState {
+                blend_enable: 0,
+                src_color_blend_factor: vk::BlendFactor::SRC_COLOR,
+                dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_DST_COLOR,
+                color_blend_op: vk::BlendOp::ADD,
+                src_alpha_blend_factor: vk::BlendFactor::ZERO,
+                dst_alpha_blend_factor: vk::BlendFactor::ZERO,
+                alpha_blend_op: vk::BlendOp::ADD,
+                color_write_mask: vk::ColorComponentFlags::RGBA,
+            }];
+            let color_blend_state = vk::PipelineColorBlendStateCreateInfo::default()
+                .attachments(&color_blend_attachments);
+            let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+            let dynamic_state =
+                vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
+            let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+                .scissor_count(1)
+                .viewport_count(1);
+            let color_attachment_formats = [self.color_out_format];
+            let mut rendering_info = vk::PipelineRenderingCreateInfo::default()
+                .color_attachment_formats(&color_attachment_formats)
+                .depth_attachment_format(if self.attachments.depth {
+                    DEPTH_FORMAT
+                } else {
+                    vk::Format::UNDEFINED
+                });
+            let render_pass = self.render_pass.as_ref().map(|manager| manager.render_pass);
+
+            let mut create_info = vk::GraphicsPipelineCreateInfo::default()
+                .stages(&stages)
+                .vertex_input_state(&vertex_input_state)
+                .input_assembly_state(&input_assembly_state)
+                .rasterization_state(&rasterization_state)
+                .multisample_state(&multisample_state)
+                .depth_stencil_state(&depth_stencil_state)
+                .color_blend_state(&color_blend_state)
+                .dynamic_state(&dynamic_state)
+                .viewport_state(&viewport_state)
+                .layout(pipeline_layout);
+            create_info = match render_pass {
+                Some(render_pass) => create_info.render_pass(render_pass),
+                None => create_info.push_next(&mut rendering_info),
+            };
             let mut pipelines = self
                 .device
-                .create_graphics_pipelines(
-                    vk::PipelineCache::null(),
-                    &[vk::GraphicsPipelineCreateInfo::default()
-                        .stages(&[
-                            vk::PipelineShaderStageCreateInfo {
-                                module: shader_module,
-                                p_name: c"main_vs".as_ptr(),
-                                stage: vk::ShaderStageFlags::VERTEX,
-                                ..Default::default()
-                            },
-                            vk::PipelineShaderStageCreateInfo {
-                                module: shader_module,
-                                p_name: c"main_fs".as_ptr(),
-                                stage: vk::ShaderStageFlags::FRAGMENT,
-
-                                ..Default::default()
-                            },
-                        ])
-                        .vertex_input_state(&vk::PipelineVertexInputStateCreateInfo::default())
-                        .input_assembly_state(&vk::PipelineInputAssemblyStateCreateInfo {
-                            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
-                            ..Default::default()
-                        })
-                        .rasterization_state(&vk::PipelineRasterizationStateCreateInfo {
-                            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
-                            line_width: 1.0,
-                            ..Default::default()
-                        })
-                        .multisample_state(&vk::PipelineMultisampleStateCreateInfo {
-                            rasterization_samples: vk::SampleCountFlags::TYPE_1,
-                            ..Default::default()
-                        })
-                        .depth_stencil_state(&vk::PipelineDepthStencilStateCreateInfo::default())
-                        .color_blend_state(
-                            &vk::PipelineColorBlendStateCreateInfo::default().attachments(&[
-                                vk::PipelineColorBlendAttachmentState {
-                                    blend_enable: 0,
-                                    src_color_blend_factor: vk::BlendFactor::SRC_COLOR,
-                                    dst_color_blend_factor: vk::BlendFactor::ONE_MINUS_DST_COLOR,
-                                    color_blend_op: vk::BlendOp::ADD,
-                                    src_alpha_blend_factor: vk::BlendFactor::ZERO,
-                                    dst_alpha_blend_factor: vk::BlendFactor::ZERO,
-                                    alpha_blend_op: vk::BlendOp::ADD,
-                                    color_write_mask: vk::ColorComponentFlags::RGBA,
-                                },
-                            ]),
-                        )
-                        .dynamic_state(
-                            &vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&[
-                                vk::DynamicState::VIEWPORT,
-                                vk::DynamicState::SCISSOR,
-                            ]),
-                        )
-                        .viewport_state(
-                            &vk::PipelineViewportStateCreateInfo::default()
-                                .scissor_count(1)
-                                .viewport_count(1),
-                        )
-                        .layout(pipeline_layout)
-                        .push_next(
-                            &mut vk::PipelineRenderingCreateInfo::default()
-                                .color_attachment_formats(&[self.color_out_format]),
-                        )],
-                    None,
-                )
-                .map_err(|(_, e)| e)
-                .context("Unable to create graphics pipeline")?;
+                .create_graphics_pipelines(self.device.pipeline_cache, &[create_info], None)
+                .map_err(|(_, e)| RendererError::PipelineCreation(e))?;
 
             // A single `pipeline_info` results in a single pipeline.
             assert_eq!(pipelines.len(), 1);
+            self.device.set_object_name(pipelines[0], "render pipeline");
+            self.device
+                .set_object_name(pipeline_layout, "render pipeline layout");
             self.pipeline = pipelines.pop().map(|pipeline| MyRenderPipeline {
                 pipeline,
                 pipeline_layout,
+                attachments: self.attachments,
+                constants: self.constants,
+                vertex_input: self.vertex_input,
+                bindless: self.bindless,
+                push_constants: self.push_constants.clone(),
+                render_pass,
             });
 
             // shader modules are allowed to be deleted after the pipeline has been created
@@ -154,7 +612,7 @@ impl MyRenderPipelineManager {
         }
     }
 
-    unsafe fn destroy_pipeline(&mut self) -> anyhow::Result<()> {
+    unsafe fn destroy_pipeline(&mut self) -> Result<(), RendererError> {
         unsafe {
             if let Some(pipeline) = self.pipeline.take() {
                 // Figuring out when the pipeline stops being used is hard, so we take this shortcut
@@ -169,37 +627,81 @@ impl MyRenderPipelineManager {
     }
 }
 
+/// The size of the constants pushed by a pipeline that is `bindless` or not
+fn pushed_size(bindless: bool) -> u32 {
+    if bindless {
+        size_of::<BindlessPushConstants>() as u32
+    } else {
+        size_of::<ShaderConstants>() as u32
+    }
+}
+
 impl MyRenderPipeline {
     pub fn render(
         &self,
         device: &MyDevice,
         cmd: vk::CommandBuffer,
-        color_out: vk::ImageView,
-        extent: vk::Extent2D,
-        global_descriptor_set: &GlobalDescriptorSet,
-    ) -> anyhow::Result<()> {
+        out: RenderAttachments,
+        inputs: ShaderInputs<'_>,
+        clear_color: [f32; 4],
+        mesh: &MyMesh,
+    ) -> Result<(), RendererError> {
+        if self.vertex_input != mesh.vertex_input() {
+            return Err(anyhow!(
+                "Pipeline vertex input is {}, but the mesh needs {}",
+                self.vertex_input,
+                mesh.vertex_input()
+            )
+            .into());
+        }
+        let depth = match (self.attachments.depth, out.depth) {
+            (true, Some(depth)) => Some(depth),
+            (true, None) => {
+                return Err(
+                    anyhow!("Pipeline uses depth testing, but no depth image was given").into(),
+                );
+            }
+            (false, _) => None,
+        };
+        let msaa_color = match (self.attachments.msaa(), out.msaa_color) {
+            (true, Some(msaa_color)) => Some(msaa_color),
+            (true, None) => {
+                return Err(
+                    anyhow!("Pipeline uses multisampling, but no msaa image was given").into(),
+                );
+            }
+            (false, _) => None,
+        };
+        let render_pass = match (self.render_pass, out.framebuffer) {
+            (Some(render_pass), Some(framebuffer)) => Some((render_pass, framebuffer)),
+            (Some(_), None) => {
+                return Err(anyhow!(
+                    "Pipeline draws with a render pass, but no framebuffer was given"
+                )
+                .into());
+            }
+            (None, _) => None,
+        };
         unsafe {
+            let extent = out.extent;
             let render_area = vk::Rect2D {
                 offset: vk::Offset2D::default(),
                 extent,
             };
+            let color_clear = vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: clear_color,
+                },
+            };
+            let depth_clear = vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            };
 
-            device.cmd_begin_rendering(
-                cmd,
-                &vk::RenderingInfo::default()
-                    .render_area(render_area)
-                    .layer_count(1)
-                    .color_attachments(&[vk::RenderingAttachmentInfo::default()
-                        .image_view(color_out)
-                        .load_op(vk::AttachmentLoadOp::CLEAR)
-                        .store_op(vk::AttachmentStoreOp::STORE)
-                        .clear_value(vk::ClearValue {
-                            color: vk::ClearColorValue {
-                                float32: [0.0, 0.0, 0.0, 0.0],
-                            },
-                        })
-                        .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)]),
-            );
+            // everything but the draw itself is recorded before beginning rendering, so failing to push the
+            // constants doesn't leave a render pass instance open
             device.cmd_bind_pipeline(cmd, vk::PipelineBindPoint::GRAPHICS, self.pipeline);
             device.cmd_set_viewport(
                 cmd,
@@ -220,11 +722,86 @@ impl MyRenderPipeline {
                 vk::PipelineBindPoint::GRAPHICS,
                 self.pipeline_layout,
                 0,
-                &[global_descriptor_set.set],
+                &[inputs.descriptor_set.set],
                 &[],
             );
-            device.cmd_draw(cmd, 3, 1, 0, 0);
-            device.cmd_end_rendering(cmd);
+            if self.bindless {
+                self.push_constants.cmd_push(
+                    device,
+                    cmd,
+                    self.pipeline_layout,
+                    bytemuck::bytes_of(&BindlessPushConstants {
+                        constants: *inputs.constants,
+                        tint_index: inputs.tint_index,
+                    }),
+                )?;
+            } else if self.constants == ConstantsMode::PushConstants {
+                self.push_constants.cmd_push(
+                    device,
+                    cmd,
+                    self.pipeline_layout,
+                    bytemuck::bytes_of(inputs.constants),
+                )?;
+            }
+
+            if let Some((render_pass, framebuffer)) = render_pass {
+                // in the order of the attachments of the render pass
+                let clear_values = msaa_color
+                    .map(|_| color_clear)
+                    .into_iter()
+                    .chain([color_clear])
+                    .chain(depth.map(|_| depth_clear))
+                    .collect::<Vec<_>>();
+                device.cmd_begin_render_pass(
+                    cmd,
+                    &vk::RenderPassBeginInfo::default()
+                        .render_pass(render_pass)
+                        .framebuffer(framebuffer)
+                        .render_area(render_area)
+                        .clear_values(&clear_values),
+                    vk::SubpassContents::INLINE,
+                );
+            } else {
+                let depth_attachment = depth.map(|depth_out| {
+                    vk::RenderingAttachmentInfo::default()
+                        .image_view(depth_out)
+                        .load_op(vk::AttachmentLoadOp::CLEAR)
+                        .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                        .clear_value(depth_clear)
+                        .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+                });
+                let color_attachment = vk::RenderingAttachmentInfo::default()
+                    .load_op(vk::AttachmentLoadOp::CLEAR)
+                    .clear_value(color_clear)
+                    .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+                let color_attachment = match msaa_color {
+                    // render into the multisampled image, then resolve into `out.color` at the end of rendering
+                    Some(msaa_color) => color_attachment
+                        .image_view(msaa_color)
+                        .store_op(vk::AttachmentStoreOp::DONT_CARE)
+                        .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+                        .resolve_image_view(out.color)
+                        .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+                    None => color_attachment
+                        .image_view(out.color)
+                        .store_op(vk::AttachmentStoreOp::STORE),
+                };
+                let color_attachments = [color_attachment];
+                let mut rendering_info = vk::RenderingInfo::default()
+                    .render_area(render_area)
+                    .layer_count(1)
+                    .color_attachments(&color_attachments);
+                if let Some(depth_attachment) = depth_attachment.as_ref() {
+                    rendering_info = rendering_info.depth_attachment(depth_attachment);
+                }
+                device.cmd_begin_rendering(cmd, &rendering_info);
+            }
+            mesh.cmd_draw(device, cmd, inputs.constants.instances());
+            if render_pass.is_some() {
+                device.cmd_end_render_pass(cmd);
+            } else {
+                device.cmd_end_rendering(cmd);
+            }
             Ok(())
         }
     }
@@ -237,3 +814,39 @@ impl Drop for MyRenderPipelineManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn push_constant_layout() {
+        let split = PushConstantLayout::split(64, 16).unwrap();
+        let ranges = split
+            .ranges()
+            .iter()
+            .map(|r| (r.stage_flags, r.offset, r.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            [
+                (vk::ShaderStageFlags::VERTEX, 0, 64),
+                (vk::ShaderStageFlags::FRAGMENT, 64, 16),
+            ]
+        );
+        assert_eq!(split.size(), 80);
+
+        // the default fits the budget
+        PushConstantLayout::shared(size_of::<BindlessPushConstants>() as u32).unwrap();
+        // too large, unaligned and overlapping stages
+        assert!(PushConstantLayout::split(64, 68).is_err());
+        assert!(PushConstantLayout::split(62, 16).is_err());
+        assert!(
+            PushConstantLayout::new([
+                (vk::ShaderStageFlags::ALL_GRAPHICS, 16),
+                (vk::ShaderStageFlags::FRAGMENT, 16),
+            ])
+            .is_err()
+        );
+    }
+}
@@ -1,3 +1,7 @@
-pub fn main() -> anyhow::Result<()> {
-    mygraphics::ash_renderer::main()
+use mygraphics::ash_renderer::error::exit_code;
+use mygraphics::util::exit_with;
+use std::process::ExitCode;
+
+pub fn main() -> ExitCode {
+    exit_with(mygraphics::ash_renderer::main(), exit_code)
 }
@@ -0,0 +1,3 @@
+pub fn main() -> anyhow::Result<()> {
+    mygraphics::cpu_compute::main()
+}
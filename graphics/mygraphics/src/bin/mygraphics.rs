@@ -0,0 +1,70 @@
+//! Selects the renderer at runtime, with `--backend` or the `RENDERER_BACKEND` env var. Only this workspace contains
+//! several APIs, a generated project always uses the one selected by the `api` placeholder.
+
+use anyhow::bail;
+use clap::{Parser, ValueEnum};
+use mygraphics::util::{AppArgs, AppConfig, exit_with};
+use std::process::ExitCode;
+
+#[cfg(not(any(feature = "ash", feature = "wgpu", feature = "cpu")))]
+compile_error!("Enable at least one of the `ash`, `wgpu` or `cpu` features");
+
+#[derive(Copy, Clone, Debug, Default, ValueEnum)]
+enum Backend {
+    Ash,
+    #[default]
+    Wgpu,
+    Cpu,
+}
+
+impl Backend {
+    /// The name of both the feature and the `api` placeholder
+    fn name(self) -> &'static str {
+        match self {
+            Backend::Ash => "ash",
+            Backend::Wgpu => "wgpu",
+            Backend::Cpu => "cpu",
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// The renderer to use, defaults to the `RENDERER_BACKEND` env var or `wgpu`
+    #[arg(long, value_enum)]
+    backend: Option<Backend>,
+    #[command(flatten)]
+    app: AppArgs,
+}
+
+pub fn main() -> ExitCode {
+    #[cfg(feature = "ash")]
+    let exit_code = mygraphics::ash_renderer::error::exit_code;
+    // only the ash renderer reports errors with a more specific exit code
+    #[cfg(not(feature = "ash"))]
+    let exit_code = |_: &anyhow::Error| mygraphics::util::EXIT_FAILURE;
+    exit_with(run(), exit_code)
+}
+
+fn run() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let backend = match (args.backend, std::env::var("RENDERER_BACKEND")) {
+        (Some(backend), _) => backend,
+        (None, Ok(env)) => match Backend::from_str(&env, true) {
+            Ok(backend) => backend,
+            Err(e) => bail!("Invalid RENDERER_BACKEND: {e}"),
+        },
+        (None, Err(_)) => Backend::default(),
+    };
+    let run: fn(AppConfig) -> anyhow::Result<()> = match backend {
+        #[cfg(feature = "ash")]
+        Backend::Ash => mygraphics::ash_renderer::run,
+        #[cfg(feature = "wgpu")]
+        Backend::Wgpu => mygraphics::wgpu_renderer::run,
+        #[cfg(feature = "cpu")]
+        Backend::Cpu => mygraphics::cpu_renderer::run,
+        #[allow(unreachable_patterns)]
+        backend => bail!("Built without the `{}` feature", backend.name()),
+    };
+    run(AppConfig::new(format!("Rust GPU - {}", backend.name())).with_args(args.app))
+}
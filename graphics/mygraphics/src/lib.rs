@@ -1,3 +1,23 @@
+/// Enters a `tracing` span named `$name` until the end of the enclosing block if the `profiling` feature is enabled,
+/// otherwise expands to nothing
+#[cfg(any(feature = "ash", feature = "wgpu"))]
+macro_rules! profile_span {
+    ($name:literal) => {
+        #[cfg(feature = "profiling")]
+        let _span = tracing::info_span!($name).entered();
+    };
+}
+
+#[cfg(feature = "ash")]
+pub mod ash_compute;
+#[cfg(feature = "ash")]
 pub mod ash_renderer;
+#[cfg(feature = "cpu")]
+pub mod cpu_compute;
+#[cfg(feature = "cpu")]
+pub mod cpu_renderer;
 pub mod util;
+#[cfg(feature = "wgpu")]
+pub mod wgpu_compute;
+#[cfg(feature = "wgpu")]
 pub mod wgpu_renderer;
@@ -1,3 +1,1133 @@
+use anyhow::{Context, bail};
+use clap::Parser;
+use image::RgbaImage;
+use mygraphics_shaders::{SAXPY_A, SaxpyElement, ShaderConstants, Vertex};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::event::{ElementState, KeyEvent, MouseButton, WindowEvent};
+use winit::keyboard::{Key, NamedKey};
+use winit::window::{Fullscreen, Icon, Window, WindowAttributes};
+
 pub fn enable_debug_layer() -> bool {
     std::env::var("DEBUG_LAYER").is_ok_and(|e| !(e == "0" || e == "false"))
 }
+
+/// The exit code of any error without a more specific one, the same as returning the error from `main`
+pub const EXIT_FAILURE: u8 = 1;
+/// The exit code of failing to create the device or losing it
+pub const EXIT_DEVICE: u8 = 2;
+/// The exit code of failing to create or present to the swapchain
+pub const EXIT_SWAPCHAIN: u8 = 3;
+/// The exit code of failing to create a pipeline from the shaders
+pub const EXIT_SHADER: u8 = 4;
+
+/// Prints the error of `result` like returning it from `main` would, but exits with the code chosen by `exit_code`
+/// instead of always [`EXIT_FAILURE`], so scripts can tell the classes of failures apart
+pub fn exit_with(
+    result: anyhow::Result<()>,
+    exit_code: impl FnOnce(&anyhow::Error) -> u8,
+) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            ExitCode::from(exit_code(&e))
+        }
+    }
+}
+
+/// The window and GPU used by the `main` of each renderer, pass your own to their `run` to change it
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub title: String,
+    /// the initial size of the window's content area, in logical pixels
+    pub size: LogicalSize<u32>,
+    /// a PNG to use as the window icon, not supported on every platform
+    pub icon: Option<PathBuf>,
+    /// which GPU to render with, ignored by the cpu renderer
+    pub device: DevicePreference,
+    /// overrides the `PRESENT_MODE` of the renderer, ignored by the cpu renderer
+    pub present_mode: Option<PresentMode>,
+    /// whether to enable the Vulkan validation layers or wgpu validation
+    pub validation: bool,
+    /// render a single frame without a window, save it as a PNG to this path and exit
+    pub capture: Option<PathBuf>,
+    /// exit successfully after this many rendered frames, see [`RunLimit`]
+    pub max_frames: Option<u64>,
+    /// exit successfully after running this long, see [`RunLimit`]
+    pub max_duration: Option<Duration>,
+    /// load the shaders from this SPIR-V file at startup instead of the embedded ones, ignored by the cpu renderer
+    pub shader: Option<PathBuf>,
+}
+
+/// The command line arguments of the renderers, see [`AppConfig::from_args`]. Add them to your own arguments with
+/// `#[command(flatten)]` and apply them with [`AppConfig::with_args`].
+#[derive(Parser, Debug)]
+pub struct AppArgs {
+    /// The initial width of the window in logical pixels, or of the captured image in pixels
+    #[arg(long, default_value_t = 1280)]
+    width: u32,
+    /// The initial height of the window in logical pixels, or of the captured image in pixels
+    #[arg(long, default_value_t = 720)]
+    height: u32,
+    /// Present with vsync, instead of the default present mode of the renderer
+    #[arg(long)]
+    vsync: bool,
+    /// The GPU to use: `discrete`, `integrated`, `virtual`, `cpu` or a part of its name. Overrides `GPU_DEVICE`.
+    #[arg(long)]
+    adapter: Option<String>,
+    /// Enable validation, like setting `DEBUG_LAYER=1`
+    #[arg(long)]
+    validation: bool,
+    /// Render a single frame without a window, save it as a PNG to this path and exit
+    #[arg(long)]
+    capture: Option<PathBuf>,
+    /// Exit after rendering this many frames, for smoke tests
+    #[arg(long)]
+    max_frames: Option<u64>,
+    /// Exit after running for this many seconds, for smoke tests
+    #[arg(long, value_parser = parse_seconds)]
+    max_duration: Option<Duration>,
+    /// Load the shaders from this SPIR-V file instead of the ones embedded at compile time
+    #[arg(long)]
+    shader: Option<PathBuf>,
+}
+
+fn parse_seconds(arg: &str) -> Result<Duration, String> {
+    let seconds = arg.parse::<f64>().map_err(|e| e.to_string())?;
+    Duration::try_from_secs_f64(seconds).map_err(|e| e.to_string())
+}
+
+impl AppConfig {
+    /// A 1280x720 window without an icon, rendered by the GPU selected with [`DevicePreference::from_env`]
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            size: LogicalSize::new(1280, 720),
+            icon: None,
+            device: DevicePreference::from_env(),
+            present_mode: None,
+            validation: enable_debug_layer(),
+            capture: None,
+            max_frames: None,
+            max_duration: None,
+            shader: None,
+        }
+    }
+
+    /// Like [`Self::new`], but configured by the command line arguments. Run with `--help` to list them.
+    pub fn from_args(title: impl Into<String>) -> Self {
+        Self::new(title).with_args(AppArgs::parse())
+    }
+
+    /// Apply the parsed command line arguments
+    pub fn with_args(mut self, args: AppArgs) -> Self {
+        self.size = LogicalSize::new(args.width, args.height);
+        if args.vsync {
+            self.present_mode = Some(PresentMode::Vsync);
+        }
+        if let Some(adapter) = args.adapter {
+            self.device = DevicePreference::parse(&adapter);
+        }
+        self.validation |= args.validation;
+        self.capture = args.capture;
+        self.max_frames = args.max_frames;
+        self.max_duration = args.max_duration;
+        self.shader = args.shader;
+        self
+    }
+
+    pub fn window_attributes(&self) -> anyhow::Result<WindowAttributes> {
+        let icon = self.icon.as_deref().map(load_icon).transpose()?;
+        Ok(Window::default_attributes()
+            .with_title(&self.title)
+            .with_inner_size(self.size)
+            .with_window_icon(icon))
+    }
+}
+
+fn load_icon(path: &Path) -> anyhow::Result<Icon> {
+    let rgba = image::open(path)
+        .with_context(|| format!("Failed to load window icon {}", path.display()))?
+        .to_rgba8();
+    let (width, height) = rgba.dimensions();
+    Ok(Icon::from_rgba(rgba.into_raw(), width, height)?)
+}
+
+/// The kind of a GPU, as reported by the driver
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DeviceType {
+    DiscreteGpu,
+    IntegratedGpu,
+    VirtualGpu,
+    /// a software implementation, e.g. llvmpipe or `SwiftShader`
+    Cpu,
+    Other,
+}
+
+/// The name and type of a GPU, to select one with [`select_device`]
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub device_type: DeviceType,
+}
+
+impl Display for DeviceInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:?})", self.name, self.device_type)
+    }
+}
+
+/// Which GPU to use if there are multiple
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum DevicePreference {
+    /// Prefer discrete over virtual over integrated GPUs, and those over software implementations
+    #[default]
+    Default,
+    /// The first device whose name contains this string, ignoring case
+    Name(String),
+    /// The first device of this type
+    Type(DeviceType),
+}
+
+impl DevicePreference {
+    /// Reads the `GPU_DEVICE` environment variable, which is either `discrete`, `integrated`, `virtual`, `cpu` or a
+    /// part of the device name. Unset or empty selects [`DevicePreference::Default`].
+    pub fn from_env() -> Self {
+        match std::env::var("GPU_DEVICE") {
+            Ok(value) => Self::parse(&value),
+            Err(_) => Self::Default,
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "" => Self::Default,
+            "discrete" => Self::Type(DeviceType::DiscreteGpu),
+            "integrated" => Self::Type(DeviceType::IntegratedGpu),
+            "virtual" => Self::Type(DeviceType::VirtualGpu),
+            "cpu" => Self::Type(DeviceType::Cpu),
+            _ => Self::Name(value.to_string()),
+        }
+    }
+}
+
+/// Selects the index of the device in `devices` matching `preference`, failing with a list of all devices if there
+/// is none
+pub fn select_device(
+    devices: &[DeviceInfo],
+    preference: &DevicePreference,
+) -> anyhow::Result<usize> {
+    let selected = match preference {
+        DevicePreference::Default => devices
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, device)| match device.device_type {
+                DeviceType::DiscreteGpu => 1,
+                DeviceType::VirtualGpu => 2,
+                DeviceType::IntegratedGpu => 3,
+                DeviceType::Cpu => 4,
+                DeviceType::Other => 5,
+            })
+            .map(|(index, _)| index),
+        DevicePreference::Name(name) => {
+            let name = name.to_lowercase();
+            devices
+                .iter()
+                .position(|device| device.name.to_lowercase().contains(&name))
+        }
+        DevicePreference::Type(device_type) => devices
+            .iter()
+            .position(|device| device.device_type == *device_type),
+    };
+    selected.with_context(|| {
+        let available = devices
+            .iter()
+            .map(|device| format!("\n  {device}"))
+            .collect::<String>();
+        format!("No device matches {preference:?}, available devices:{available}")
+    })
+}
+
+/// How finished frames are presented by the swapchain. Modes not supported by the surface fall back to
+/// [`PresentMode::Vsync`], which is always available.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum PresentMode {
+    /// Wait for the vertical blank, limiting the framerate to the refresh rate of the display
+    #[default]
+    Vsync,
+    /// Present immediately without waiting for the vertical blank, lowest latency but may tear
+    Immediate,
+    /// Replace the frame waiting for the vertical blank with the newest one, low latency without tearing
+    Mailbox,
+}
+
+impl PresentMode {
+    /// The mode to switch to when toggling at runtime
+    pub fn next(self) -> Self {
+        match self {
+            Self::Vsync => Self::Immediate,
+            Self::Immediate => Self::Mailbox,
+            Self::Mailbox => Self::Vsync,
+        }
+    }
+}
+
+/// How the linear colors written by the fragment shaders are encoded for the display, decided by the surface format
+///
+/// The swapchains prefer sRGB formats, but a surface may not offer any, in which case the shaders have to do the
+/// encoding. Deciding it here makes every renderer look the same, whichever format it ends up with.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum OutputColorSpace {
+    /// An sRGB format, the hardware encodes the colors when they are written
+    Srgb,
+    /// A UNORM format displayed as sRGB, the shaders encode the colors themselves
+    Unorm,
+    /// A linear HDR format, no encoding is needed
+    Hdr,
+}
+
+impl OutputColorSpace {
+    pub fn new(is_srgb: bool, is_hdr: bool) -> Self {
+        match (is_srgb, is_hdr) {
+            (_, true) => Self::Hdr,
+            (true, false) => Self::Srgb,
+            (false, false) => Self::Unorm,
+        }
+    }
+
+    /// Write [`ShaderConstants::encode_srgb`]
+    pub fn update_constants(self, constants: &mut ShaderConstants) {
+        constants.encode_srgb = u32::from(self == Self::Unorm);
+    }
+}
+
+/// The interface shared by the renderers of every API, for code that should not care which one it draws with
+pub trait Renderer {
+    /// What a frame is drawn into: a `DrawFrame` of the swapchain for ash, a `TextureView` for wgpu and nothing for
+    /// the cpu renderer, which always draws into its window
+    type Target<'a>;
+
+    /// Draw a single frame with `shader_constants` into `target`
+    fn render(
+        &mut self,
+        shader_constants: &ShaderConstants,
+        target: Self::Target<'_>,
+    ) -> anyhow::Result<()>;
+
+    /// Draw a single frame of the size in `shader_constants` without a window and read it back
+    fn capture(&mut self, shader_constants: &ShaderConstants) -> anyhow::Result<RgbaImage>;
+
+    /// Set the color the output is cleared to before drawing, in linear RGBA
+    fn set_clear_color(&mut self, clear_color: [f32; 4]);
+}
+
+/// The largest difference between the channels of any pixel of two images of the same size
+pub fn max_channel_diff(a: &RgbaImage, b: &RgbaImage) -> anyhow::Result<u8> {
+    if a.dimensions() != b.dimensions() {
+        bail!(
+            "Image sizes differ: {:?} and {:?}",
+            a.dimensions(),
+            b.dimensions()
+        );
+    }
+    Ok(a.as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(a, b)| a.abs_diff(*b))
+        .max()
+        .unwrap_or(0))
+}
+
+/// Compares `image` to the golden image at `path`, failing if any channel differs by more than `tolerance`. If the
+/// `UPDATE_GOLDEN` environment variable is set, `image` is written to `path` instead.
+pub fn compare_golden(image: &RgbaImage, path: &Path, tolerance: u8) -> anyhow::Result<()> {
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        image
+            .save(path)
+            .with_context(|| format!("Failed to write golden image {}", path.display()))?;
+        return Ok(());
+    }
+    let golden = image::open(path)
+        .with_context(|| {
+            format!(
+                "Failed to load golden image {}, run with UPDATE_GOLDEN=1 to create it",
+                path.display()
+            )
+        })?
+        .to_rgba8();
+    let diff = max_channel_diff(image, &golden)
+        .with_context(|| format!("Comparing to golden image {}", path.display()))?;
+    if diff > tolerance {
+        bail!(
+            "Image differs from golden image {} by {diff}, more than the tolerance of {tolerance}. If the change is \
+            intended, run with UPDATE_GOLDEN=1 to update it.",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// How long the window size must be stable after a resize before the swapchain is recreated. Until then, we keep
+/// presenting with the old swapchain, unless the surface rejects it.
+pub const RESIZE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// The first word of every SPIR-V module
+pub const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/// Checks that `spv` looks like a little-endian SPIR-V module, a whole number of words starting with [`SPIRV_MAGIC`].
+/// Only the header is checked, the rest is validated when creating the shader module.
+pub fn validate_spirv(spv: &[u8]) -> anyhow::Result<()> {
+    if !spv.len().is_multiple_of(4) {
+        bail!(
+            "SPIR-V of {} bytes is not a whole number of words",
+            spv.len()
+        );
+    }
+    if !spv.starts_with(&SPIRV_MAGIC.to_le_bytes()) {
+        bail!("SPIR-V doesn't start with the magic number {SPIRV_MAGIC:#010x}");
+    }
+    Ok(())
+}
+
+/// Reads and validates the SPIR-V module at `path` at runtime, the counterpart to the modules embedded in
+/// [`SHADER_MODULES`] for shipping shaders as separate files
+pub fn read_spirv_file(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let spv =
+        std::fs::read(path).with_context(|| format!("Failed to read shader {}", path.display()))?;
+    validate_spirv(&spv).with_context(|| format!("Invalid shader {}", path.display()))?;
+    Ok(spv)
+}
+
+/// A SPIR-V module compiled from one of the shader crates by the build script
+#[derive(Copy, Clone, Debug)]
+pub struct ShaderModule {
+    /// the name of the shader crate
+    pub name: &'static str,
+    /// where the build script wrote the module, to watch it for changes
+    pub path: &'static str,
+    pub spv: &'static [u8],
+}
+
+/// The shader modules to choose from at runtime, cycled by pressing `S`. Each entry must have a matching shader crate
+/// in `SHADER_CRATES` of the build script.
+pub const SHADER_MODULES: &[ShaderModule] = &[ShaderModule {
+    name: "mygraphics-shaders",
+    path: env!("SHADER_SPV_PATH_MYGRAPHICS_SHADERS"),
+    spv: include_bytes!(env!("SHADER_SPV_PATH_MYGRAPHICS_SHADERS")),
+}];
+
+/// The vertices of a quad covering the center of the screen, to be drawn with [`QUAD_INDICES`]
+pub const QUAD_VERTICES: [Vertex; 4] = [
+    Vertex {
+        position: [-0.5, -0.5],
+        color: [1., 0., 0.],
+    },
+    Vertex {
+        position: [0.5, -0.5],
+        color: [0., 1., 0.],
+    },
+    Vertex {
+        position: [0.5, 0.5],
+        color: [0., 0., 1.],
+    },
+    Vertex {
+        position: [-0.5, 0.5],
+        color: [1., 1., 0.],
+    },
+];
+
+/// The two triangles making up the quad of [`QUAD_VERTICES`]
+pub const QUAD_INDICES: [u32; 6] = [0, 1, 2, 0, 2, 3];
+
+/// Decodes the texture sampled by `main_fs_texture`, which is embedded into the executable
+#[cfg(feature = "texture")]
+pub fn load_texture() -> anyhow::Result<image::RgbaImage> {
+    let png = include_bytes!("../assets/texture.png");
+    Ok(image::load_from_memory_with_format(png, image::ImageFormat::Png)?.to_rgba8())
+}
+
+/// How many elements the compute examples process, deliberately not a multiple of the workgroup size
+pub const SAXPY_LEN: usize = 1000;
+
+/// The input of the compute examples, with `y` being overwritten by the result
+pub fn saxpy_input() -> Vec<SaxpyElement> {
+    (0..SAXPY_LEN)
+        .map(|i| SaxpyElement { x: i as f32, y: 1. })
+        .collect()
+}
+
+/// Prints the first few results computed by `main_cs` and checks all of them against the expected values
+pub fn print_saxpy(input: &[SaxpyElement], output: &[SaxpyElement]) -> anyhow::Result<()> {
+    if input.len() != output.len() {
+        bail!("Expected {} results, got {}", input.len(), output.len());
+    }
+    for (input, output) in input.iter().zip(output).take(8) {
+        println!("{SAXPY_A} * {} + {} = {}", input.x, input.y, output.y);
+    }
+    for (i, (input, output)) in input.iter().zip(output).enumerate() {
+        let expected = SAXPY_A * input.x + input.y;
+        if output.y != expected {
+            bail!("Element {i} is {}, expected {expected}", output.y);
+        }
+    }
+    println!("all {} results are correct", output.len());
+    Ok(())
+}
+
+/// Tracks the cursor and mouse buttons from [`WindowEvent`]s, to be forwarded to the shader via [`ShaderConstants`]
+#[derive(Copy, Clone, Debug)]
+pub struct MouseState {
+    pub cursor: [f32; 2],
+    pub drag_start: [f32; 2],
+    pub drag_end: [f32; 2],
+    /// Bit mask of the pressed buttons (0 = Left, 1 = Middle, 2 = Right).
+    pub button_pressed: u32,
+    /// The last time each button was pressed, see [`ShaderConstants::mouse_button_press_time`]
+    pub button_press_time: [f32; 3],
+}
+
+impl Default for MouseState {
+    fn default() -> Self {
+        Self {
+            cursor: [0.; 2],
+            drag_start: [0.; 2],
+            drag_end: [0.; 2],
+            button_pressed: 0,
+            button_press_time: [f32::NEG_INFINITY; 3],
+        }
+    }
+}
+
+impl MouseState {
+    /// Update the mouse state, `time` should be the same clock as [`ShaderConstants::time`].
+    pub fn window_event(&mut self, event: &WindowEvent, time: f32) {
+        match *event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                if self.button_pressed & 1 != 0 {
+                    self.drag_end = self.cursor;
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let index = match button {
+                    MouseButton::Left => 0,
+                    MouseButton::Middle => 1,
+                    MouseButton::Right => 2,
+                    _ => return,
+                };
+                let mask = 1 << index;
+                match state {
+                    ElementState::Pressed => {
+                        self.button_pressed |= mask;
+                        self.button_press_time[index] = time;
+                        if button == MouseButton::Left {
+                            self.drag_start = self.cursor;
+                            self.drag_end = self.cursor;
+                        }
+                    }
+                    ElementState::Released => self.button_pressed &= !mask,
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Write the mouse related fields of `constants`
+    pub fn update_constants(&self, constants: &mut ShaderConstants) {
+        [constants.cursor_x, constants.cursor_y] = self.cursor;
+        [constants.drag_start_x, constants.drag_start_y] = self.drag_start;
+        [constants.drag_end_x, constants.drag_end_y] = self.drag_end;
+        constants.mouse_button_pressed = self.button_pressed;
+        constants.mouse_button_press_time = self.button_press_time;
+    }
+}
+
+/// Decides when to exit the event loop, after [`AppConfig::max_frames`] rendered frames or once
+/// [`AppConfig::max_duration`] has passed. Lets CI run the real event loop as a smoke test.
+#[derive(Clone, Debug)]
+pub struct RunLimit {
+    frames_left: Option<u64>,
+    deadline: Option<Instant>,
+}
+
+impl RunLimit {
+    /// Starts counting the duration now
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            frames_left: config.max_frames,
+            deadline: config
+                .max_duration
+                .map(|duration| Instant::now() + duration),
+        }
+    }
+
+    /// Call after every rendered frame
+    pub fn frame_rendered(&mut self) {
+        if let Some(frames_left) = &mut self.frames_left {
+            *frames_left = frames_left.saturating_sub(1);
+        }
+    }
+
+    /// Whether either limit has been reached at `now`
+    pub fn reached(&self, now: Instant) -> bool {
+        self.frames_left == Some(0) || self.deadline.is_some_and(|deadline| now >= deadline)
+    }
+}
+
+/// The min, average and max time of a series of frames, as measured by [`bench`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FrameTimes {
+    pub frames: u32,
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+}
+
+impl FrameTimes {
+    /// `None` if there are no `times`
+    pub fn new(times: &[Duration]) -> Option<Self> {
+        let frames = u32::try_from(times.len())
+            .ok()
+            .filter(|&frames| frames > 0)?;
+        Some(Self {
+            frames,
+            min: *times.iter().min()?,
+            avg: times.iter().sum::<Duration>() / frames,
+            max: *times.iter().max()?,
+        })
+    }
+}
+
+impl Display for FrameTimes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.;
+        write!(
+            f,
+            "{} frames: min {:.3} ms, avg {:.3} ms, max {:.3} ms ({:.1} FPS avg)",
+            self.frames,
+            ms(self.min),
+            ms(self.avg),
+            ms(self.max),
+            1. / self.avg.as_secs_f64(),
+        )
+    }
+}
+
+/// Renders `frames` frames with [`Renderer::capture`] in a tight loop and measures each of them, after a single
+/// warm-up frame that isn't measured
+///
+/// `capture` waits for the GPU and reads the image back, so every time includes recording on the CPU, executing on the
+/// GPU and the readback, without any overlap between frames.
+pub fn bench(
+    renderer: &mut impl Renderer,
+    shader_constants: &ShaderConstants,
+    frames: u32,
+) -> anyhow::Result<FrameTimes> {
+    // the first frame creates pipelines and allocates memory
+    renderer.capture(shader_constants)?;
+    let times = (0..frames)
+        .map(|_| {
+            let start = Instant::now();
+            renderer.capture(shader_constants)?;
+            Ok(start.elapsed())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    FrameTimes::new(&times).context("Benchmark needs at least one frame")
+}
+
+/// Toggles the window between windowed and borderless fullscreen, bound to `F11`
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FullscreenState {
+    /// the size of the window before entering fullscreen, to restore it when leaving
+    windowed_size: Option<PhysicalSize<u32>>,
+}
+
+impl FullscreenState {
+    pub fn toggle(&mut self, window: &Window) {
+        if window.fullscreen().is_some() {
+            window.set_fullscreen(None);
+            if let Some(size) = self.windowed_size.take() {
+                // the new size arrives as a `Resized` event, which recreates the swapchain
+                let _ = window.request_inner_size(size);
+            }
+        } else {
+            self.windowed_size = Some(window.inner_size());
+            window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+    }
+}
+
+/// How fast the camera accelerates while a key is held, in clip space units per second squared
+const CAMERA_ACCELERATION: f32 = 8.;
+
+/// How quickly the camera comes to a halt, its velocity decays by `exp(-CAMERA_DAMPING * dt)`
+const CAMERA_DAMPING: f32 = 6.;
+
+/// A camera controlled with the keyboard: the arrow keys pan, `PageUp` / `PageDown` move along z and `+` / `-` zoom
+///
+/// Keys accelerate the camera instead of moving it directly and damping slows it down again, both scaled by the time
+/// between frames so the camera moves the same at any framerate.
+#[derive(Copy, Clone, Debug)]
+pub struct CameraState {
+    pub position: [f32; 3],
+    pub zoom: f32,
+    /// the velocity of `position` and of the logarithm of `zoom`
+    velocity: [f32; 4],
+    /// Bit mask of the held keys, see [`Self::key_bit`]
+    held: u8,
+    last_update: Option<f32>,
+}
+
+impl Default for CameraState {
+    fn default() -> Self {
+        Self {
+            position: [0.; 3],
+            zoom: 1.,
+            velocity: [0.; 4],
+            held: 0,
+            last_update: None,
+        }
+    }
+}
+
+impl CameraState {
+    /// Each axis has a key pair: bit `2 * axis` moves in the positive and bit `2 * axis + 1` in the negative direction
+    fn key_bit(key: &Key) -> Option<u8> {
+        Some(match key {
+            Key::Named(NamedKey::ArrowRight) => 0,
+            Key::Named(NamedKey::ArrowLeft) => 1,
+            Key::Named(NamedKey::ArrowUp) => 2,
+            Key::Named(NamedKey::ArrowDown) => 3,
+            Key::Named(NamedKey::PageUp) => 4,
+            Key::Named(NamedKey::PageDown) => 5,
+            // `=` shares a key with `+` on most layouts
+            Key::Character(c) if c == "+" || c == "=" => 6,
+            Key::Character(c) if c == "-" => 7,
+            _ => return None,
+        })
+    }
+
+    pub fn window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            event: KeyEvent {
+                logical_key, state, ..
+            },
+            ..
+        } = event
+            && let Some(bit) = Self::key_bit(logical_key)
+        {
+            match state {
+                ElementState::Pressed => self.held |= 1 << bit,
+                ElementState::Released => self.held &= !(1 << bit),
+            }
+        }
+    }
+
+    /// Advance the camera to `time`, which should be the same clock as [`ShaderConstants::time`]
+    pub fn update(&mut self, time: f32) {
+        let dt = self.last_update.map_or(0., |last| (time - last).max(0.));
+        self.last_update = Some(time);
+        let damping = (-CAMERA_DAMPING * dt).exp();
+        for (axis, velocity) in self.velocity.iter_mut().enumerate() {
+            let input =
+                f32::from(self.held >> (2 * axis) & 1) - f32::from(self.held >> (2 * axis + 1) & 1);
+            *velocity = (*velocity + input * CAMERA_ACCELERATION * dt) * damping;
+        }
+        // pan slower while zoomed in, so the scene moves at the same speed on screen
+        self.position[0] += self.velocity[0] * dt / self.zoom;
+        self.position[1] += self.velocity[1] * dt / self.zoom;
+        self.position[2] += self.velocity[2] * dt;
+        self.zoom *= (self.velocity[3] * dt).exp();
+    }
+
+    /// Write the camera related fields of `constants`
+    pub fn update_constants(&self, constants: &mut ShaderConstants) {
+        constants.camera_pos = self.position;
+        constants.zoom = self.zoom;
+    }
+}
+
+/// How far the left and right arrow keys step a paused [`ShaderClock`], one frame at 60 FPS
+const CLOCK_STEP: f32 = 1. / 60.;
+
+/// The factor `,` and `.` divide and multiply the [`ShaderClock::scale`] by
+const CLOCK_SCALE_STEP: f32 = 2.;
+
+/// The clock driving [`ShaderConstants::time`], advanced by the real time between frames times [`Self::scale`]
+///
+/// `Space` pauses it, `,` and `.` halve and double its speed and while paused, the left and right arrow keys step it
+/// back and forth by one frame. Unlike the time since startup, this lets you freeze and scrub through animations.
+#[derive(Copy, Clone, Debug)]
+pub struct ShaderClock {
+    /// the current time, in seconds
+    pub accumulated: f32,
+    pub scale: f32,
+    pub paused: bool,
+    last_update: Option<f32>,
+}
+
+impl Default for ShaderClock {
+    fn default() -> Self {
+        Self {
+            accumulated: 0.,
+            scale: 1.,
+            paused: false,
+            last_update: None,
+        }
+    }
+}
+
+impl ShaderClock {
+    /// Handles the keys controlling the clock, returns whether `event` was one of them. The arrow keys are only taken
+    /// while paused, otherwise they are left to the [`CameraState`].
+    pub fn window_event(&mut self, event: &WindowEvent) -> bool {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    logical_key,
+                    state: ElementState::Pressed,
+                    repeat,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return false;
+        };
+        self.key_pressed(logical_key, *repeat)
+    }
+
+    fn key_pressed(&mut self, key: &Key, repeat: bool) -> bool {
+        match key {
+            Key::Named(NamedKey::Space) if !repeat => self.paused = !self.paused,
+            Key::Named(NamedKey::ArrowRight) if self.paused => self.accumulated += CLOCK_STEP,
+            Key::Named(NamedKey::ArrowLeft) if self.paused => self.accumulated -= CLOCK_STEP,
+            Key::Character(c) if c == "," => self.scale /= CLOCK_SCALE_STEP,
+            Key::Character(c) if c == "." => self.scale *= CLOCK_SCALE_STEP,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Advance the clock to the real time `time`, e.g. the time since startup. Returns [`Self::accumulated`].
+    pub fn update(&mut self, time: f32) -> f32 {
+        let dt = self.last_update.map_or(0., |last| (time - last).max(0.));
+        self.last_update = Some(time);
+        if !self.paused {
+            self.accumulated += dt * self.scale;
+        }
+        self.accumulated
+    }
+}
+
+/// How often [`FrameStats`] reports a summary
+const FRAME_STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many frame times [`FrameStats`] keeps per interval, any further frames only contribute to the average
+const FRAME_STATS_CAPACITY: usize = 2048;
+
+/// Accumulates frame times and summarizes them once per [`FRAME_STATS_INTERVAL`], without allocating per frame
+pub struct FrameStats {
+    title: String,
+    last_frame: Option<Instant>,
+    elapsed: Duration,
+    frames: u32,
+    frame_times: Vec<Duration>,
+    gpu_elapsed: Duration,
+    gpu_frames: u32,
+}
+
+/// The frame times of the last interval of [`FrameStats`]
+#[derive(Copy, Clone, Debug)]
+pub struct FrameSummary {
+    pub fps: f32,
+    pub p99_frame_time: Duration,
+    /// the average GPU time per frame, if the renderer measures it, see [`FrameStats::record_gpu_time`]
+    pub mean_gpu_time: Option<Duration>,
+}
+
+impl Display for FrameSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.1} fps, 99th percentile {:.2?}",
+            self.fps, self.p99_frame_time
+        )?;
+        if let Some(gpu_time) = self.mean_gpu_time {
+            write!(f, ", GPU {gpu_time:.2?}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FrameStats {
+    /// `title` is the window title to append the summary to
+    pub fn new(title: String) -> Self {
+        Self {
+            title,
+            last_frame: None,
+            elapsed: Duration::ZERO,
+            frames: 0,
+            frame_times: Vec::with_capacity(FRAME_STATS_CAPACITY),
+            gpu_elapsed: Duration::ZERO,
+            gpu_frames: 0,
+        }
+    }
+
+    pub fn record(&mut self, dt: Duration) {
+        self.elapsed += dt;
+        self.frames += 1;
+        if self.frame_times.len() < self.frame_times.capacity() {
+            self.frame_times.push(dt);
+        }
+    }
+
+    /// Records the time the GPU spent on a frame
+    pub fn record_gpu_time(&mut self, dt: Duration) {
+        self.gpu_elapsed += dt;
+        self.gpu_frames += 1;
+    }
+
+    /// Returns the summary of the current interval and starts the next one, or `None` if the interval isn't over yet
+    pub fn summary(&mut self) -> Option<FrameSummary> {
+        if self.elapsed < FRAME_STATS_INTERVAL || self.frame_times.is_empty() {
+            return None;
+        }
+        self.frame_times.sort_unstable();
+        let p99_index = (self.frame_times.len() * 99).div_ceil(100) - 1;
+        let summary = FrameSummary {
+            fps: self.frames as f32 / self.elapsed.as_secs_f32(),
+            p99_frame_time: self.frame_times[p99_index],
+            mean_gpu_time: (self.gpu_frames > 0).then(|| self.gpu_elapsed / self.gpu_frames),
+        };
+        self.elapsed = Duration::ZERO;
+        self.frames = 0;
+        self.frame_times.clear();
+        self.gpu_elapsed = Duration::ZERO;
+        self.gpu_frames = 0;
+        Some(summary)
+    }
+
+    /// Records the time since the previous call, then logs the summary and shows it in the window title once it's
+    /// available
+    pub fn tick(&mut self, window: &Window) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            self.record(now - last_frame);
+        }
+        if let Some(summary) = self.summary() {
+            log::info!("{summary}");
+            window.set_title(&format!("{} - {summary}", self.title));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn validate_spirv_header() {
+        validate_spirv(SHADER_MODULES[0].spv).unwrap();
+        let mut spv = SPIRV_MAGIC.to_le_bytes().to_vec();
+        validate_spirv(&spv).unwrap();
+        spv.push(0);
+        assert!(validate_spirv(&spv).is_err());
+        assert!(validate_spirv(&SPIRV_MAGIC.to_be_bytes()).is_err());
+        assert!(validate_spirv(&[]).is_err());
+    }
+
+    #[test]
+    pub fn frame_times() {
+        let ms = Duration::from_millis;
+        let times = FrameTimes::new(&[ms(4), ms(2), ms(6)]).unwrap();
+        assert_eq!(
+            times,
+            FrameTimes {
+                frames: 3,
+                min: ms(2),
+                avg: ms(4),
+                max: ms(6),
+            }
+        );
+        assert_eq!(
+            times.to_string(),
+            "3 frames: min 2.000 ms, avg 4.000 ms, max 6.000 ms (250.0 FPS avg)"
+        );
+        assert_eq!(FrameTimes::new(&[]), None);
+    }
+
+    fn devices() -> Vec<DeviceInfo> {
+        [
+            ("llvmpipe (LLVM 19.1.7, 256 bits)", DeviceType::Cpu),
+            ("Intel(R) Graphics (RPL-S)", DeviceType::IntegratedGpu),
+            ("NVIDIA GeForce RTX 4070", DeviceType::DiscreteGpu),
+        ]
+        .map(|(name, device_type)| DeviceInfo {
+            name: name.to_string(),
+            device_type,
+        })
+        .to_vec()
+    }
+
+    #[test]
+    pub fn select_device_preference() {
+        let devices = devices();
+        let select = |preference| select_device(&devices, &preference).unwrap();
+        assert_eq!(select(DevicePreference::Default), 2);
+        assert_eq!(select(DevicePreference::Name("intel".to_string())), 1);
+        assert_eq!(select(DevicePreference::Type(DeviceType::Cpu)), 0);
+        assert_eq!(select(DevicePreference::parse("Integrated")), 1);
+        assert_eq!(select(DevicePreference::parse("RTX")), 2);
+        assert_eq!(DevicePreference::parse(""), DevicePreference::Default);
+    }
+
+    #[test]
+    pub fn max_channel_diff_images() {
+        let a = RgbaImage::from_pixel(2, 2, image::Rgba([10, 20, 30, 255]));
+        let mut b = a.clone();
+        assert_eq!(max_channel_diff(&a, &b).unwrap(), 0);
+        b.put_pixel(1, 0, image::Rgba([10, 27, 25, 255]));
+        assert_eq!(max_channel_diff(&a, &b).unwrap(), 7);
+        assert!(max_channel_diff(&a, &RgbaImage::new(2, 3)).is_err());
+    }
+
+    #[test]
+    pub fn select_device_no_match() {
+        let error = select_device(&devices(), &DevicePreference::Type(DeviceType::VirtualGpu))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("NVIDIA GeForce RTX 4070 (DiscreteGpu)"),
+            "{error}"
+        );
+        assert!(select_device(&[], &DevicePreference::Default).is_err());
+    }
+
+    #[test]
+    pub fn camera_framerate_independent() {
+        // hold the right arrow and `+` for one second
+        let simulate = |fps: u32| {
+            let mut camera = CameraState {
+                held: 1 << 0 | 1 << 6,
+                ..CameraState::default()
+            };
+            for frame in 0..=fps {
+                camera.update(frame as f32 / fps as f32);
+            }
+            camera
+        };
+        let slow = simulate(30);
+        let fast = simulate(240);
+        assert!(fast.position[0] > 0.1 && fast.zoom > 1., "{fast:?}");
+        assert!(
+            (slow.position[0] / fast.position[0] - 1.).abs() < 0.1,
+            "{slow:?} {fast:?}"
+        );
+        assert!(
+            (slow.zoom / fast.zoom - 1.).abs() < 0.1,
+            "{slow:?} {fast:?}"
+        );
+
+        // once released, damping brings the camera to a halt
+        let mut camera = fast;
+        camera.held = 0;
+        camera.update(10.);
+        let position = camera.position;
+        camera.update(11.);
+        assert_eq!(camera.position, position);
+    }
+
+    #[test]
+    pub fn shader_clock() {
+        let char = |c: &str| Key::Character(c.into());
+        let mut clock = ShaderClock::default();
+        assert_eq!(clock.update(10.), 0.);
+        assert_eq!(clock.update(11.), 1.);
+
+        // the arrow keys belong to the camera while running
+        assert!(!clock.key_pressed(&Key::Named(NamedKey::ArrowRight), false));
+        assert!(clock.key_pressed(&char("."), false));
+        assert_eq!(clock.update(12.), 3.);
+
+        assert!(clock.key_pressed(&Key::Named(NamedKey::Space), false));
+        assert_eq!(clock.update(13.), 3.);
+        assert!(clock.key_pressed(&Key::Named(NamedKey::ArrowLeft), false));
+        assert_eq!(clock.update(14.), 3. - CLOCK_STEP);
+
+        assert!(clock.key_pressed(&Key::Named(NamedKey::Space), false));
+        assert!(clock.key_pressed(&char(","), false));
+        assert!(clock.key_pressed(&char(","), false));
+        assert!((clock.update(16.) - (4. - CLOCK_STEP)).abs() < 1e-5);
+    }
+
+    #[test]
+    pub fn app_config_args() {
+        let parse = |args: &[&str]| {
+            let args = AppArgs::try_parse_from(["mygraphics"].iter().chain(args)).unwrap();
+            AppConfig::new("test").with_args(args)
+        };
+        let config = parse(&[]);
+        assert_eq!(config.size, LogicalSize::new(1280, 720));
+        assert_eq!(config.present_mode, None);
+        assert_eq!(config.capture, None);
+
+        let config = parse(&[
+            "--width",
+            "800",
+            "--height",
+            "600",
+            "--vsync",
+            "--adapter",
+            "discrete",
+            "--validation",
+            "--capture",
+            "out.png",
+            "--max-frames",
+            "3",
+            "--max-duration",
+            "1.5",
+        ]);
+        assert_eq!(config.size, LogicalSize::new(800, 600));
+        assert_eq!(config.present_mode, Some(PresentMode::Vsync));
+        assert_eq!(
+            config.device,
+            DevicePreference::Type(DeviceType::DiscreteGpu)
+        );
+        assert!(config.validation);
+        assert_eq!(config.capture.as_deref(), Some(Path::new("out.png")));
+        assert_eq!(config.max_frames, Some(3));
+        assert_eq!(config.max_duration, Some(Duration::from_millis(1500)));
+        assert!(AppArgs::try_parse_from(["mygraphics", "--max-duration", "-1"]).is_err());
+    }
+
+    #[test]
+    pub fn run_limit() {
+        let now = Instant::now();
+        let unlimited = RunLimit::new(&AppConfig::new("test"));
+        assert!(!unlimited.reached(now + Duration::from_secs(3600)));
+
+        let mut frames = RunLimit::new(&AppConfig {
+            max_frames: Some(2),
+            ..AppConfig::new("test")
+        });
+        frames.frame_rendered();
+        assert!(!frames.reached(now));
+        frames.frame_rendered();
+        assert!(frames.reached(now));
+
+        let duration = RunLimit::new(&AppConfig {
+            max_duration: Some(Duration::from_secs(10)),
+            ..AppConfig::new("test")
+        });
+        assert!(!duration.reached(now));
+        assert!(duration.reached(now + Duration::from_secs(11)));
+    }
+}
@@ -1,89 +1,277 @@
-use crate::wgpu_renderer::renderer::MyRenderer;
+use crate::util::{
+    AppConfig, CameraState, DeviceInfo, DevicePreference, DeviceType, FullscreenState, MouseState,
+    PresentMode, QUAD_INDICES, QUAD_VERTICES, RunLimit, SHADER_MODULES, ShaderClock, select_device,
+};
+use crate::wgpu_renderer::mesh::MyMesh;
+#[cfg(feature = "egui")]
+use crate::wgpu_renderer::overlay::Overlay;
+use crate::wgpu_renderer::renderer::{MyRenderer, validate_sample_count, validate_sample_shading};
 use crate::wgpu_renderer::swapchain::MySwapchainManager;
 use anyhow::Context;
-use mygraphics_shaders::ShaderConstants;
+use bytemuck::Zeroable;
+use mygraphics_shaders::{PUSH_CONSTANT_BUDGET, ShaderConstants};
 use pollster::block_on;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
 use winit::event_loop::EventLoop;
 use winit::{
     application::ApplicationHandler,
-    dpi::LogicalSize,
     event::{ElementState, KeyEvent, WindowEvent},
     event_loop::ActiveEventLoop,
     keyboard::{Key, NamedKey},
     window::{Window, WindowId},
 };
 
+pub mod mesh;
+pub mod multi_window;
+#[cfg(feature = "egui")]
+pub mod overlay;
 mod render_pipeline;
-mod renderer;
-mod swapchain;
+pub mod renderer;
+pub mod swapchain;
+#[cfg(feature = "texture")]
+pub mod texture;
+
+/// The MSAA sample count, 1 disables multisampling. 4 is always supported, others may not be.
+pub const MSAA_SAMPLES: u32 = 1;
+
+/// Whether to shade every sample individually with MSAA instead of once per pixel. Requires the `sample-shading`
+/// feature, see [`validate_sample_shading`].
+pub const SAMPLE_SHADING: bool = cfg!(feature = "sample-shading");
+
+/// The initial present mode, toggled at runtime by pressing `V`. Falls back to vsync if unsupported.
+pub const PRESENT_MODE: PresentMode = PresentMode::Vsync;
+
+/// The preferred surface format, falls back to an 8-bit sRGB format if `None` or unsupported.
+pub const SURFACE_FORMAT: Option<wgpu::TextureFormat> = None;
+
+/// Whether to render to an HDR surface if available, overriding [`SURFACE_FORMAT`]. See [`swapchain::HDR_FORMAT`]
+/// for platform support.
+pub const HDR: bool = false;
+
+/// Whether to draw a quad from a vertex and index buffer, instead of the vertexless triangle.
+pub const DRAW_QUAD: bool = false;
+
+/// How many instances of the vertexless triangle to draw, arranged in a grid.
+pub const INSTANCE_COUNT: u32 = 1;
+
+/// Select the adapter matching `preference`, which must be able to present to `surface` if given
+///
+/// [`DevicePreference::Default`] lets wgpu decide, which also respects the `WGPU_ADAPTER_NAME` environment variable.
+pub async fn select_adapter(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface<'_>>,
+    preference: &DevicePreference,
+) -> anyhow::Result<wgpu::Adapter> {
+    let adapter = if *preference == DevicePreference::Default {
+        wgpu::util::initialize_adapter_from_env_or_default(instance, surface).await?
+    } else {
+        let mut adapters = instance.enumerate_adapters(wgpu::Backends::all()).await;
+        adapters
+            .retain(|adapter| surface.is_none_or(|surface| adapter.is_surface_supported(surface)));
+        let infos = adapters
+            .iter()
+            .map(|adapter| {
+                let info = adapter.get_info();
+                DeviceInfo {
+                    name: info.name,
+                    device_type: match info.device_type {
+                        wgpu::DeviceType::DiscreteGpu => DeviceType::DiscreteGpu,
+                        wgpu::DeviceType::IntegratedGpu => DeviceType::IntegratedGpu,
+                        wgpu::DeviceType::VirtualGpu => DeviceType::VirtualGpu,
+                        wgpu::DeviceType::Cpu => DeviceType::Cpu,
+                        wgpu::DeviceType::Other => DeviceType::Other,
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+        adapters.swap_remove(select_device(&infos, preference)?)
+    };
+    let info = adapter.get_info();
+    log::info!(
+        "Selected adapter {} ({:?}, {:?})",
+        info.name,
+        info.device_type,
+        info.backend
+    );
+    Ok(adapter)
+}
+
+/// Request a device with the features and limits required by [`MyRenderer`]
+pub async fn request_device(
+    adapter: &wgpu::Adapter,
+) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    let mut required_features = wgpu::Features::IMMEDIATES;
+    // required for sample counts other than 1 and 4
+    if adapter
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    {
+        required_features |= wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+    }
+    // optional, for toggling wireframe with `W`
+    if adapter
+        .features()
+        .contains(wgpu::Features::POLYGON_MODE_LINE)
+    {
+        required_features |= wgpu::Features::POLYGON_MODE_LINE;
+    }
+    let required_limits = wgpu::Limits {
+        // the minimum every Vulkan implementation must support, `ShaderConstants` must fit within it
+        max_immediate_size: PUSH_CONSTANT_BUDGET as u32,
+        ..Default::default()
+    };
+    adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: None,
+            required_features,
+            required_limits,
+            experimental_features: wgpu::ExperimentalFeatures::disabled(),
+            memory_hints: wgpu::MemoryHints::Performance,
+            trace: Default::default(),
+        })
+        .await
+        .context("Failed to create device")
+}
 
 pub fn main() -> anyhow::Result<()> {
+    run(AppConfig::from_args("Rust GPU - wgpu"))
+}
+
+/// Like [`main`], but with a custom title, size or icon for the window
+pub fn run(config: AppConfig) -> anyhow::Result<()> {
     env_logger::init();
+    if let Some(path) = &config.capture {
+        return block_on(capture(&config, path));
+    }
     let event_loop = EventLoop::new()?;
-    let mut app = App::default();
+    let mut app = App::new(config);
     event_loop.run_app(&mut app)?;
     Ok(())
 }
 
-#[derive(Default)]
-pub struct App(Option<State>);
+/// Create an instance, enabling validation if requested by `config`
+fn create_instance(config: &AppConfig, mut descriptor: wgpu::InstanceDescriptor) -> wgpu::Instance {
+    if config.validation {
+        descriptor.flags |= wgpu::InstanceFlags::debugging();
+    }
+    wgpu::Instance::new(descriptor)
+}
+
+/// Render a single frame of the size in `config` without a window and save it to `path`
+pub async fn capture(config: &AppConfig, path: &Path) -> anyhow::Result<()> {
+    let instance = create_instance(
+        config,
+        wgpu::InstanceDescriptor::new_without_display_handle_from_env(),
+    );
+    let adapter = select_adapter(&instance, None, &config.device).await?;
+    let (device, queue) = request_device(&adapter).await?;
+    let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+    let sample_count = validate_sample_count(&adapter, &device, format, MSAA_SAMPLES)?;
+    let mut renderer = MyRenderer::new(device, queue, format, sample_count)?;
+    if let Some(shader) = &config.shader {
+        renderer.reload_shader_from_path(shader)?;
+    }
+    renderer.set_sample_shading(validate_sample_shading(
+        &adapter,
+        sample_count,
+        SAMPLE_SHADING,
+    ));
+    if DRAW_QUAD {
+        let mesh = MyMesh::new(&renderer.device, &QUAD_VERTICES, &QUAD_INDICES);
+        renderer.set_mesh(Some(mesh))?;
+    }
+    let (width, height) = (config.size.width, config.size.height);
+    let image = renderer.capture_frame(
+        width,
+        height,
+        &ShaderConstants {
+            width,
+            height,
+            instance_count: INSTANCE_COUNT,
+            ..Zeroable::zeroed()
+        },
+    )?;
+    image.save(path)?;
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+pub struct App {
+    config: AppConfig,
+    limit: RunLimit,
+    state: Option<State>,
+}
+
+impl App {
+    pub fn new(config: AppConfig) -> Self {
+        Self {
+            limit: RunLimit::new(&config),
+            config,
+            state: None,
+        }
+    }
+}
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if self.0.is_none() {
-            self.0 = Some(block_on(State::new(event_loop)).unwrap());
+        if self.state.is_none() {
+            self.state = Some(block_on(State::new(event_loop, &self.config)).unwrap());
         }
     }
 
     fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
-        let state = self.0.as_mut().unwrap();
+        let state = self.state.as_mut().unwrap();
+        let redraw = matches!(event, WindowEvent::RedrawRequested);
         state.window_event(event_loop, id, event).unwrap();
+        if redraw {
+            self.limit.frame_rendered();
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.limit.reached(Instant::now()) {
+            event_loop.exit();
+            return;
+        }
+        // continuously redraw
+        if let Some(state) = &self.state {
+            state.window.request_redraw();
+        }
     }
 }
 
 struct State {
     start: Instant,
+    clock: ShaderClock,
+    mouse: MouseState,
+    camera: CameraState,
+    fullscreen: FullscreenState,
+    present_mode: PresentMode,
+    /// index into [`SHADER_MODULES`]
+    shader_module: usize,
     window: Arc<Window>,
     renderer: MyRenderer,
     swapchain: MySwapchainManager<'static>,
+    #[cfg(feature = "egui")]
+    overlay: Overlay,
 }
 
 impl State {
-    async fn new(event_loop: &ActiveEventLoop) -> anyhow::Result<Self> {
-        let window = Arc::new(
-            event_loop.create_window(
-                Window::default_attributes()
-                    .with_title("Rust GPU - wgpu")
-                    .with_inner_size(LogicalSize::new(1280, 720)),
-            )?,
-        );
+    async fn new(event_loop: &ActiveEventLoop, config: &AppConfig) -> anyhow::Result<Self> {
+        let window = Arc::new(event_loop.create_window(config.window_attributes()?)?);
 
-        let instance =
-            wgpu::Instance::new(wgpu::InstanceDescriptor::new_with_display_handle_from_env(
-                Box::new(event_loop.owned_display_handle()),
-            ));
+        let instance = create_instance(
+            config,
+            wgpu::InstanceDescriptor::new_with_display_handle_from_env(Box::new(
+                event_loop.owned_display_handle(),
+            )),
+        );
         let surface = instance.create_surface(window.clone())?;
-        let adapter =
-            wgpu::util::initialize_adapter_from_env_or_default(&instance, Some(&surface)).await?;
-
-        let required_features = wgpu::Features::IMMEDIATES;
-        let required_limits = wgpu::Limits {
-            max_immediate_size: 128,
-            ..Default::default()
-        };
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features,
-                required_limits,
-                experimental_features: wgpu::ExperimentalFeatures::disabled(),
-                memory_hints: wgpu::MemoryHints::Performance,
-                trace: Default::default(),
-            })
-            .await
-            .context("Failed to create device")?;
+        let adapter = select_adapter(&instance, Some(&surface), &config.device).await?;
+
+        let (device, queue) = request_device(&adapter).await?;
 
         let swapchain = MySwapchainManager::new(
             instance.clone(),
@@ -91,13 +279,47 @@ impl State {
             device.clone(),
             window.clone(),
             surface,
-        );
-        let renderer = MyRenderer::new(device, queue, swapchain.format())?;
+            config.present_mode.unwrap_or(PRESENT_MODE),
+            if HDR {
+                Some(swapchain::HDR_FORMAT)
+            } else {
+                SURFACE_FORMAT
+            },
+        )?;
+        if HDR && !swapchain.is_hdr() {
+            log::warn!("HDR is not supported by the surface, falling back to SDR");
+        }
+        log::info!("Output color space {:?}", swapchain.color_space());
+        let sample_count =
+            validate_sample_count(&adapter, &device, swapchain.format(), MSAA_SAMPLES)?;
+        let mut renderer = MyRenderer::new(device, queue, swapchain.format(), sample_count)?;
+        if let Some(shader) = &config.shader {
+            renderer.reload_shader_from_path(shader)?;
+        }
+        renderer.set_sample_shading(validate_sample_shading(
+            &adapter,
+            sample_count,
+            SAMPLE_SHADING,
+        ));
+        if DRAW_QUAD {
+            let mesh = MyMesh::new(&renderer.device, &QUAD_VERTICES, &QUAD_INDICES);
+            renderer.set_mesh(Some(mesh))?;
+        }
+        #[cfg(feature = "egui")]
+        let overlay = Overlay::new(&renderer.device, &window, swapchain.format());
         Ok(Self {
             start: Instant::now(),
+            clock: ShaderClock::default(),
+            mouse: MouseState::default(),
+            camera: CameraState::default(),
+            fullscreen: FullscreenState::default(),
+            present_mode: config.present_mode.unwrap_or(PRESENT_MODE),
+            shader_module: 0,
             window,
             swapchain,
             renderer,
+            #[cfg(feature = "egui")]
+            overlay,
         })
     }
 
@@ -107,19 +329,44 @@ impl State {
         _id: WindowId,
         event: WindowEvent,
     ) -> anyhow::Result<()> {
+        #[cfg(feature = "egui")]
+        if self.overlay.window_event(&self.window, &event) {
+            return Ok(());
+        }
         match event {
             WindowEvent::RedrawRequested => {
+                let max_luminance = self.swapchain.max_luminance();
+                let color_space = self.swapchain.color_space();
+                let real_time = self.start.elapsed().as_secs_f32();
                 self.swapchain.render(|render_target| {
-                    self.renderer.render(
-                        &ShaderConstants {
-                            time: self.start.elapsed().as_secs_f32(),
-                            width: render_target.texture().width(),
-                            height: render_target.texture().height(),
-                        },
-                        render_target,
-                    )
+                    let mut shader_constants = ShaderConstants {
+                        time: self.clock.update(real_time),
+                        width: render_target.texture().width(),
+                        height: render_target.texture().height(),
+                        max_luminance,
+                        instance_count: INSTANCE_COUNT,
+                        ..Zeroable::zeroed()
+                    };
+                    self.mouse.update_constants(&mut shader_constants);
+                    self.camera.update(real_time);
+                    self.camera.update_constants(&mut shader_constants);
+                    color_space.update_constants(&mut shader_constants);
+                    #[cfg(feature = "egui")]
+                    {
+                        self.overlay
+                            .update(&self.window, &mut self.clock, &mut shader_constants);
+                        self.renderer
+                            .render(&shader_constants, render_target.clone())?;
+                        self.overlay.paint(
+                            &self.renderer.device,
+                            &self.renderer.queue,
+                            &render_target,
+                        );
+                        Ok(())
+                    }
+                    #[cfg(not(feature = "egui"))]
+                    self.renderer.render(&shader_constants, render_target)
                 })?;
-                self.window.request_redraw();
             }
             WindowEvent::KeyboardInput {
                 event:
@@ -131,7 +378,73 @@ impl State {
                 ..
             }
             | WindowEvent::CloseRequested => event_loop.exit(),
-            WindowEvent::Resized(_) => self.swapchain.should_recreate(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if key.eq_ignore_ascii_case("v") => {
+                self.present_mode = self.present_mode.next();
+                self.swapchain.set_present_mode(self.present_mode);
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if key.eq_ignore_ascii_case("s") => {
+                self.shader_module = (self.shader_module + 1) % SHADER_MODULES.len();
+                let module = &SHADER_MODULES[self.shader_module];
+                log::info!("Selected shader module {}", module.name);
+                self.renderer.reload_shader(module.spv)?;
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(key),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if key.eq_ignore_ascii_case("w") => {
+                self.renderer.set_wireframe(!self.renderer.wireframe());
+            }
+            WindowEvent::Resized(_) => self.swapchain.resized(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::F11),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => self.fullscreen.toggle(&self.window),
+            #[cfg(feature = "egui")]
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::F1),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => self.overlay.visible = !self.overlay.visible,
+            WindowEvent::KeyboardInput { .. } if self.clock.window_event(&event) => (),
+            WindowEvent::KeyboardInput { .. } => self.camera.window_event(&event),
+            WindowEvent::CursorMoved { .. } | WindowEvent::MouseInput { .. } => {
+                self.mouse.window_event(&event, self.clock.accumulated);
+            }
             _ => (),
         }
         Ok(())
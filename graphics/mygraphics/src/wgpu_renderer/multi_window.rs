@@ -0,0 +1,208 @@
+//! Multiple windows, each with its own [`MySwapchainManager`], drawn by a single [`MyRenderer`] on one device
+
+use crate::util::{AppConfig, RunLimit};
+use crate::wgpu_renderer::renderer::{MyRenderer, validate_sample_count};
+use crate::wgpu_renderer::swapchain::MySwapchainManager;
+use crate::wgpu_renderer::{
+    INSTANCE_COUNT, MSAA_SAMPLES, PRESENT_MODE, SURFACE_FORMAT, create_instance, request_device,
+    select_adapter,
+};
+use anyhow::{Context, bail};
+use bytemuck::Zeroable;
+use mygraphics_shaders::ShaderConstants;
+use pollster::block_on;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::{
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::ActiveEventLoop,
+    keyboard::{Key, NamedKey},
+    window::WindowId,
+};
+
+/// Like [`super::run`], but opens `window_count` windows sharing one device and renderer. Closing a window keeps
+/// drawing the others, closing the last one or pressing `Escape` exits.
+pub fn run(config: AppConfig, window_count: usize) -> anyhow::Result<()> {
+    env_logger::init();
+    let event_loop = EventLoop::new()?;
+    let mut app = App {
+        limit: RunLimit::new(&config),
+        config,
+        window_count,
+        state: None,
+        error: None,
+    };
+    event_loop.run_app(&mut app)?;
+    app.error.map_or(Ok(()), Err)
+}
+
+struct App {
+    config: AppConfig,
+    window_count: usize,
+    limit: RunLimit,
+    state: Option<State>,
+    /// the error that made the app exit, returned by [`run`] to report it with the exit code of `main`
+    error: Option<anyhow::Error>,
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_none() {
+            match block_on(State::new(event_loop, &self.config, self.window_count)) {
+                Ok(state) => self.state = Some(state),
+                Err(e) => {
+                    self.error = Some(e);
+                    event_loop.exit();
+                }
+            }
+        }
+        // every window is drawn in `about_to_wait`, instead of requesting a redraw of each
+        event_loop.set_control_flow(ControlFlow::Poll);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        // gone if creating it failed and the app is exiting
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Escape),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => event_loop.exit(),
+            WindowEvent::CloseRequested => {
+                state.swapchains.remove(&id);
+                if state.swapchains.is_empty() {
+                    event_loop.exit();
+                }
+            }
+            WindowEvent::Resized(_) => {
+                if let Some(swapchain) = state.swapchains.get_mut(&id) {
+                    swapchain.resized();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if self.limit.reached(Instant::now()) {
+            event_loop.exit();
+            return;
+        }
+        if let Some(state) = &mut self.state {
+            if let Err(e) = state.render() {
+                self.error.get_or_insert(e);
+                event_loop.exit();
+                return;
+            }
+            self.limit.frame_rendered();
+        }
+    }
+}
+
+struct State {
+    start: Instant,
+    renderer: MyRenderer,
+    /// one per open window, all presenting with the device of `renderer`
+    swapchains: HashMap<WindowId, MySwapchainManager<'static>>,
+}
+
+impl State {
+    async fn new(
+        event_loop: &ActiveEventLoop,
+        config: &AppConfig,
+        window_count: usize,
+    ) -> anyhow::Result<Self> {
+        let windows = (0..window_count)
+            .map(|i| {
+                let attributes =
+                    config
+                        .window_attributes()?
+                        .with_title(format!("{} {}", config.title, i + 1));
+                Ok(Arc::new(event_loop.create_window(attributes)?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let instance = create_instance(
+            config,
+            wgpu::InstanceDescriptor::new_with_display_handle_from_env(Box::new(
+                event_loop.owned_display_handle(),
+            )),
+        );
+        let surfaces = windows
+            .iter()
+            .map(|window| instance.create_surface(window.clone()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let adapter = select_adapter(&instance, surfaces.first(), &config.device).await?;
+        let (device, queue) = request_device(&adapter).await?;
+
+        // the pipeline of the renderer is created for a single format, which all windows must share
+        let mut out_format = None;
+        let mut swapchains = HashMap::new();
+        for (window, surface) in windows.into_iter().zip(surfaces) {
+            if !adapter.is_surface_supported(&surface) {
+                bail!("The adapter can't present to window {}", window.title());
+            }
+            let swapchain = MySwapchainManager::new(
+                instance.clone(),
+                adapter.clone(),
+                device.clone(),
+                window.clone(),
+                surface,
+                config.present_mode.unwrap_or(PRESENT_MODE),
+                out_format.or(SURFACE_FORMAT),
+            )?;
+            if let Some(out_format) = out_format
+                && swapchain.format() != out_format
+            {
+                bail!(
+                    "Window {} has format {:?}, but the other windows have {out_format:?}",
+                    window.title(),
+                    swapchain.format()
+                );
+            }
+            out_format = Some(swapchain.format());
+            swapchains.insert(window.id(), swapchain);
+        }
+        let out_format = out_format.context("No windows to render to")?;
+
+        let sample_count = validate_sample_count(&adapter, &device, out_format, MSAA_SAMPLES)?;
+        let renderer = MyRenderer::new(device, queue, out_format, sample_count)?;
+        Ok(Self {
+            start: Instant::now(),
+            renderer,
+            swapchains,
+        })
+    }
+
+    /// Draw every window with the shared renderer, each with the size of its own surface
+    fn render(&mut self) -> anyhow::Result<()> {
+        let time = self.start.elapsed().as_secs_f32();
+        for swapchain in self.swapchains.values_mut() {
+            let max_luminance = swapchain.max_luminance();
+            let color_space = swapchain.color_space();
+            swapchain.render(|render_target| {
+                let mut shader_constants = ShaderConstants {
+                    time,
+                    width: render_target.texture().width(),
+                    height: render_target.texture().height(),
+                    max_luminance,
+                    instance_count: INSTANCE_COUNT,
+                    ..Zeroable::zeroed()
+                };
+                color_space.update_constants(&mut shader_constants);
+                self.renderer.render(&shader_constants, render_target)
+            })?;
+        }
+        Ok(())
+    }
+}
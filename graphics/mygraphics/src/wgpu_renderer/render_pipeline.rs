@@ -1,70 +1,209 @@
+use crate::wgpu_renderer::mesh::MyMesh;
 use crate::wgpu_renderer::renderer::{GlobalBindGroup, GlobalBindGroupLayout};
+use anyhow::{bail, ensure};
 use mygraphics_shaders::ShaderConstants;
 use wgpu::{
-    ColorTargetState, ColorWrites, Device, FragmentState, FrontFace, MultisampleState,
-    PipelineLayoutDescriptor, PolygonMode, PrimitiveState, PrimitiveTopology, RenderPass,
-    RenderPipeline, RenderPipelineDescriptor, TextureFormat, VertexState, include_spirv,
+    ColorTargetState, ColorWrites, Device, FragmentState, MultisampleState, PipelineLayout,
+    PipelineLayoutDescriptor, PolygonMode, PrimitiveState, RenderPass, RenderPipeline,
+    RenderPipelineDescriptor, ShaderModule, TextureFormat, VertexBufferLayout, VertexState,
 };
 
+/// A render pipeline that can be rebuilt with new shaders ([`Self::set_shader_module`]), vertex input
+/// ([`Self::set_vertex_input`]), polygon mode ([`Self::set_polygon_mode`]) or sample shading
+/// ([`Self::set_sample_shading`]), while keeping the same pipeline layout and bind group layout
 #[derive(Debug, Clone)]
 pub struct MyRenderPipeline {
+    layout: PipelineLayout,
+    module: ShaderModule,
+    out_format: TextureFormat,
+    sample_count: u32,
+    /// whether this pipeline draws a [`MyMesh`] or the vertexless triangle
+    vertex_input: bool,
+    /// whether this pipeline uses `main_fs_per_sample`
+    sample_shading: bool,
+    primitive: PrimitiveState,
     pipeline: RenderPipeline,
 }
 
 impl MyRenderPipeline {
+    /// `primitive` defaults to an unculled triangle list. Its winding is defined in clip space with y pointing up, the
+    /// same as on the ash renderer, so a [`wgpu::FrontFace`] culls the same triangles on both.
     pub fn new(
         device: &Device,
         global_bind_group_layout: &GlobalBindGroupLayout,
+        module: ShaderModule,
         out_format: TextureFormat,
+        sample_count: u32,
+        vertex_input: bool,
+        primitive: PrimitiveState,
     ) -> anyhow::Result<Self> {
-        let module = device.create_shader_module(include_spirv!(env!("SHADER_SPV_PATH")));
-
+        let immediate_size = size_of::<ShaderConstants>() as u32;
+        let max_immediate_size = device.limits().max_immediate_size;
+        ensure!(
+            immediate_size <= max_immediate_size,
+            "ShaderConstants of {immediate_size} bytes exceed the {max_immediate_size} bytes of immediates of the device"
+        );
         let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("MyRenderPipeline layout"),
             bind_group_layouts: &[Some(&global_bind_group_layout.0)],
-            immediate_size: size_of::<ShaderConstants>() as u32,
+            immediate_size,
         });
-
+        let pipeline = create_pipeline(
+            device,
+            &layout,
+            &module,
+            out_format,
+            sample_count,
+            entry_points(vertex_input, false),
+            primitive,
+        );
         Ok(Self {
-            pipeline: device.create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("MyRenderPipeline"),
-                layout: Some(&layout),
-                vertex: VertexState {
-                    module: &module,
-                    entry_point: Some("main_vs"),
-                    compilation_options: Default::default(),
-                    buffers: &[],
-                },
-                primitive: PrimitiveState {
-                    topology: PrimitiveTopology::TriangleList,
-                    strip_index_format: None,
-                    front_face: FrontFace::Ccw,
-                    cull_mode: None,
-                    unclipped_depth: false,
-                    polygon_mode: PolygonMode::Fill,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: MultisampleState::default(),
-                fragment: Some(FragmentState {
-                    module: &module,
-                    entry_point: Some("main_fs"),
-                    compilation_options: Default::default(),
-                    targets: &[Some(ColorTargetState {
-                        format: out_format,
-                        blend: None,
-                        write_mask: ColorWrites::ALL,
-                    })],
-                }),
-                multiview_mask: None,
-                cache: None,
-            }),
+            layout,
+            module,
+            out_format,
+            sample_count,
+            vertex_input,
+            sample_shading: false,
+            primitive,
+            pipeline,
         })
     }
 
-    pub fn draw(&self, rpass: &mut RenderPass<'_>, global_bind_group: &GlobalBindGroup) {
+    /// Switch to the shaders of `module`, which must have the same entry points
+    pub fn set_shader_module(&mut self, device: &Device, module: ShaderModule) {
+        self.module = module;
+        self.recreate(device);
+    }
+
+    /// Switch between drawing a [`MyMesh`] and the vertexless triangle
+    pub fn set_vertex_input(&mut self, device: &Device, vertex_input: bool) {
+        if self.vertex_input != vertex_input {
+            self.vertex_input = vertex_input;
+            self.recreate(device);
+        }
+    }
+
+    /// Switch between filled and wireframe triangles. [`PolygonMode::Line`] requires
+    /// [`wgpu::Features::POLYGON_MODE_LINE`].
+    pub fn set_polygon_mode(&mut self, device: &Device, polygon_mode: PolygonMode) {
+        if self.primitive.polygon_mode != polygon_mode {
+            self.primitive.polygon_mode = polygon_mode;
+            self.recreate(device);
+        }
+    }
+
+    #[inline]
+    pub fn polygon_mode(&self) -> PolygonMode {
+        self.primitive.polygon_mode
+    }
+
+    /// Switch between shading once per pixel and once per sample with MSAA. Shading every sample requires the
+    /// `main_fs_per_sample` entry point, compiled with the `sample-shading` feature, and
+    /// [`wgpu::DownlevelFlags::MULTISAMPLED_SHADING`], see [`validate_sample_shading`](super::renderer::validate_sample_shading).
+    pub fn set_sample_shading(&mut self, device: &Device, sample_shading: bool) {
+        if self.sample_shading != sample_shading {
+            self.sample_shading = sample_shading;
+            self.recreate(device);
+        }
+    }
+
+    /// Rebuild the [`RenderPipeline`] from the current shader module and settings
+    pub fn recreate(&mut self, device: &Device) {
+        self.pipeline = create_pipeline(
+            device,
+            &self.layout,
+            &self.module,
+            self.out_format,
+            self.sample_count,
+            entry_points(self.vertex_input, self.sample_shading),
+            self.primitive,
+        );
+    }
+
+    /// Draw `mesh`, or `instance_count` instances of the vertexless triangle if the pipeline has no vertex input
+    pub fn draw(
+        &self,
+        rpass: &mut RenderPass<'_>,
+        global_bind_group: &GlobalBindGroup,
+        mesh: Option<&MyMesh>,
+        instance_count: u32,
+    ) -> anyhow::Result<()> {
         rpass.set_pipeline(&self.pipeline);
         rpass.set_bind_group(0, &global_bind_group.0, &[]);
-        rpass.draw(0..3, 0..1);
+        match (self.vertex_input, mesh) {
+            (true, Some(mesh)) => mesh.draw(rpass),
+            (true, None) => bail!("Pipeline uses vertex input, but no mesh was given"),
+            // the vertexless triangle generates its vertices from the vertex index
+            (false, _) => rpass.draw(0..3, 0..instance_count),
+        }
+        Ok(())
     }
 }
+
+fn create_pipeline(
+    device: &Device,
+    layout: &PipelineLayout,
+    module: &ShaderModule,
+    out_format: TextureFormat,
+    sample_count: u32,
+    (vs_entry_point, fs_entry_point, vertex_buffers): EntryPoints,
+    primitive: PrimitiveState,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("MyRenderPipeline"),
+        layout: Some(layout),
+        vertex: VertexState {
+            module,
+            entry_point: Some(vs_entry_point),
+            compilation_options: Default::default(),
+            buffers: vertex_buffers,
+        },
+        primitive,
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: sample_count,
+            ..MultisampleState::default()
+        },
+        fragment: Some(FragmentState {
+            module,
+            entry_point: Some(fs_entry_point),
+            compilation_options: Default::default(),
+            targets: &[Some(ColorTargetState {
+                format: out_format,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+/// The vertex and fragment entry points of a pipeline and the layouts of its vertex buffers
+type EntryPoints = (
+    &'static str,
+    &'static str,
+    &'static [VertexBufferLayout<'static>],
+);
+
+fn entry_points(vertex_input: bool, sample_shading: bool) -> EntryPoints {
+    // meshes bring their own vertex colors and aren't textured
+    let (vs_entry_point, fs_entry_point, vertex_buffers) = if vertex_input {
+        (
+            "main_vs_mesh",
+            "main_fs",
+            [MyMesh::VERTEX_LAYOUT].as_slice(),
+        )
+    } else if cfg!(feature = "texture") {
+        ("main_vs_texture", "main_fs_texture", [].as_slice())
+    } else {
+        ("main_vs", "main_fs", [].as_slice())
+    };
+    // reading the sample index makes the fragment shader run per sample, the texture has no such variant
+    let fs_entry_point = if sample_shading && fs_entry_point == "main_fs" {
+        "main_fs_per_sample"
+    } else {
+        fs_entry_point
+    };
+    (vs_entry_point, fs_entry_point, vertex_buffers)
+}
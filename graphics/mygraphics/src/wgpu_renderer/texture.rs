@@ -0,0 +1,60 @@
+use image::RgbaImage;
+use wgpu::{
+    AddressMode, Device, Extent3d, FilterMode, Queue, Sampler, SamplerDescriptor,
+    TexelCopyBufferLayout, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+/// A sampled texture, together with the sampler used to read it
+#[derive(Debug, Clone)]
+pub struct MyTexture {
+    pub texture: Texture,
+    pub view: TextureView,
+    pub sampler: Sampler,
+}
+
+impl MyTexture {
+    /// Uploads `rgba` with [`Queue::write_texture`], which is staged and executed with the next submission
+    pub fn new(device: &Device, queue: &Queue, rgba: &RgbaImage, label: &str) -> Self {
+        let size = Extent3d {
+            width: rgba.width(),
+            height: rgba.height(),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8UnormSrgb,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            rgba.as_raw(),
+            TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(rgba.width() * 4),
+                rows_per_image: None,
+            },
+            size,
+        );
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            address_mode_w: AddressMode::Repeat,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..SamplerDescriptor::default()
+        });
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
@@ -0,0 +1,25 @@
+//! Renders the triangle with the cpu renderer and compares it to the golden image in `tests/golden`. After changing
+//! the shaders, regenerate it with `UPDATE_GOLDEN=1 cargo test`.
+
+use bytemuck::Zeroable;
+use mygraphics::cpu_renderer::renderer::render_image;
+use mygraphics::util::compare_golden;
+use mygraphics_shaders::ShaderConstants;
+use std::path::Path;
+
+/// Allows for small differences in floating point math between platforms
+const TOLERANCE: u8 = 2;
+
+#[test]
+pub fn cpu_triangle() {
+    let image = render_image(
+        &ShaderConstants {
+            width: 320,
+            height: 240,
+            ..Zeroable::zeroed()
+        },
+        [0., 0., 0., 1.],
+    );
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/cpu_triangle.png");
+    compare_golden(&image, &path, TOLERANCE).unwrap();
+}
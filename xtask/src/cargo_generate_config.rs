@@ -17,11 +17,18 @@ pub struct Config {
 #[derive(Deserialize, Debug, PartialEq, Eq, Default, Clone)]
 pub struct TemplateConfig {
     pub sub_templates: Option<Vec<String>>,
+    /// Not part of cargo-generate, which ignores it: commands xtask executes on each generated variant. Only read from
+    /// the root config.
+    pub post_generate: Option<Vec<String>>,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Clone)]
 pub struct ConditionalConfig {
     pub placeholders: Option<TemplateSlotsTable>,
+    /// Not part of cargo-generate, which ignores it: files xtask copies into the variants matching the condition after
+    /// generating them, mapping their destination within the variant to their source within the template. The sources
+    /// should be in `[template] ignore`, so cargo-generate doesn't also template them into every variant.
+    pub extra_files: Option<IndexMap<String, String>>,
 }
 
 #[derive(Deserialize, Debug, PartialEq, Clone, Default)]
@@ -0,0 +1,143 @@
+//! Evaluation of the conditions of `[conditional]` tables in `cargo-generate.toml`.
+//!
+//! `cargo_generate` evaluates them as liquid expressions. We only support the subset this repo uses: comparing
+//! placeholders to strings with `==` and `!=`, combined with `and` and `or`.
+
+use anyhow::{Context, bail};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Condition {
+    source: String,
+    first: Comparison,
+    /// like liquid, operators have no precedence and are evaluated right to left
+    rest: Vec<(Operator, Comparison)>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Comparison {
+    key: String,
+    value: String,
+    equal: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Operator {
+    And,
+    Or,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let context = || format!("Unsupported condition `{source}`");
+        let mut tokens = source.split_whitespace();
+        let first = Comparison::parse(&mut tokens).with_context(context)?;
+        let mut rest = Vec::new();
+        while let Some(operator) = tokens.next() {
+            let operator = match operator {
+                "and" => Operator::And,
+                "or" => Operator::Or,
+                _ => bail!("{}: expected `and` or `or`, got `{operator}`", context()),
+            };
+            rest.push((
+                operator,
+                Comparison::parse(&mut tokens).with_context(context)?,
+            ));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            first,
+            rest,
+        })
+    }
+
+    /// Evaluates the condition with the placeholder values returned by `get`, unknown placeholders are never equal
+    pub fn eval<'a>(&self, get: impl Fn(&str) -> Option<&'a str>) -> bool {
+        let comparisons = std::iter::once(&self.first).chain(self.rest.iter().map(|(_, c)| c));
+        let mut values = comparisons.map(|c| c.eval(&get)).collect::<Vec<_>>();
+        let mut result = values.pop().unwrap();
+        for ((operator, _), value) in self.rest.iter().rev().zip(values.into_iter().rev()) {
+            result = match operator {
+                Operator::And => value && result,
+                Operator::Or => value || result,
+            };
+        }
+        result
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Comparison {
+    fn parse<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> anyhow::Result<Self> {
+        let (Some(key), Some(operator), Some(value)) =
+            (tokens.next(), tokens.next(), tokens.next())
+        else {
+            bail!("expected a comparison like `key == \"value\"`");
+        };
+        let equal = match operator {
+            "==" => true,
+            "!=" => false,
+            _ => bail!("expected `==` or `!=`, got `{operator}`"),
+        };
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .with_context(|| format!("expected a quoted string, got `{value}`"))?;
+        Ok(Self {
+            key: key.to_string(),
+            value: value.to_string(),
+            equal,
+        })
+    }
+
+    fn eval<'a>(&self, get: &impl Fn(&str) -> Option<&'a str>) -> bool {
+        (get(&self.key) == Some(self.value.as_str())) == self.equal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(condition: &str, values: &[(&'static str, &'static str)]) -> bool {
+        Condition::parse(condition)
+            .unwrap()
+            .eval(|key| values.iter().find(|(k, _)| *k == key).map(|(_, v)| *v))
+    }
+
+    #[test]
+    pub fn condition_comparison() {
+        assert!(eval(r#"api == "ash""#, &[("api", "ash")]));
+        assert!(!eval(r#"api == "ash""#, &[("api", "wgpu")]));
+        assert!(eval(r#"api != "ash""#, &[("api", "wgpu")]));
+        assert!(!eval(r#"api == "ash""#, &[]));
+    }
+
+    #[test]
+    pub fn condition_right_to_left() {
+        let values = [("a", "x"), ("b", "y"), ("c", "z")];
+        // `a and (b or c)`, `(a and b) or c` would be true
+        assert!(!eval(r#"a == "y" and b == "y" or c == "z""#, &values));
+        // `a or (b and c)`
+        assert!(eval(r#"a == "x" or b == "x" and c == "z""#, &values));
+    }
+
+    #[test]
+    pub fn condition_unsupported() {
+        for condition in [
+            "",
+            r#"api == ash"#,
+            r#"api > "ash""#,
+            r#"api == "ash" xor api == "wgpu""#,
+            r#"api == "ash" and"#,
+        ] {
+            let result = Condition::parse(condition);
+            assert!(result.is_err(), "{condition}: {result:?}");
+        }
+    }
+}
@@ -1,12 +1,83 @@
 use crate::cargo_generate_config::{CONFIG_FILE_NAME, Config};
+use crate::condition::Condition;
 use anyhow::{Context, bail};
 use cargo_generate::GenerateArgs;
 use clap::Parser;
 use indexmap::IndexMap;
-use log::{debug, info};
+use log::{debug, error, info, warn};
+use notify::{RecursiveMode, Watcher};
 use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::fmt::{Debug, Display, Formatter};
-use std::path::{Path, PathBuf};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+pub mod smoke;
+
+/// With `--watch`, how long to wait for further changes before regenerating. Editors and `git checkout` tend to emit
+/// many events in quick succession, which should only trigger a single run.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// With `--execute-timeout`, how often to check whether a command exited
+const EXECUTE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// `cargo_generate::generate` is not thread-safe, as it changes the current directory of the process while running
+/// hooks. Calls to it must be serialized by holding this lock, see [`CargoGenerateGuard`].
+static CARGO_GENERATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Serializes calls to `cargo_generate::generate` and normalizes the environment variables it reads while held.
+///
+/// The authors are read from `CARGO_NAME` and `CARGO_EMAIL`, which can't be overridden with `--define`:
+/// https://github.com/cargo-generate/cargo-generate/issues/1602
+/// The variables are only set while this guard is held and restored when it's dropped, so they never leak out of it.
+struct CargoGenerateGuard {
+    previous: Vec<(&'static str, Option<OsString>)>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl CargoGenerateGuard {
+    const ENV: [(&'static str, &'static str); 2] =
+        [("CARGO_NAME", "generated"), ("CARGO_EMAIL", "generated")];
+
+    fn lock() -> Self {
+        let lock = CARGO_GENERATE_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let previous = Self::ENV
+            .iter()
+            .map(|&(key, value)| {
+                let previous = std::env::var_os(key);
+                // Safety: the environment is only modified while holding `CARGO_GENERATE_LOCK`, other threads only
+                // read it through std (e.g. when spawning processes), which synchronizes with `set_var`
+                unsafe { std::env::set_var(key, value) };
+                (key, previous)
+            })
+            .collect();
+        Self {
+            previous,
+            _lock: lock,
+        }
+    }
+}
+
+impl Drop for CargoGenerateGuard {
+    fn drop(&mut self) {
+        for (key, previous) in self.previous.drain(..) {
+            // Safety: see `CargoGenerateGuard::lock`, the lock is only released after this
+            unsafe {
+                match previous {
+                    Some(previous) => std::env::set_var(key, previous),
+                    None => std::env::remove_var(key),
+                }
+            }
+        }
+    }
+}
 
 #[derive(Parser, Debug, Default)]
 pub struct Generate {
@@ -19,10 +90,58 @@ pub struct Generate {
     clean: bool,
     /// A command that should be executed on each generated template.
     ///
-    /// If a command fails, this process will fail as well, allowing you to test the template output.
+    /// If a command fails, this process will fail as well, allowing you to test the template output. Runs after the
+    /// `post_generate` commands of the root `cargo-generate.toml`.
     #[clap(long, short = 'x')]
     execute: Option<String>,
-    /// Filter for values that any placeholder accepts
+    /// How many variants to generate and execute the command on concurrently.
+    ///
+    /// With more than one, the output of commands is captured and logged once they finish, instead of being streamed.
+    #[clap(long, default_value_t = 1)]
+    parallel: usize,
+    /// Kill a command executed on a variant if it runs longer than this many seconds, failing the variant. Without it,
+    /// a hung command, e.g. a `cargo build` waiting on the network, blocks forever.
+    #[clap(long, value_name = "SECS")]
+    execute_timeout: Option<u64>,
+    /// Keep executing the remaining commands on a variant after one of them failed, reporting all their failures
+    /// together in the summary.
+    ///
+    /// A failing variant never stops the others, but by default the commands after the first failure on a variant are
+    /// skipped, as they usually fail for the same reason.
+    #[clap(long)]
+    keep_going: bool,
+    /// Only print the variants and the directories they would be generated into, without writing anything.
+    #[clap(long)]
+    dry_run: bool,
+    /// Only print the discovered templates with their placeholders and the `choices` of each, without generating
+    /// anything.
+    #[clap(long, conflicts_with_all = ["dry_run", "watch"])]
+    list: bool,
+    /// After generating, keep watching the template directories and regenerate the variants of a template whenever
+    /// one of its files changes, executing the command on them again.
+    #[clap(long, conflicts_with = "dry_run")]
+    watch: bool,
+    /// Join all variants into a single workspace with its `Cargo.toml` in the output directory, so `cargo build` there
+    /// builds every variant at once.
+    ///
+    /// The `[workspace]` and profiles of each variant move into that manifest, merging those of all variants. Package
+    /// names must be unique within a workspace, so the packages of each variant get its values appended, e.g.
+    /// `mygraphics-render-wgpu-cargo-gpu`, while keeping their library names. Their targets keep their names as well,
+    /// so cargo warns about the outputs of the same target of different variants colliding, pick a variant with
+    /// `cargo run -p`. As the variants are no workspaces of their own anymore, no commands can be executed on them.
+    #[clap(long, conflicts_with = "execute")]
+    workspace: bool,
+    /// Directory containing the root `cargo-generate.toml` that lists the templates, defaults to this repository.
+    ///
+    /// Unless `--out` is given, the variants are generated into the `generated` directory next to it.
+    #[clap(long)]
+    templates_dir: Option<PathBuf>,
+    /// Set a placeholder to a value, as `key=value`, instead of generating a variant for each of its `choices`.
+    ///
+    /// The value is passed straight through to cargo-generate, so this also sets placeholders without `choices`.
+    #[clap(long, value_name = "KEY=VALUE", value_parser = parse_define)]
+    define: Vec<(String, String)>,
+    /// Filter for values that any placeholder accepts, or one of their `aliases`
     ///
     /// We assume there are no values that two different placeholders match, within a single template, so we don't have
     /// to specify the placeholder the value is associated to.
@@ -30,13 +149,16 @@ pub struct Generate {
 }
 
 #[derive(Clone, Debug)]
-struct TemplateDiscovery {
+pub struct TemplateDiscovery {
     templates: Vec<Template>,
+    /// Commands from the root `post_generate`, executed in order on each generated variant
+    post_generate: Vec<String>,
 }
 
 impl TemplateDiscovery {
     pub const TEMPLATE_PATH: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
 
+    #[cfg(test)]
     fn discover() -> anyhow::Result<Self> {
         Self::discover_at(Path::new(Self::TEMPLATE_PATH))
     }
@@ -45,14 +167,27 @@ impl TemplateDiscovery {
     /// *fast*, even with a large target directory. Primarily, that means not scanning through the entire dir tree,
     /// instead just using the paths that are explicitly defined in the config.
     /// https://github.com/cargo-generate/cargo-generate/issues/1600
-    fn discover_at(base_dir: &Path) -> anyhow::Result<Self> {
-        let sub_templates = {
+    ///
+    /// `base_dir` must contain the root `cargo-generate.toml` listing the `sub_templates`.
+    pub fn discover_at(base_dir: &Path) -> anyhow::Result<Self> {
+        // `cargo_generate` changes the current directory, so relative paths would resolve differently later on
+        let base_dir = &std::path::absolute(base_dir)?;
+        let (sub_templates, post_generate) = {
             let root_file = base_dir.join(CONFIG_FILE_NAME);
+            if !root_file.is_file() {
+                bail!(
+                    "`{}` is not a templates directory, expected a `{CONFIG_FILE_NAME}` in it",
+                    base_dir.display()
+                );
+            }
             let root: Config = toml::from_str(&std::fs::read_to_string(&root_file)?)?;
             let root_template = root
                 .template
                 .with_context(|| format!("Expected `template` in `{}`", root_file.display()))?;
-            root_template.sub_templates.unwrap_or_else(Vec::new)
+            (
+                root_template.sub_templates.unwrap_or_else(Vec::new),
+                root_template.post_generate.unwrap_or_else(Vec::new),
+            )
         };
 
         let templates = sub_templates
@@ -62,11 +197,59 @@ impl TemplateDiscovery {
                 Template::parse(name, template_dir)
             })
             .collect::<anyhow::Result<Vec<_>>>()?;
-        let discovery = Self { templates };
+        let discovery = Self {
+            templates,
+            post_generate,
+        };
         debug!("Discovery found: {discovery:#?}");
         Ok(discovery)
     }
 
+    /// Formats the templates as a tree of their placeholders, each with its `choices` and their `aliases`
+    fn list(&self) -> String {
+        let mut out = String::new();
+        for template in &self.templates {
+            out.push_str(&template.name);
+            out.push('\n');
+            for (i, (p, choices)) in template.placeholders.iter().enumerate() {
+                let branch = if i + 1 == template.placeholders.len() {
+                    "└──"
+                } else {
+                    "├──"
+                };
+                let choices = if choices.is_empty() {
+                    "<no choices, set with --define>".to_string()
+                } else {
+                    let aliases = template.aliases.get(p);
+                    choices
+                        .iter()
+                        .map(|c| {
+                            let aliases = aliases
+                                .into_iter()
+                                .flatten()
+                                .filter(|(_, value)| *value == c)
+                                .map(|(alias, _)| alias.as_str())
+                                .collect::<Vec<_>>();
+                            if aliases.is_empty() {
+                                c.clone()
+                            } else {
+                                format!("{c} (alias {})", aliases.join(", "))
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                };
+                let condition = template
+                    .conditional_placeholders
+                    .get(p)
+                    .map(|condition| format!(" (if {condition})"))
+                    .unwrap_or_default();
+                out.push_str(&format!("{branch} {p}: {choices}{condition}\n"));
+            }
+        }
+        out
+    }
+
     fn split_filter<'a>(&self, filters: impl Iterator<Item = &'a str>) -> Filters<'a> {
         let mut out = Filters::default();
         for filter in filters {
@@ -80,24 +263,55 @@ impl TemplateDiscovery {
         out
     }
 
+    /// Computes the variants of all templates matching `filters`, with the placeholders in `defines` set to their
+    /// value instead of all of their `choices`.
     fn filter_variants<'a>(
         &'a self,
         filters: impl Iterator<Item = &'a str>,
+        defines: &[Define<'a>],
     ) -> anyhow::Result<Vec<(&'a Template, Vec<Define<'a>>)>> {
         let filters = self.split_filter(filters);
+        for define in defines {
+            if !self
+                .templates
+                .iter()
+                .any(|t| t.placeholders.contains_key(define.key))
+            {
+                bail!(
+                    "Unknown placeholder `{}` in `--define {define}`",
+                    define.key
+                );
+            }
+        }
 
-        let mut has_unknown_filter = true;
-        let mut unknown_filter = None;
-        let variants = self
+        let templates = self
             .templates
             .iter()
             .filter(|template| {
                 filters.template_filters.is_empty()
                     || filters.template_filters.contains(template.name.as_str())
             })
+            .collect::<Vec<_>>();
+        for template in &templates {
+            let undefined = template
+                .placeholders
+                .iter()
+                .find(|(p, choices)| choices.is_empty() && !defines.iter().any(|d| d.key == *p));
+            if let Some((p, _)) = undefined {
+                bail!(
+                    "Placeholder `{p}` of template `{}` has no `choices`, set it with `--define {p}=...`",
+                    template.name
+                );
+            }
+        }
+
+        let mut has_unknown_filter = true;
+        let mut unknown_filter = None;
+        let variants = templates
+            .into_iter()
             .flat_map(|template| {
                 let variants_result =
-                    template.variants(filters.placeholder_filters.iter().copied());
+                    template.variants(filters.placeholder_filters.iter().copied(), defines);
                 let variants = match variants_result {
                     Ok(e) => {
                         has_unknown_filter = false;
@@ -113,7 +327,14 @@ impl TemplateDiscovery {
             .collect::<Vec<_>>();
         if has_unknown_filter {
             if let Some(filter) = unknown_filter {
-                bail!("Unknown filter `{filter}`")
+                let known = self.known_values();
+                let suggestion = closest_value(filter, &known)
+                    .map(|closest| format!(", did you mean `{closest}`?"))
+                    .unwrap_or_default();
+                bail!(
+                    "Unknown filter `{filter}`{suggestion}\nValid filters are: {}",
+                    known.join(", ")
+                )
             } else {
                 // Only reachable if no templates exist, Or if all templates have been filtered out, but you must filter
                 // for at least one template for template filtering to even activate, so should be unreachable.
@@ -123,6 +344,76 @@ impl TemplateDiscovery {
         debug!("Variants: {variants:?}");
         Ok(variants)
     }
+
+    /// The first variant of each template for every value of `placeholder`, a small but representative subset of the
+    /// variants of [`Self::filter_variants`]. Templates without the placeholder are represented by their first variant.
+    fn representatives<'a>(
+        &'a self,
+        placeholder: &str,
+        defines: &[Define<'a>],
+    ) -> anyhow::Result<Vec<(&'a Template, Vec<Define<'a>>)>> {
+        let mut seen = HashSet::new();
+        let mut variants = self.filter_variants(std::iter::empty(), defines)?;
+        variants.retain(|(template, variant)| {
+            let value = variant
+                .iter()
+                .find(|d| d.key == placeholder)
+                .map(|d| d.value);
+            seen.insert((template.name.as_str(), value))
+        });
+        Ok(variants)
+    }
+
+    /// All template names, placeholder values and aliases of all templates, without duplicates and in the order they
+    /// are declared in
+    fn known_values(&self) -> Vec<&str> {
+        let mut known = Vec::new();
+        for template in &self.templates {
+            let values = template.placeholders.values().flatten();
+            let aliases = template.aliases.values().flat_map(|a| a.keys());
+            for value in [&template.name].into_iter().chain(values).chain(aliases) {
+                if !known.contains(&value.as_str()) {
+                    known.push(value.as_str());
+                }
+            }
+        }
+        known
+    }
+}
+
+/// The value of `known` closest to `filter`, if it's close enough to be a typo
+fn closest_value<'a>(filter: &str, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|known| (edit_distance(filter, known), *known))
+        .filter(|(distance, _)| *distance <= filter.chars().count().div_ceil(3))
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// The Levenshtein distance between `a` and `b`, the number of chars to insert, remove or replace to turn one into the
+/// other. Swapping two adjacent chars, a common typo, only counts as one edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // `d[i][j]` is the distance between the first `i` chars of `a` and the first `j` chars of `b`
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let replace = d[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = replace.min(d[i - 1][j] + 1).min(d[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -136,10 +427,20 @@ struct Filters<'a> {
 struct Template {
     name: String,
     template_dir: PathBuf,
+    /// The `choices` of each placeholder, empty for placeholders without any, which must be set with `--define`
     placeholders: IndexMap<String, Vec<String>>,
+    /// Placeholders only declared within a conditional, which only exist in the variants matching their condition.
+    /// Their `choices` are in [`Self::placeholders`] like any other.
+    conditional_placeholders: IndexMap<String, Condition>,
+    /// Alternative names for the `choices` of a placeholder, mapping each alias to its canonical value
+    aliases: IndexMap<String, IndexMap<String, String>>,
+    /// Conditionals that restrict the `choices` of placeholders, disallowing variants with other values
+    conditionals: Vec<(Condition, IndexMap<String, Vec<String>>)>,
+    /// The `extra_files` of conditionals, copied into the variants matching the condition
+    extra_files: Vec<(Condition, IndexMap<String, String>)>,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
 struct Define<'a> {
     key: &'a str,
     value: &'a str,
@@ -161,60 +462,154 @@ impl Template {
     fn parse(name: String, template_dir: PathBuf) -> anyhow::Result<Self> {
         let config_file = template_dir.join(CONFIG_FILE_NAME);
         let config: Config = toml::from_str(&std::fs::read_to_string(&config_file)?)?;
-        let placeholders = config
+        let placeholders_toml = config
             .placeholders
             .with_context(|| format!("Expected `placeholders` in `{}`", config_file.display()))?
             .0;
-        let placeholders = placeholders
-            .into_iter()
+        let mut placeholders = placeholders_toml
+            .iter()
             .map(|(p, toml)| {
-                let choices = toml.get("choices").with_context(|| {
-                    format!(
-                        "Expected `placeholders.{p}` in `{}` to have `choices` set",
-                        config_file.display()
-                    )
-                })?;
-                let choices = choices.as_array().with_context(|| {
-                    format!(
-                        "Expected `placeholders.{p}.choices` in `{}` to be an array",
-                        config_file.display()
-                    )
-                })?;
-                let choices =
-                    choices
-                        .iter()
-                        .enumerate()
-                        .map(|(i, c)| {
-                            let c = c.as_str().with_context(|| format!(
-                                "Expected `placeholders.{p}.choices[{i}]` in `{}` to be a string",
-                                config_file.display()
-                            ))?;
-                            Ok(c.to_string())
-                        })
-                        .collect::<anyhow::Result<Vec<_>>>()?;
-                Ok((p, choices))
+                let choices = parse_choices(&config_file, "placeholders", p, toml)?;
+                Ok((p.clone(), choices.unwrap_or_default()))
             })
             .collect::<anyhow::Result<IndexMap<_, _>>>()?;
+        let aliases = parse_aliases(&config_file, &placeholders, &placeholders_toml)?;
+
+        // Placeholders with `choices` that are only declared in a conditional join the cross product of the variants
+        // matching it. Any restriction of their `choices` belongs into that condition.
+        let conditional_config = config.conditional.unwrap_or_default();
+        let mut conditional_placeholders = IndexMap::new();
+        for (condition, conditional) in &conditional_config {
+            let table = format!("conditional.'{condition}'.placeholders");
+            let declared = conditional.placeholders.iter().flat_map(|p| &p.0);
+            for (p, toml) in declared {
+                if placeholders_toml.contains_key(p) {
+                    continue;
+                }
+                let Some(choices) = parse_choices(&config_file, &table, p, toml)? else {
+                    continue;
+                };
+                let condition = Condition::parse(condition)
+                    .with_context(|| format!("In `{}`", config_file.display()))?;
+                if conditional_placeholders
+                    .insert(p.clone(), (condition, choices))
+                    .is_some()
+                {
+                    bail!(
+                        "Placeholder `{p}` is declared with `choices` in more than one conditional of `{}`, declare \
+                         it in `[placeholders]` instead",
+                        config_file.display()
+                    );
+                }
+            }
+        }
+        // the tables are unordered, sort for a stable layout of the generated directories
+        conditional_placeholders.sort_keys();
+        let conditional_placeholders = conditional_placeholders
+            .into_iter()
+            .map(|(p, (condition, choices))| {
+                placeholders.insert(p.clone(), choices);
+                (p, condition)
+            })
+            .collect();
+
+        let mut conditionals = Vec::new();
+        let mut extra_files = Vec::new();
+        for (condition, conditional) in conditional_config {
+            let parse_condition = || {
+                Condition::parse(&condition)
+                    .with_context(|| format!("In `{}`", config_file.display()))
+            };
+            if let Some(files) = conditional.extra_files
+                && !files.is_empty()
+            {
+                extra_files.push((parse_condition()?, files));
+            }
+            let Some(conditional_placeholders) = conditional.placeholders else {
+                continue;
+            };
+            let table = format!("conditional.'{condition}'.placeholders");
+            let mut restrictions = IndexMap::new();
+            for (p, toml) in conditional_placeholders.0 {
+                // placeholders that only exist within the conditional can't restrict anything
+                if !placeholders.contains_key(&p) {
+                    continue;
+                }
+                if let Some(choices) = parse_choices(&config_file, &table, &p, &toml)? {
+                    restrictions.insert(p, choices);
+                }
+            }
+            if !restrictions.is_empty() {
+                conditionals.push((parse_condition()?, restrictions));
+            }
+        }
+
         Ok(Self {
             name,
             template_dir,
             placeholders,
+            conditional_placeholders,
+            aliases,
+            conditionals,
+            extra_files,
         })
     }
 
-    fn value_to_placeholder(&self) -> HashMap<&str, &str> {
-        self.placeholders
+    /// The `extra_files` of all conditionals matching `variant`, as `(destination, source)` pairs
+    fn extra_files_of<'a>(&'a self, variant: &[Define<'_>]) -> Vec<(&'a str, &'a str)> {
+        let get = |key: &str| variant.iter().find(|d| d.key == key).map(|d| d.value);
+        self.extra_files
             .iter()
-            .flat_map(|(key, values)| values.iter().map(|v| (v.as_str(), key.as_str())))
+            .filter(|(condition, _)| condition.eval(get))
+            .flat_map(|(_, files)| {
+                files
+                    .iter()
+                    .map(|(dest, src)| (dest.as_str(), src.as_str()))
+            })
             .collect()
     }
 
+    /// Returns the first conditional that disallows this variant, by restricting the `choices` of a placeholder to
+    /// exclude the value of the variant.
+    fn disallowed_by(&self, variant: &[Define<'_>]) -> Option<&Condition> {
+        let get = |key: &str| variant.iter().find(|d| d.key == key).map(|d| d.value);
+        self.conditionals
+            .iter()
+            .find(|(condition, restrictions)| {
+                condition.eval(get)
+                    && restrictions.iter().any(|(key, choices)| {
+                        get(key).is_some_and(|value| !choices.iter().any(|c| c == value))
+                    })
+            })
+            .map(|(condition, _)| condition)
+    }
+
+    /// Maps every value and alias a placeholder accepts to the [`Define`] of its canonical value
+    fn value_to_placeholder(&self) -> HashMap<&str, Define<'_>> {
+        let values = self.placeholders.iter().flat_map(|(key, values)| {
+            values
+                .iter()
+                .map(|value| (value.as_str(), Define { key, value }))
+        });
+        let aliases = self.aliases.iter().flat_map(|(key, aliases)| {
+            aliases
+                .iter()
+                .map(|(alias, value)| (alias.as_str(), Define { key, value }))
+        });
+        values.chain(aliases).collect()
+    }
+
     /// Computes all template variants to expand to and returns them in a Vec.
     /// The inner vec is guaranteed to contain no duplicate [`Placeholders`] and is sorted by order of the
-    /// [`Placeholders`] in the enum. Returns an error if a filter was not found.
+    /// [`Placeholders`] in the enum. Variants disallowed by a conditional are skipped. Placeholders in `defines` only
+    /// take the defined value, overriding any filters. Returns an error if a filter was not found.
+    ///
+    /// Conditional placeholders are left out of the variants not matching their condition. If they were filtered for
+    /// or defined, those variants are skipped instead.
     fn variants<'a>(
         &'a self,
         filter: impl Iterator<Item = &'a str>,
+        defines: &[Define<'a>],
     ) -> Result<Vec<Vec<Define<'a>>>, &'a str> {
         // insert all Placeholders with empty Vec for possible values
         let mut variant_map: IndexMap<&str, Vec<&str>> = self
@@ -228,10 +623,20 @@ impl Template {
         if filter.peek().is_some() {
             let value_to_key = self.value_to_placeholder();
             for v in filter {
-                let key = value_to_key.get(v).ok_or(v)?;
-                variant_map.get_mut(key).unwrap().push(v);
+                let define = value_to_key.get(v).ok_or(v)?;
+                variant_map.get_mut(define.key).unwrap().push(define.value);
+            }
+        }
+        for define in defines {
+            if let Some(values) = variant_map.get_mut(define.key) {
+                *values = Vec::from([define.value]);
             }
         }
+        let selected = variant_map
+            .iter()
+            .filter(|(_, values)| !values.is_empty())
+            .map(|(p, _)| *p)
+            .collect::<HashSet<_>>();
 
         // cross product of all Placeholder keys
         let mut variants: Vec<Vec<Define>> = Vec::from(&[Vec::new()]);
@@ -241,7 +646,8 @@ impl Template {
                 let all = self.placeholders.get(p).unwrap();
                 values = all.iter().map(|s| s.as_str()).collect::<Vec<_>>();
             }
-            assert!(!values.is_empty());
+            // placeholders without `choices` must be set with `--define`, checked by `filter_variants`
+            assert!(!values.is_empty(), "Placeholder `{p}` has no value");
 
             variants = values
                 .into_iter()
@@ -256,18 +662,140 @@ impl Template {
                 .collect::<Vec<_>>();
         }
 
+        let mut variants = variants
+            .into_iter()
+            .filter_map(|mut variant| {
+                for (p, condition) in &self.conditional_placeholders {
+                    let get = |key: &str| variant.iter().find(|d| d.key == key).map(|d| d.value);
+                    if !condition.eval(get) {
+                        if selected.contains(p.as_str()) {
+                            return None;
+                        }
+                        variant.retain(|d| d.key != p.as_str());
+                    }
+                }
+                Some(variant)
+            })
+            .collect::<Vec<_>>();
+        // leaving out a conditional placeholder turns the variants differing only in its value into duplicates
+        let mut seen = HashSet::new();
+        variants.retain(|variant| seen.insert(variant.clone()));
+
+        variants.retain(|variant| match self.disallowed_by(variant) {
+            Some(condition) => {
+                debug!("Pruned variant {variant:?}, disallowed by conditional `{condition}`");
+                false
+            }
+            None => true,
+        });
+
         debug!("Variants for template `{}`: {variants:?}", self.name);
         Ok(variants)
     }
 }
 
+/// Parses a `--define` of the form `key=value`
+fn parse_define(define: &str) -> Result<(String, String), String> {
+    match define.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("Expected `key=value`, got `{define}`")),
+    }
+}
+
+/// Parses the `aliases` table of each placeholder, which cargo-generate ignores. An alias must resolve to one of the
+/// `choices` of its placeholder and may not shadow the value of any placeholder.
+fn parse_aliases(
+    config_file: &Path,
+    placeholders: &IndexMap<String, Vec<String>>,
+    placeholders_toml: &IndexMap<String, toml::Value>,
+) -> anyhow::Result<IndexMap<String, IndexMap<String, String>>> {
+    let mut out = IndexMap::new();
+    for (p, toml) in placeholders_toml {
+        let Some(aliases) = toml.get("aliases") else {
+            continue;
+        };
+        let table = aliases.as_table().with_context(|| {
+            format!(
+                "Expected `placeholders.{p}.aliases` in `{}` to be a table",
+                config_file.display()
+            )
+        })?;
+        let mut aliases = IndexMap::new();
+        for (alias, value) in table {
+            let value = value
+                .as_str()
+                .filter(|value| placeholders[p].iter().any(|c| c == value))
+                .with_context(|| {
+                    format!(
+                        "Expected `placeholders.{p}.aliases.{alias}` in `{}` to be one of the `choices`",
+                        config_file.display()
+                    )
+                })?;
+            if placeholders.values().flatten().any(|c| c == alias) {
+                bail!(
+                    "Alias `{alias}` in `{}` shadows a placeholder value",
+                    config_file.display()
+                );
+            }
+            aliases.insert(alias.clone(), value.to_string());
+        }
+        out.insert(p.clone(), aliases);
+    }
+    Ok(out)
+}
+
+/// Parses the `choices` of placeholder `p` in `table`, returns `None` if it has none
+fn parse_choices(
+    config_file: &Path,
+    table: &str,
+    p: &str,
+    toml: &toml::Value,
+) -> anyhow::Result<Option<Vec<String>>> {
+    let Some(choices) = toml.get("choices") else {
+        return Ok(None);
+    };
+    let choices = choices.as_array().with_context(|| {
+        format!(
+            "Expected `{table}.{p}.choices` in `{}` to be an array",
+            config_file.display()
+        )
+    })?;
+    let choices = choices
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let c = c.as_str().with_context(|| {
+                format!(
+                    "Expected `{table}.{p}.choices[{i}]` in `{}` to be a string",
+                    config_file.display()
+                )
+            })?;
+            Ok(c.to_string())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(Some(choices))
+}
+
 impl Generate {
-    fn out_base_dir(&self) -> anyhow::Result<PathBuf> {
+    fn templates_dir(&self) -> &Path {
+        self.templates_dir
+            .as_deref()
+            .unwrap_or(Path::new(TemplateDiscovery::TEMPLATE_PATH))
+    }
+
+    /// The directory all templates are generated into, without touching the file system
+    fn out_base_dir_path(&self) -> anyhow::Result<PathBuf> {
         let out = self
             .out
             .clone()
-            .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../generated"));
+            .unwrap_or_else(|| self.templates_dir().join("generated"));
         debug!("out_base_dir: {}", out.display());
+        // `cargo_generate` changes the current directory, so relative paths would resolve differently later on
+        Ok(std::path::absolute(out)?)
+    }
+
+    fn out_base_dir(&self) -> anyhow::Result<PathBuf> {
+        let out = self.out_base_dir_path()?;
         if self.clean {
             std::fs::remove_dir_all(&out)?;
         }
@@ -275,14 +803,13 @@ impl Generate {
         Ok(out)
     }
 
-    /// Some params can't be normalized with `--define`
-    /// https://github.com/cargo-generate/cargo-generate/issues/1602
-    fn normalize_env(&self) {
-        // Safety: xtask generate is not multithreaded
-        unsafe {
-            std::env::set_var("CARGO_NAME", "generated");
-            std::env::set_var("CARGO_EMAIL", "generated");
+    /// The directory a variant of a template is generated into, with one level per placeholder
+    fn out_dir(out_base_dir: &Path, template: &Template, variant: &[Define]) -> PathBuf {
+        let mut out_dir = out_base_dir.join(&template.name);
+        for value in variant {
+            out_dir.push(value.value);
         }
+        out_dir
     }
 
     fn generate(
@@ -291,16 +818,21 @@ impl Generate {
         template: &Template,
         variant: &[Define],
     ) -> anyhow::Result<PathBuf> {
-        let out_dir = {
-            let mut out_dir = PathBuf::from(out_base_dir);
-            for value in variant {
-                out_dir.push(value.value);
-            }
-            std::fs::create_dir_all(&out_dir)?;
-            out_dir
+        let out_dir = Self::out_dir(out_base_dir, template, variant);
+        let context = || {
+            format!(
+                "Failed to generate template `{}` variant `{variant:?}` at `{}`",
+                template.name,
+                out_dir.display()
+            )
         };
+        std::fs::create_dir_all(&out_dir).with_context(context)?;
 
-        debug!("Generating `{variant:?}` at `{}`", out_dir.display());
+        info!(
+            "Generating {} {variant:?} at `{}`",
+            template.name,
+            out_dir.display()
+        );
         let mut args = GenerateArgs::default();
         args.template_path.path = Some(template.template_dir.to_string_lossy().into_owned());
         args.init = true;
@@ -309,126 +841,1125 @@ impl Generate {
         args.define = variant.iter().map(|v| v.to_string()).collect();
         args.name = Some("name-is-ignored".to_string());
         args.destination = Some(out_dir.clone());
-        cargo_generate::generate(args)?;
+        {
+            let _guard = CargoGenerateGuard::lock();
+            cargo_generate::generate(args).with_context(context)?;
+        }
+        for (dest, src) in template.extra_files_of(variant) {
+            let (src, dest) = (template.template_dir.join(src), out_dir.join(dest));
+            debug!(
+                "Copying extra file `{}` to `{}`",
+                src.display(),
+                dest.display()
+            );
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent).with_context(context)?;
+            }
+            std::fs::copy(&src, &dest)
+                .with_context(|| format!("Failed to copy extra file `{}`", src.display()))
+                .with_context(context)?;
+        }
 
         Ok(out_dir)
     }
 
-    fn execute<'a>(&self, out_dirs: impl Iterator<Item = &'a Path>) -> anyhow::Result<()> {
-        if let Some(execute) = &self.execute {
-            let mut split = execute.split(" ");
-            // split iterator has at least one entry
-            let exec = split.next().unwrap();
-            let args = split.collect::<Vec<_>>();
-
-            let mut success = true;
-            for out_dir in out_dirs {
-                let mut cmd = std::process::Command::new(exec);
-                cmd.args(args.iter()).current_dir(out_dir);
-                info!("Spawning process: {cmd:?}");
-                let status = cmd.spawn()?.wait().context("Process spawning failed")?;
-                success &= status.success();
+    /// Executes the `post_generate` commands and then `--execute` in `out_dir`, stopping at the first that fails unless
+    /// `--keep-going` is set
+    fn execute(&self, post_generate: &[String], out_dir: &Path) -> anyhow::Result<()> {
+        let mut errors = Vec::new();
+        for execute in post_generate.iter().chain(&self.execute) {
+            if let Err(e) = self.execute_command(execute, out_dir) {
+                if !self.keep_going {
+                    return Err(e);
+                }
+                errors.push(e);
             }
-            if !success {
-                bail!("Some processes spawned by `--execute` failed");
+        }
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().unwrap()),
+            _ => Err(ExecuteErrors(errors).into()),
+        }
+    }
+
+    /// Executes a single command in `out_dir`
+    fn execute_command(&self, execute: &str, out_dir: &Path) -> anyhow::Result<()> {
+        let mut split = execute.split(" ");
+        // split iterator has at least one entry
+        let exec = split.next().unwrap();
+
+        let mut cmd = std::process::Command::new(exec);
+        cmd.args(split).current_dir(out_dir);
+        info!("Spawning process: {cmd:?}");
+        let context = || format!("Failed to execute `{execute}` in `{}`", out_dir.display());
+        // log the output as a whole, so it doesn't interleave with the output of other processes
+        let capture = self.parallel > 1;
+        if capture {
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        let timeout = self.execute_timeout.map(Duration::from_secs);
+        let (status, [stdout, stderr]) = cmd
+            .spawn()
+            .and_then(|child| wait_child(child, timeout))
+            .with_context(context)?;
+        if capture {
+            info!(
+                "Output of {cmd:?}:\n{}{}",
+                String::from_utf8_lossy(&stdout),
+                String::from_utf8_lossy(&stderr)
+            );
+        }
+        let Some(status) = status else {
+            return Err(ExecuteTimeout {
+                execute: execute.to_string(),
+                out_dir: out_dir.to_path_buf(),
+                timeout: timeout.unwrap(),
             }
+            .into());
+        };
+        if !status.success() {
+            bail!(
+                "`{execute}` in `{}` failed with {status}",
+                out_dir.display()
+            );
         }
         Ok(())
     }
 
-    pub fn run(&self) -> anyhow::Result<()> {
-        self.normalize_env();
-        let out_base_dir = self.out_base_dir()?;
+    /// Prints the variants that would be generated and where to, without writing anything
+    fn dry_run(&self, variants: &[(&Template, Vec<Define<'_>>)]) -> anyhow::Result<()> {
+        let out_base_dir = self.out_base_dir_path()?;
+        for (template, variant) in variants {
+            let out_dir = Self::out_dir(&out_base_dir, template, variant);
+            println!("{} {variant:?}: {}", template.name, out_dir.display());
+        }
+        Ok(())
+    }
+
+    /// Whether any command is executed on the generated variants
+    fn executes(&self, post_generate: &[String]) -> bool {
+        !post_generate.is_empty() || self.execute.is_some()
+    }
 
-        let discovery = TemplateDiscovery::discover()?;
-        let variants = discovery.filter_variants(self.filter.iter().map(|a| a.as_str()))?;
-        let results = variants
+    pub fn run(&self) -> anyhow::Result<()> {
+        let discovery = TemplateDiscovery::discover_at(self.templates_dir())?;
+        if self.list {
+            print!("{}", discovery.list());
+            return Ok(());
+        }
+        let defines = self
+            .define
             .iter()
-            .map(|(template, variants)| {
-                self.generate(&out_base_dir.join(&template.name), template, variants)
-            })
-            .collect::<anyhow::Result<Vec<_>>>()?;
-        if results.is_empty() {
+            .map(|(key, value)| Define { key, value })
+            .collect::<Vec<_>>();
+        let variants =
+            discovery.filter_variants(self.filter.iter().map(|a| a.as_str()), &defines)?;
+        if variants.is_empty() {
             // reachable with two templates with differing placeholders and filtering for both
             bail!("Nothing generated, all variants filtered out");
         }
+        if self.workspace && !discovery.post_generate.is_empty() {
+            bail!("`post_generate` commands can't be executed on the variants of a `--workspace`");
+        }
+        if self.dry_run {
+            return self.dry_run(&variants);
+        }
 
-        self.execute(results.iter().map(|b| b.as_path()))?;
-        Ok(())
+        let out_base_dir = self.out_base_dir()?;
+        let post_generate = &discovery.post_generate;
+        let result = self.generate_all(&out_base_dir, post_generate, &variants);
+        if self.watch {
+            if let Err(e) = result {
+                error!("{e:#}");
+            }
+            return self.watch(&out_base_dir, post_generate, &variants);
+        }
+        result
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Generates all `variants` and executes the command on them, returning an error if any of them failed
+    fn generate_all(
+        &self,
+        out_base_dir: &Path,
+        post_generate: &[String],
+        variants: &[(&Template, Vec<Define<'_>>)],
+    ) -> anyhow::Result<()> {
+        // generating is serialized anyway, doing it up front catches colliding packages before any command runs
+        let mut results = Vec::new();
+        let mut generated = Vec::new();
+        for (index, (template, variant)) in variants.iter().enumerate() {
+            match self.generate(out_base_dir, template, variant) {
+                Ok(out_dir) => generated.push((index, out_dir)),
+                Err(e) => results.push((index, Err(e))),
+            }
+        }
+        if self.workspace {
+            let joined = generated
+                .iter()
+                .map(|(index, out_dir)| (variants[*index].1.as_slice(), out_dir.as_path()));
+            join_workspace(out_base_dir, joined)?;
+        }
+        check_packages(
+            out_base_dir,
+            generated.iter().map(|(_, out_dir)| out_dir.as_path()),
+        )?;
 
-    const CARGO_GPU: Define = Define {
-        key: "integration",
-        value: "cargo-gpu",
-    };
-    const SPIRV_BUILDER: Define = Define {
-        key: "integration",
-        value: "spirv-builder",
-    };
-    const ASH: Define = Define {
-        key: "api",
-        value: "ash",
-    };
-    const WGPU: Define = Define {
-        key: "api",
-        value: "wgpu",
-    };
-    const CPU: Define = Define {
-        key: "api",
-        value: "cpu",
-    };
+        // workers take the next variant from the shared index, until all variants are processed
+        let next = AtomicUsize::new(0);
+        results.extend(std::thread::scope(|scope| {
+            let workers = (0..self.parallel.clamp(1, generated.len().max(1)))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+                        while let Some((index, out_dir)) =
+                            generated.get(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            results.push((*index, self.execute(post_generate, out_dir)));
+                        }
+                        results
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect::<Vec<_>>()
+        }));
+        // report in the order of `variants`, not in the order they finished
+        results.sort_by_key(|(index, _)| *index);
+        let results = results
+            .into_iter()
+            .map(|(index, result)| {
+                let (template, variant) = &variants[index];
+                VariantResult {
+                    template,
+                    variant,
+                    out_dir: Self::out_dir(out_base_dir, template, variant),
+                    result,
+                }
+            })
+            .collect::<Vec<_>>();
 
-    pub fn test_template() -> Template {
-        Template {
-            name: "my-template".to_string(),
-            template_dir: PathBuf::from("./my_template/"),
-            placeholders: IndexMap::from(
-                [
-                    ("integration", ["cargo-gpu", "spirv-builder"].as_slice()),
-                    ("api", ["ash", "wgpu", "cpu"].as_slice()),
-                ]
-                .map(|(k, v)| {
-                    (
-                        k.to_string(),
-                        v.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
-                    )
-                }),
-            ),
+        for error in results.iter().filter_map(|r| r.result.as_ref().err()) {
+            error!("{error:#}");
         }
+        print!("{}", summary(&results, self.executes(post_generate)));
+        let failed = results.iter().filter(|r| r.result.is_err()).count();
+        if failed > 0 {
+            bail!("{failed} of {} variants failed", variants.len());
+        }
+        Ok(())
     }
 
-    #[test]
-    pub fn variants_all() {
-        let template = test_template();
-        let all = template.variants(std::iter::empty()).unwrap();
-        debug!("all: {all:?}");
+    /// Watches the directories of the templates in `variants` forever, regenerating the variants of a template when
+    /// its files change. Failing runs are logged and don't stop watching.
+    fn watch(
+        &self,
+        out_base_dir: &Path,
+        post_generate: &[String],
+        variants: &[(&Template, Vec<Define<'_>>)],
+    ) -> anyhow::Result<()> {
+        let mut templates = variants
+            .iter()
+            .map(|(template, _)| *template)
+            .collect::<Vec<_>>();
+        templates.dedup_by_key(|template| &template.name);
 
-        // order *in the outer slice* is arbitrary
-        // order within the inner slice is not, and will affect `generated/` folder structure
-        let expected = [
-            [CARGO_GPU, ASH],
-            [SPIRV_BUILDER, ASH],
-            [CARGO_GPU, WGPU],
-            [SPIRV_BUILDER, WGPU],
-            [CARGO_GPU, CPU],
-            [SPIRV_BUILDER, CPU],
-        ];
-        assert_eq!(all, expected);
-    }
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        // watch and compare canonical paths, as events report paths based on the watched path
+        let mut template_dirs = Vec::new();
+        for template in &templates {
+            let template_dir = template.template_dir.canonicalize().with_context(|| {
+                format!("Failed to watch `{}`", template.template_dir.display())
+            })?;
+            watcher.watch(&template_dir, RecursiveMode::Recursive)?;
+            template_dirs.push((*template, template_dir));
+        }
+        let ignored = [out_base_dir.canonicalize()?];
 
-    #[test]
-    pub fn variants_cross_product_test() {
-        variants_cross_product(&test_template());
-    }
+        loop {
+            info!("Watching {} template(s) for changes", templates.len());
+            let mut changed = HashSet::new();
+            let mut timeout = None;
+            loop {
+                let event = match timeout {
+                    None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    Some(timeout) => receiver.recv_timeout(timeout),
+                };
+                match event {
+                    Ok(Ok(event)) => {
+                        if event.kind.is_access() {
+                            continue;
+                        }
+                        for path in event.paths {
+                            if let Some((template, _)) =
+                                changed_template(&template_dirs, &ignored, &path)
+                            {
+                                debug!("`{}` changed", path.display());
+                                changed.insert(template.name.as_str());
+                                timeout = Some(WATCH_DEBOUNCE);
+                            }
+                        }
+                    }
+                    Ok(Err(e)) => warn!("Watcher error: {e}"),
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => bail!("Watcher stopped unexpectedly"),
+                }
+            }
 
-    #[test]
-    pub fn variants_cross_product_repo() {
-        let discovery = TemplateDiscovery::discover().unwrap();
+            let changed_variants = variants
+                .iter()
+                .filter(|(template, _)| changed.contains(template.name.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+            let mut changed = changed.into_iter().collect::<Vec<_>>();
+            changed.sort_unstable();
+            info!("{}", "=".repeat(80));
+            info!(
+                "`{}` changed, regenerating {} variant(s)",
+                changed.join("`, `"),
+                changed_variants.len()
+            );
+            info!("{}", "=".repeat(80));
+            match self.generate_all(out_base_dir, post_generate, &changed_variants) {
+                Ok(()) => info!("All {} variant(s) succeeded", changed_variants.len()),
+                Err(e) => error!("{e:#}"),
+            }
+        }
+    }
+}
+
+/// Checks that no two packages generated into `out_dirs` share a name within the same workspace, which cargo refuses to
+/// build. Packages not within any workspace below `out_base_dir` are assumed to share one enclosing it. Fails if a
+/// variant has no `Cargo.toml` at all, as there's nothing to keep apart then.
+fn check_packages<'a>(
+    out_base_dir: &Path,
+    out_dirs: impl IntoIterator<Item = &'a Path>,
+) -> anyhow::Result<()> {
+    let mut packages: HashMap<(String, Option<PathBuf>), PathBuf> = HashMap::new();
+    for out_dir in out_dirs {
+        let mut manifests = Vec::new();
+        find_manifests(out_dir, &mut manifests)?;
+        if manifests.is_empty() {
+            bail!("No `Cargo.toml` was generated in `{}`", out_dir.display());
+        }
+        for manifest in manifests {
+            let package = read_manifest(&manifest)?
+                .get("package")
+                .and_then(|package| package.get("name"))
+                .and_then(|name| name.as_str())
+                .map(str::to_string);
+            let Some(package) = package else {
+                continue;
+            };
+            let workspace_root = workspace_root(out_base_dir, &manifest)?;
+            if let Some(other) =
+                packages.insert((package.clone(), workspace_root), manifest.clone())
+            {
+                bail!(
+                    "`{}` and `{}` both declare package `{package}` in the same workspace, which cargo can't build. \
+                     Declare a `[workspace]` in each variant or give the packages distinct names.",
+                    other.display(),
+                    manifest.display()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects the `Cargo.toml` files within `dir` into `manifests`, skipping `target` and hidden directories
+fn find_manifests(dir: &Path, manifests: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read `{}`", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        if entry.file_type()?.is_dir() {
+            if name != "target" && !name.to_string_lossy().starts_with('.') {
+                find_manifests(&path, manifests)?;
+            }
+        } else if name == "Cargo.toml" {
+            manifests.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn read_manifest(manifest: &Path) -> anyhow::Result<toml::Table> {
+    let content = std::fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read `{}`", manifest.display()))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse `{}`", manifest.display()))
+}
+
+/// The closest directory containing `manifest` whose `Cargo.toml` declares a `[workspace]`, or `None` if there is
+/// none up to and including `out_base_dir`
+fn workspace_root(out_base_dir: &Path, manifest: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let dirs = manifest.ancestors().skip(1);
+    for dir in dirs.take_while(|dir| dir.starts_with(out_base_dir)) {
+        let manifest = dir.join("Cargo.toml");
+        if manifest.is_file() && read_manifest(&manifest)?.contains_key("workspace") {
+            return Ok(Some(dir.to_path_buf()));
+        }
+    }
+    Ok(None)
+}
+
+/// Joins the generated `variants` into a single workspace at `out_base_dir`, see [`Generate::workspace`]. An existing
+/// `Cargo.toml` there is extended, so regenerating some of the variants keeps the others.
+fn join_workspace<'a>(
+    out_base_dir: &Path,
+    variants: impl IntoIterator<Item = (&'a [Define<'a>], &'a Path)>,
+) -> anyhow::Result<()> {
+    let root_manifest = out_base_dir.join("Cargo.toml");
+    let mut root = if root_manifest.is_file() {
+        read_document(&root_manifest)?
+    } else {
+        toml_edit::DocumentMut::new()
+    };
+    let workspace = root
+        .entry("workspace")
+        .or_insert_with(toml_edit::table)
+        .as_table_mut()
+        .with_context(|| {
+            format!(
+                "Expected `workspace` in `{}` to be a table",
+                root_manifest.display()
+            )
+        })?;
+    let mut members = workspace
+        .get("members")
+        .and_then(|members| members.as_array())
+        .map(|members| {
+            members
+                .iter()
+                .filter_map(|m| m.as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_else(Vec::new);
+    // reserve the first spot for the members, which are only known once all variants are joined
+    workspace.insert("members", toml_edit::value(toml_edit::Array::new()));
+
+    for (variant, out_dir) in variants {
+        let suffix = variant
+            .iter()
+            .map(|define| {
+                define
+                    .value
+                    .replace(|c: char| !c.is_ascii_alphanumeric(), "-")
+            })
+            .collect::<Vec<_>>()
+            .join("-");
+        for member in join_variant(&mut root, out_dir, &suffix)? {
+            let member = member.strip_prefix(out_base_dir)?;
+            members.push(member.to_string_lossy().replace('\\', "/"));
+        }
+    }
+
+    members.sort_unstable();
+    members.dedup();
+    let mut array = members.into_iter().collect::<toml_edit::Array>();
+    for member in array.iter_mut() {
+        member.decor_mut().set_prefix("\n    ");
+    }
+    array.set_trailing("\n");
+    array.set_trailing_comma(true);
+    root["workspace"]["members"] = toml_edit::value(array);
+    std::fs::write(&root_manifest, root.to_string())
+        .with_context(|| format!("Failed to write `{}`", root_manifest.display()))?;
+    Ok(())
+}
+
+/// Moves the `[workspace]` and profiles of the variant generated into `out_dir` into `root` and appends `suffix` to the
+/// names of its packages. Returns the directories of its members.
+fn join_variant(
+    root: &mut toml_edit::DocumentMut,
+    out_dir: &Path,
+    suffix: &str,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let manifest_path = out_dir.join("Cargo.toml");
+    let mut manifest = read_document(&manifest_path)?;
+    let Some(toml_edit::Item::Table(mut workspace)) = manifest.remove("workspace") else {
+        bail!(
+            "`{}` declares no `[workspace]` to join",
+            manifest_path.display()
+        );
+    };
+    let mut member_dirs = Vec::new();
+    if let Some(members) = workspace.remove("members") {
+        for member in members.as_array().into_iter().flatten() {
+            let member = member.as_str().with_context(|| {
+                format!(
+                    "Expected `workspace.members` in `{}` to be strings",
+                    manifest_path.display()
+                )
+            })?;
+            if member.contains('*') {
+                bail!(
+                    "Can't join the glob `{member}` of `{}` into a workspace",
+                    manifest_path.display()
+                );
+            }
+            member_dirs.push(out_dir.join(member));
+        }
+    }
+    // only meaningful relative to the workspace of the variant
+    workspace.remove("exclude");
+    workspace.remove("default-members");
+    merge_table(
+        root["workspace"].as_table_mut().unwrap(),
+        &workspace,
+        "workspace",
+    )?;
+    if let Some(profile) = manifest.remove("profile") {
+        let profile = profile.as_table().with_context(|| {
+            format!(
+                "Expected `profile` in `{}` to be a table",
+                manifest_path.display()
+            )
+        })?;
+        let root_profile = root.entry("profile").or_insert_with(toml_edit::table);
+        merge_table(root_profile.as_table_mut().unwrap(), profile, "profile")?;
+    }
+    if manifest.is_empty() {
+        std::fs::remove_file(&manifest_path)?;
+    } else {
+        // a package at the root of the variant stays, as a member of the joined workspace
+        std::fs::write(&manifest_path, manifest.to_string())?;
+        member_dirs.push(out_dir.to_path_buf());
+    }
+
+    let mut members = Vec::new();
+    let mut renamed = HashMap::new();
+    for dir in member_dirs {
+        let manifest_path = dir.join("Cargo.toml");
+        let manifest = read_document(&manifest_path)?;
+        let name = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(|name| name.as_str())
+            .with_context(|| format!("Expected a package name in `{}`", manifest_path.display()))?
+            .to_string();
+        renamed.insert(name.clone(), format!("{name}-{suffix}"));
+        members.push((dir, manifest_path, manifest, name));
+    }
+    let mut member_dirs = Vec::new();
+    for (dir, manifest_path, mut manifest, name) in members {
+        manifest["package"]["name"] = toml_edit::value(&renamed[&name]);
+        // the code of the variant still refers to the library by its old name
+        let has_lib = manifest.contains_key("lib") || dir.join("src/lib.rs").is_file();
+        if has_lib
+            && manifest
+                .get("lib")
+                .and_then(|lib| lib.get("name"))
+                .is_none()
+        {
+            let lib = manifest.entry("lib").or_insert_with(|| {
+                let mut lib = toml_edit::Table::new();
+                lib.decor_mut().set_prefix("\n");
+                lib.into()
+            });
+            lib["name"] = toml_edit::value(name.replace('-', "_"));
+        }
+        rename_dependencies(manifest.as_table_mut(), &renamed);
+        if let Some(targets) = manifest
+            .get_mut("target")
+            .and_then(|t| t.as_table_like_mut())
+        {
+            for (_, target) in targets.iter_mut() {
+                if let Some(target) = target.as_table_like_mut() {
+                    rename_dependencies(target, &renamed);
+                }
+            }
+        }
+        std::fs::write(&manifest_path, manifest.to_string())
+            .with_context(|| format!("Failed to write `{}`", manifest_path.display()))?;
+        member_dirs.push(dir);
+    }
+    Ok(member_dirs)
+}
+
+/// Points the path dependencies on packages in `renamed` in `table` to their new names, keeping the names they are
+/// imported with
+fn rename_dependencies(table: &mut dyn toml_edit::TableLike, renamed: &HashMap<String, String>) {
+    for kind in ["dependencies", "dev-dependencies", "build-dependencies"] {
+        let Some(dependencies) = table.get_mut(kind).and_then(|d| d.as_table_like_mut()) else {
+            continue;
+        };
+        for (key, dependency) in dependencies.iter_mut() {
+            let Some(dependency) = dependency.as_table_like_mut() else {
+                continue;
+            };
+            let package = dependency
+                .get("package")
+                .and_then(|package| package.as_str())
+                .unwrap_or(key.get());
+            if let Some(new) = renamed.get(package)
+                && dependency.contains_key("path")
+            {
+                dependency.insert("package", toml_edit::value(new));
+                dependency.fmt();
+            }
+        }
+    }
+}
+
+/// Merges `src` into `dst`, failing if both set the same key to different values
+fn merge_table(
+    dst: &mut toml_edit::Table,
+    src: &toml_edit::Table,
+    path: &str,
+) -> anyhow::Result<()> {
+    for (key, item) in src.iter() {
+        let path = format!("{path}.{key}");
+        match (dst.get_mut(key), item) {
+            (None, _) => {
+                dst.insert(key, item.clone());
+            }
+            (Some(toml_edit::Item::Table(dst)), toml_edit::Item::Table(src)) => {
+                merge_table(dst, src, &path)?;
+            }
+            (Some(existing), _) => {
+                if toml_value(existing) != toml_value(item) {
+                    bail!(
+                        "Variants set `{path}` to both `{}` and `{}`",
+                        existing.to_string().trim(),
+                        item.to_string().trim()
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `item` without its formatting, to compare it with others
+fn toml_value(item: &toml_edit::Item) -> Option<toml::Value> {
+    let mut value = item.clone().into_value().ok()?;
+    value.decor_mut().clear();
+    value.to_string().parse().ok()
+}
+
+fn read_document(manifest: &Path) -> anyhow::Result<toml_edit::DocumentMut> {
+    let content = std::fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read `{}`", manifest.display()))?;
+    content
+        .parse()
+        .with_context(|| format!("Failed to parse `{}`", manifest.display()))
+}
+
+/// Waits for `child` to exit, or kills it once `timeout` elapsed and returns `None` as its status. Also returns the
+/// output of its piped stdout and stderr, which are empty if they weren't piped.
+fn wait_child(
+    mut child: Child,
+    timeout: Option<Duration>,
+) -> std::io::Result<(Option<ExitStatus>, [Vec<u8>; 2])> {
+    // read the pipes while waiting, the child blocks once their buffers are full
+    let pipes: [Option<Box<dyn Read + Send>>; 2] = [
+        child.stdout.take().map(|pipe| Box::new(pipe) as _),
+        child.stderr.take().map(|pipe| Box::new(pipe) as _),
+    ];
+    let readers = pipes.map(|pipe| {
+        std::thread::spawn(move || {
+            let mut output = Vec::new();
+            if let Some(mut pipe) = pipe {
+                pipe.read_to_end(&mut output)?;
+            }
+            Ok::<_, std::io::Error>(output)
+        })
+    });
+
+    let status = match timeout {
+        None => Some(child.wait()?),
+        Some(timeout) => {
+            let start = Instant::now();
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break Some(status);
+                }
+                if start.elapsed() >= timeout {
+                    child.kill()?;
+                    child.wait()?;
+                    break None;
+                }
+                std::thread::sleep(EXECUTE_POLL_INTERVAL);
+            }
+        }
+    };
+    let [stdout, stderr] = readers.map(|reader| reader.join().unwrap());
+    Ok((status, [stdout?, stderr?]))
+}
+
+/// A command executed on a variant was killed after running longer than `--execute-timeout`
+#[derive(Debug)]
+struct ExecuteTimeout {
+    execute: String,
+    out_dir: PathBuf,
+    timeout: Duration,
+}
+
+impl Display for ExecuteTimeout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` in `{}` timed out after {:?}",
+            self.execute,
+            self.out_dir.display(),
+            self.timeout
+        )
+    }
+}
+
+impl std::error::Error for ExecuteTimeout {}
+
+/// More than one command executed on a variant failed, with `--keep-going`
+#[derive(Debug)]
+struct ExecuteErrors(Vec<anyhow::Error>);
+
+impl Display for ExecuteErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} commands failed:", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  {error:#}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ExecuteErrors {}
+
+/// Whether `error` is or contains an [`ExecuteTimeout`]
+fn timed_out(error: &anyhow::Error) -> bool {
+    error.is::<ExecuteTimeout>()
+        || error
+            .downcast_ref::<ExecuteErrors>()
+            .is_some_and(|errors| errors.0.iter().any(|e| e.is::<ExecuteTimeout>()))
+}
+
+/// The outcome of generating a single variant and executing the command on it
+struct VariantResult<'a> {
+    template: &'a Template,
+    variant: &'a [Define<'a>],
+    out_dir: PathBuf,
+    result: anyhow::Result<()>,
+}
+
+/// Renders `results` as a table with one row per variant, preceded by the totals
+fn summary(results: &[VariantResult<'_>], executed: bool) -> String {
+    let rows = results
+        .iter()
+        .map(|r| {
+            let status = match (&r.result, executed) {
+                (Ok(()), true) => "passed",
+                (Ok(()), false) => "generated",
+                (Err(e), _) if timed_out(e) => "TIMED OUT",
+                (Err(_), _) => "FAILED",
+            };
+            [
+                r.template.name.clone(),
+                format!("{:?}", r.variant),
+                status.to_string(),
+                r.out_dir.display().to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let header = ["template", "variant", "result", "output"].map(String::from);
+    let mut widths = [0; 3];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let templates = results
+        .iter()
+        .map(|r| r.template.name.as_str())
+        .collect::<HashSet<_>>()
+        .len();
+    let failed = results.iter().filter(|r| r.result.is_err()).count();
+    let timed_out = results
+        .iter()
+        .filter(|r| r.result.as_ref().is_err_and(timed_out))
+        .count();
+    let mut out = format!(
+        "\nSummary: {} variant(s) of {templates} template(s), {failed} failed",
+        results.len()
+    );
+    if timed_out > 0 {
+        out += &format!(" ({timed_out} timed out)");
+    }
+    out += "\n";
+    for [template, variant, status, out_dir] in std::iter::once(&header).chain(&rows) {
+        out += &format!(
+            "{template:<0$}  {variant:<1$}  {status:<2$}  {out_dir}\n",
+            widths[0], widths[1], widths[2]
+        );
+    }
+    out
+}
+
+/// The template containing `path`, unless it's within a `target` directory of the template or one of the `ignored`
+/// directories, like the directory we generate into
+fn changed_template<'a, 'b>(
+    template_dirs: &'a [(&'b Template, PathBuf)],
+    ignored: &[PathBuf],
+    path: &Path,
+) -> Option<&'a (&'b Template, PathBuf)> {
+    if ignored.iter().any(|dir| path.starts_with(dir)) {
+        return None;
+    }
+    template_dirs.iter().find(|(_, template_dir)| {
+        path.strip_prefix(template_dir).is_ok_and(|relative| {
+            !relative
+                .components()
+                .any(|c| c == Component::Normal("target".as_ref()))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RENDER: Define = Define {
+        key: "kind",
+        value: "render",
+    };
+    const COMPUTE: Define = Define {
+        key: "kind",
+        value: "compute",
+    };
+    const CARGO_GPU: Define = Define {
+        key: "integration",
+        value: "cargo-gpu",
+    };
+    const SPIRV_BUILDER: Define = Define {
+        key: "integration",
+        value: "spirv-builder",
+    };
+    const ASH: Define = Define {
+        key: "api",
+        value: "ash",
+    };
+    const WGPU: Define = Define {
+        key: "api",
+        value: "wgpu",
+    };
+    const CPU: Define = Define {
+        key: "api",
+        value: "cpu",
+    };
+
+    pub fn test_template() -> Template {
+        Template {
+            name: "my-template".to_string(),
+            template_dir: PathBuf::from("./my_template/"),
+            placeholders: IndexMap::from(
+                [
+                    ("kind", ["render", "compute"].as_slice()),
+                    ("integration", ["cargo-gpu", "spirv-builder"].as_slice()),
+                    ("api", ["ash", "wgpu", "cpu"].as_slice()),
+                ]
+                .map(|(k, v)| {
+                    (
+                        k.to_string(),
+                        v.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+                    )
+                }),
+            ),
+            conditional_placeholders: IndexMap::new(),
+            aliases: IndexMap::from([
+                (
+                    "integration".to_string(),
+                    IndexMap::from([("gpu".to_string(), "cargo-gpu".to_string())]),
+                ),
+                (
+                    "api".to_string(),
+                    IndexMap::from([("vulkan".to_string(), "ash".to_string())]),
+                ),
+            ]),
+            conditionals: Vec::new(),
+            extra_files: Vec::new(),
+        }
+    }
+
+    #[test]
+    pub fn variants_conditional_test() {
+        let template_dir = std::env::temp_dir().join("xtask-variants-conditional-test");
+        std::fs::create_dir_all(&template_dir).unwrap();
+        std::fs::write(
+            template_dir.join(CONFIG_FILE_NAME),
+            r#"
+[placeholders]
+integration = { type = "string", prompt = "integration?", choices = ["cargo-gpu", "spirv-builder"] }
+api = { type = "string", prompt = "api?", choices = ["ash", "wgpu", "cpu"] }
+
+[conditional.'api == "cpu"'.placeholders]
+integration = { type = "string", prompt = "integration?", choices = ["cargo-gpu"] }
+# only exists for ash, can't restrict anything
+[conditional.'api == "ash"'.placeholders]
+validation = { type = "bool", prompt = "validation?" }
+"#,
+        )
+        .unwrap();
+        let template = Template::parse("my-template".to_string(), template_dir.clone()).unwrap();
+        std::fs::remove_dir_all(&template_dir).unwrap();
+
+        let all = template.variants(std::iter::empty(), &[]).unwrap();
+        let expected = [
+            [CARGO_GPU, ASH],
+            [SPIRV_BUILDER, ASH],
+            [CARGO_GPU, WGPU],
+            [SPIRV_BUILDER, WGPU],
+            [CARGO_GPU, CPU],
+        ];
+        assert_eq!(all, expected);
+
+        // filtering for the disallowed combination leaves nothing
+        let none = template
+            .variants(["spirv-builder", "cpu"].into_iter(), &[])
+            .unwrap();
+        assert!(none.is_empty(), "{none:?}");
+    }
+
+    #[test]
+    pub fn variants_conditional_placeholder() {
+        let template_dir = std::env::temp_dir().join("xtask-variants-conditional-placeholder-test");
+        std::fs::create_dir_all(&template_dir).unwrap();
+        std::fs::write(
+            template_dir.join(CONFIG_FILE_NAME),
+            r#"
+[placeholders]
+integration = { type = "string", prompt = "integration?", choices = ["cargo-gpu", "spirv-builder"] }
+api = { type = "string", prompt = "api?", choices = ["ash", "wgpu"] }
+
+[conditional.'api == "ash" and integration != "spirv-builder"'.placeholders]
+version = { type = "string", prompt = "version?", choices = ["1.3", "1.2"] }
+"#,
+        )
+        .unwrap();
+        let template = Template::parse("my-template".to_string(), template_dir.clone()).unwrap();
+        std::fs::remove_dir_all(&template_dir).unwrap();
+
+        const V1_3: Define = Define {
+            key: "version",
+            value: "1.3",
+        };
+        const V1_2: Define = Define {
+            key: "version",
+            value: "1.2",
+        };
+        let all = template.variants(std::iter::empty(), &[]).unwrap();
+        let expected = [
+            [CARGO_GPU, ASH, V1_3].as_slice(),
+            &[SPIRV_BUILDER, ASH],
+            &[CARGO_GPU, WGPU],
+            &[SPIRV_BUILDER, WGPU],
+            &[CARGO_GPU, ASH, V1_2],
+        ];
+        assert_eq!(all, expected);
+
+        // filtering for a value drops the variants without the placeholder
+        let filtered = template.variants(["1.2"].into_iter(), &[]).unwrap();
+        assert_eq!(filtered, [[CARGO_GPU, ASH, V1_2]]);
+        let defined = template.variants(std::iter::empty(), &[V1_3]).unwrap();
+        assert_eq!(defined, [[CARGO_GPU, ASH, V1_3]]);
+
+        let list = TemplateDiscovery {
+            templates: vec![template],
+            post_generate: Vec::new(),
+        }
+        .list();
+        assert!(
+            list.contains(
+                r#"└── version: 1.3 | 1.2 (if api == "ash" and integration != "spirv-builder")"#
+            ),
+            "{list}"
+        );
+    }
+
+    #[test]
+    pub fn discover_at_custom_dir() {
+        let base_dir = std::env::temp_dir().join("xtask-discover-at-custom-dir-test");
+        let template_dir = base_dir.join("my-template");
+        std::fs::create_dir_all(&template_dir).unwrap();
+        std::fs::write(
+            template_dir.join(CONFIG_FILE_NAME),
+            r#"
+[placeholders]
+api = { type = "string", prompt = "api?", choices = ["ash", "wgpu"] }
+"#,
+        )
+        .unwrap();
+        // only the template, not yet the root config listing it
+        let missing_root = TemplateDiscovery::discover_at(&base_dir);
+        std::fs::write(
+            base_dir.join(CONFIG_FILE_NAME),
+            r#"
+[template]
+sub_templates = ["my-template"]
+post_generate = ["cargo fmt"]
+"#,
+        )
+        .unwrap();
+        let discovery = TemplateDiscovery::discover_at(&base_dir);
+        std::fs::remove_dir_all(&base_dir).unwrap();
+
+        let err = missing_root.unwrap_err().to_string();
+        assert!(err.contains("is not a templates directory"), "{err}");
+        let discovery = discovery.unwrap();
+        assert_eq!(discovery.templates.len(), 1);
+        assert_eq!(discovery.templates[0].name, "my-template");
+        assert_eq!(discovery.post_generate, ["cargo fmt"]);
+        let variants = discovery.filter_variants(std::iter::empty(), &[]).unwrap();
+        assert_eq!(variants.len(), 2, "{variants:?}");
+    }
+
+    #[test]
+    pub fn list_placeholders() {
+        let discovery = TemplateDiscovery {
+            templates: vec![test_template()],
+            post_generate: Vec::new(),
+        };
+        let list = discovery.list();
+        assert!(list.starts_with("my-template\n"), "{list}");
+        for p in test_template().placeholders.keys() {
+            assert!(list.contains(&format!(" {p}: ")), "{list}");
+        }
+        assert!(
+            list.contains("└── api: ash (alias vulkan) | wgpu | cpu\n"),
+            "{list}"
+        );
+
+        let discovery = TemplateDiscovery::discover().unwrap();
+        let list = discovery.list();
+        for template in &discovery.templates {
+            assert!(list.contains(&template.name), "{list}");
+            for p in template.placeholders.keys() {
+                assert!(list.contains(&format!(" {p}: ")), "{list}");
+            }
+        }
+    }
+
+    #[test]
+    pub fn check_packages_collisions() {
+        let out_base_dir = std::env::temp_dir().join("xtask-check-packages-test");
+        let write = |path: &str, content: &str| {
+            let path = out_base_dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        let package = "[package]\nname = \"mygraphics\"\n";
+        let workspace = "[workspace]\nmembers = [\"mygraphics\"]\n";
+        for variant in ["isolated/ash", "isolated/wgpu"] {
+            write(&format!("{variant}/Cargo.toml"), workspace);
+            write(&format!("{variant}/mygraphics/Cargo.toml"), package);
+        }
+        for variant in ["shared/ash", "shared/wgpu"] {
+            write(&format!("{variant}/mygraphics/Cargo.toml"), package);
+        }
+        std::fs::create_dir_all(out_base_dir.join("empty")).unwrap();
+        let check = |variants: &[&str]| {
+            let out_dirs = variants
+                .iter()
+                .map(|v| out_base_dir.join(v))
+                .collect::<Vec<_>>();
+            check_packages(&out_base_dir, out_dirs.iter().map(PathBuf::as_path))
+        };
+        let isolated = check(&["isolated/ash", "isolated/wgpu"]);
+        let shared = check(&["shared/ash", "shared/wgpu"]);
+        let single = check(&["shared/ash"]);
+        let empty = check(&["empty"]);
+        std::fs::remove_dir_all(&out_base_dir).unwrap();
+
+        isolated.unwrap();
+        let err = shared.unwrap_err().to_string();
+        assert!(err.contains("both declare package `mygraphics`"), "{err}");
+        single.unwrap();
+        let err = empty.unwrap_err().to_string();
+        assert!(err.starts_with("No `Cargo.toml` was generated"), "{err}");
+    }
+
+    #[test]
+    pub fn check_packages_repo() {
+        let out_base_dir = Path::new(TemplateDiscovery::TEMPLATE_PATH).join("generated");
+        let discovery = TemplateDiscovery::discover().unwrap();
+        let out_dirs = discovery
+            .filter_variants(std::iter::empty(), &[])
+            .unwrap()
+            .into_iter()
+            .map(|(template, variant)| Generate::out_dir(&out_base_dir, template, &variant))
+            .collect::<Vec<_>>();
+        check_packages(&out_base_dir, out_dirs.iter().map(PathBuf::as_path)).unwrap();
+    }
+
+    #[test]
+    pub fn variants_all() {
+        let template = test_template();
+        let all = template.variants(std::iter::empty(), &[]).unwrap();
+        debug!("all: {all:?}");
+
+        // order *in the outer slice* is arbitrary
+        // order within the inner slice is not, and will affect `generated/` folder structure
+        let expected = [
+            [RENDER, CARGO_GPU, ASH],
+            [COMPUTE, CARGO_GPU, ASH],
+            [RENDER, SPIRV_BUILDER, ASH],
+            [COMPUTE, SPIRV_BUILDER, ASH],
+            [RENDER, CARGO_GPU, WGPU],
+            [COMPUTE, CARGO_GPU, WGPU],
+            [RENDER, SPIRV_BUILDER, WGPU],
+            [COMPUTE, SPIRV_BUILDER, WGPU],
+            [RENDER, CARGO_GPU, CPU],
+            [COMPUTE, CARGO_GPU, CPU],
+            [RENDER, SPIRV_BUILDER, CPU],
+            [COMPUTE, SPIRV_BUILDER, CPU],
+        ];
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    pub fn representatives() {
+        let discovery = TemplateDiscovery {
+            templates: vec![test_template()],
+            post_generate: Vec::new(),
+        };
+        let variants = |placeholder, defines: &[Define<'static>]| {
+            discovery
+                .representatives(placeholder, defines)
+                .unwrap()
+                .into_iter()
+                .map(|(_, variant)| variant)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            variants("api", &[]),
+            [
+                [RENDER, CARGO_GPU, ASH],
+                [RENDER, CARGO_GPU, WGPU],
+                [RENDER, CARGO_GPU, CPU]
+            ]
+        );
+        assert_eq!(
+            variants("kind", &[SPIRV_BUILDER]),
+            [[RENDER, SPIRV_BUILDER, ASH], [COMPUTE, SPIRV_BUILDER, ASH]]
+        );
+        // a placeholder the template doesn't have leaves only its first variant
+        assert_eq!(variants("unknown", &[]), [[RENDER, CARGO_GPU, ASH]]);
+    }
+
+    #[test]
+    pub fn variants_cross_product_test() {
+        variants_cross_product(&test_template());
+    }
+
+    #[test]
+    pub fn variants_cross_product_repo() {
+        let discovery = TemplateDiscovery::discover().unwrap();
         for template in &discovery.templates {
             debug!("Template {}:", template.name);
             variants_cross_product(template);
@@ -436,7 +1967,7 @@ mod tests {
     }
 
     pub fn variants_cross_product(template: &Template) {
-        let variants = template.variants(std::iter::empty()).unwrap();
+        let variants = template.variants(std::iter::empty(), &[]).unwrap();
         for variant in variants {
             for value in variant.iter() {
                 let value_count = variant.iter().filter(|o| Define::eq(o, value)).count();
@@ -463,40 +1994,379 @@ mod tests {
     }
 
     pub fn variants_filter(template: &Template) {
-        let v_all = template.variants(std::iter::empty()).unwrap();
+        let v_all = template.variants(std::iter::empty(), &[]).unwrap();
         for (p, values) in &template.placeholders {
             debug!("{p}: {values:?}");
-            assert_eq!(v_all.len() % values.len(), 0);
-            let each_type_count = v_all.len() / values.len();
-
+            // conditional placeholders only split the variants matching their condition
+            if !template.conditional_placeholders.contains_key(p) {
+                assert!(v_all.iter().all(|v| v.iter().any(|d| d.key == p)));
+            }
             for i in 1..values.len() {
-                let v_one = template
-                    .variants(values[..i].iter().map(|s| s.as_str()))
+                let v_some = template
+                    .variants(values[..i].iter().map(|s| s.as_str()), &[])
                     .unwrap();
-                assert_eq!(v_one.len(), each_type_count * i);
+                let expected = v_all
+                    .iter()
+                    .filter(|v| {
+                        v.iter()
+                            .any(|d| d.key == p && values[..i].contains(&d.value.to_string()))
+                    })
+                    .count();
+                assert_eq!(v_some.len(), expected);
             }
         }
     }
 
+    #[test]
+    pub fn out_dir_test() {
+        let out_dir = Generate::out_dir(
+            Path::new("generated"),
+            &test_template(),
+            &[COMPUTE, SPIRV_BUILDER, WGPU],
+        );
+        assert_eq!(
+            out_dir,
+            Path::new("generated/my-template/compute/spirv-builder/wgpu")
+        );
+    }
+
+    #[test]
+    pub fn generate_error_context() {
+        let out_base_dir = std::env::temp_dir().join("xtask-generate-error-context");
+        // the template dir of the test template does not exist
+        let error = Generate::default()
+            .generate(&out_base_dir, &test_template(), &[RENDER, CARGO_GPU, ASH])
+            .unwrap_err();
+        std::fs::remove_dir_all(&out_base_dir).unwrap();
+        let message = error.to_string();
+        assert!(message.contains("`my-template`"), "{message}");
+        assert!(
+            message.contains("`[kind=render, integration=cargo-gpu, api=ash]`"),
+            "{message}"
+        );
+        assert!(message.contains("render/cargo-gpu/ash"), "{message}");
+    }
+
+    #[test]
+    pub fn generate_does_not_leak_env() {
+        let out_base_dir = std::env::temp_dir().join("xtask-generate-does-not-leak-env");
+        let discovery = TemplateDiscovery::discover().unwrap();
+        let (template, variant) = &discovery.filter_variants(std::iter::empty(), &[]).unwrap()[0];
+        let env_before = CargoGenerateGuard::ENV.map(|(key, _)| std::env::var_os(key));
+
+        // back-to-back, the second generation must not see any state left behind by the first
+        for _ in 0..2 {
+            let out_dir = Generate::default()
+                .generate(&out_base_dir, template, variant)
+                .unwrap();
+            let manifest = std::fs::read_to_string(out_dir.join("Cargo.toml")).unwrap();
+            assert!(
+                manifest.contains(r#"authors = ["generated <generated>"]"#),
+                "{manifest}"
+            );
+            let env_after = CargoGenerateGuard::ENV.map(|(key, _)| std::env::var_os(key));
+            assert_eq!(env_before, env_after);
+        }
+        std::fs::remove_dir_all(&out_base_dir).unwrap();
+    }
+
+    #[test]
+    pub fn generate_extra_files() {
+        let base_dir = std::env::temp_dir().join("xtask-generate-extra-files-test");
+        let template_dir = base_dir.join("template");
+        std::fs::create_dir_all(template_dir.join("extra")).unwrap();
+        std::fs::write(
+            template_dir.join(CONFIG_FILE_NAME),
+            r#"
+[template]
+ignore = ["extra"]
+
+[placeholders]
+api = { type = "string", prompt = "api?", choices = ["ash", "wgpu"] }
+
+[conditional.'api == "ash"']
+extra_files = { "docs/README-ash.md" = "extra/README-ash.md" }
+"#,
+        )
+        .unwrap();
+        std::fs::write(template_dir.join("hello.txt"), "hello\n").unwrap();
+        std::fs::write(template_dir.join("extra/README-ash.md"), "ash only\n").unwrap();
+        let template = Template::parse("my-template".to_string(), template_dir).unwrap();
+
+        let out_base_dir = base_dir.join("generated");
+        let generate = |variant| {
+            Generate::default()
+                .generate(&out_base_dir, &template, &[variant])
+                .unwrap()
+        };
+        let (ash, wgpu) = (generate(ASH), generate(WGPU));
+        let ash_extra = std::fs::read_to_string(ash.join("docs/README-ash.md"));
+        let wgpu_has_extra = wgpu.join("docs/README-ash.md").exists();
+        let has_source = [&ash, &wgpu].map(|out_dir| out_dir.join("extra").exists());
+        std::fs::remove_dir_all(&base_dir).unwrap();
+
+        assert_eq!(ash_extra.unwrap(), "ash only\n");
+        assert!(!wgpu_has_extra);
+        // the source is only copied where it's requested, not templated into every variant
+        assert_eq!(has_source, [false, false]);
+    }
+
+    #[test]
+    pub fn generate_workspace() {
+        let base_dir = std::env::temp_dir().join("xtask-generate-workspace-test");
+        let template_dir = base_dir.join("template");
+        let write = |path: &str, content: &str| {
+            let path = template_dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write(
+            CONFIG_FILE_NAME,
+            r#"
+[placeholders]
+api = { type = "string", prompt = "api?", choices = ["ash", "wgpu"] }
+"#,
+        );
+        write(
+            "Cargo.toml",
+            r#"[workspace]
+members = ["app", "shaders"]
+resolver = "3"
+
+[workspace.dependencies]
+glam = "0.32.0"
+
+[profile.dev]
+opt-level = 1
+"#,
+        );
+        write(
+            "app/Cargo.toml",
+            r#"[package]
+name = "app"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+# the shader crate
+shaders = { path = "../shaders" }
+"#,
+        );
+        write("app/src/main.rs", "fn main() {}\n");
+        write(
+            "shaders/Cargo.toml",
+            r#"[package]
+name = "shaders"
+version = "0.1.0"
+edition = "2024"
+
+[dependencies]
+glam.workspace = true
+"#,
+        );
+        write("shaders/src/lib.rs", "");
+        let template = Template::parse("my-template".to_string(), template_dir.clone()).unwrap();
+        let variants = template
+            .variants(std::iter::empty(), &[])
+            .unwrap()
+            .into_iter()
+            .map(|variant| (&template, variant))
+            .collect::<Vec<_>>();
+
+        let out_base_dir = base_dir.join("generated");
+        let generate = Generate {
+            workspace: true,
+            ..Generate::default()
+        };
+        let result = generate.generate_all(&out_base_dir, &[], &variants);
+        let read = |path: &str| std::fs::read_to_string(out_base_dir.join(path)).unwrap();
+        let root = read("Cargo.toml");
+        let app = read("my-template/wgpu/app/Cargo.toml");
+        let shaders = read("my-template/wgpu/shaders/Cargo.toml");
+        let variant_manifest = out_base_dir.join("my-template/wgpu/Cargo.toml").exists();
+        let metadata = std::process::Command::new("cargo")
+            .args([
+                "metadata",
+                "--offline",
+                "--no-deps",
+                "--format-version",
+                "1",
+            ])
+            .current_dir(&out_base_dir)
+            .output()
+            .unwrap();
+        std::fs::remove_dir_all(&base_dir).unwrap();
+
+        result.unwrap();
+        let root: toml::Table = toml::from_str(&root).unwrap();
+        assert_eq!(
+            root["workspace"]["members"].as_array().unwrap(),
+            &[
+                "my-template/ash/app",
+                "my-template/ash/shaders",
+                "my-template/wgpu/app",
+                "my-template/wgpu/shaders",
+            ]
+            .map(toml::Value::from)
+        );
+        assert_eq!(
+            root["workspace"]["dependencies"]["glam"].as_str(),
+            Some("0.32.0")
+        );
+        assert_eq!(root["profile"]["dev"]["opt-level"].as_integer(), Some(1));
+        assert!(!variant_manifest);
+        assert!(app.contains("name = \"app-wgpu\""), "{app}");
+        assert!(app.contains("# the shader crate"), "{app}");
+        assert!(
+            app.contains(r#"shaders = { path = "../shaders", package = "shaders-wgpu" }"#),
+            "{app}"
+        );
+        assert!(shaders.contains("[lib]\nname = \"shaders\""), "{shaders}");
+        assert!(
+            metadata.status.success(),
+            "{}",
+            String::from_utf8_lossy(&metadata.stderr)
+        );
+    }
+
+    #[test]
+    pub fn merge_table_conflict() {
+        let table = |toml: &str| {
+            let document = toml.parse::<toml_edit::DocumentMut>().unwrap();
+            document.as_table().clone()
+        };
+        let mut dst = table("[dependencies]\nglam = { version = \"0.32.0\" }\n");
+        let same = table("[dependencies]\nglam = {version=\"0.32.0\"} # same\nash = \"0.38\"\n");
+        merge_table(&mut dst, &same, "workspace").unwrap();
+        let other = table("[dependencies]\nglam = \"0.31.0\"\n");
+        let err = merge_table(&mut dst, &other, "workspace").unwrap_err();
+
+        assert!(dst["dependencies"].get("ash").is_some());
+        let err = err.to_string();
+        assert!(err.contains("`workspace.dependencies.glam`"), "{err}");
+    }
+
+    #[test]
+    pub fn summary_table() {
+        let template = test_template();
+        let ok = [RENDER, CARGO_GPU, ASH];
+        let failed = [COMPUTE, SPIRV_BUILDER, CPU];
+        let results = [
+            VariantResult {
+                template: &template,
+                variant: &ok,
+                out_dir: PathBuf::from("/out/ok"),
+                result: Ok(()),
+            },
+            VariantResult {
+                template: &template,
+                variant: &failed,
+                out_dir: PathBuf::from("/out/failed"),
+                result: Err(anyhow::anyhow!("failed")),
+            },
+        ];
+        let expected = "
+Summary: 2 variant(s) of 1 template(s), 1 failed
+template     variant                                             result  output
+my-template  [kind=render, integration=cargo-gpu, api=ash]       passed  /out/ok
+my-template  [kind=compute, integration=spirv-builder, api=cpu]  FAILED  /out/failed
+";
+        assert_eq!(summary(&results, true), expected);
+        assert!(summary(&results, false).contains("generated  /out/ok"));
+
+        let timed_out = [VariantResult {
+            template: &template,
+            variant: &failed,
+            out_dir: PathBuf::from("/out/timed-out"),
+            result: Err(ExecuteTimeout {
+                execute: "cargo build".to_string(),
+                out_dir: PathBuf::from("/out/timed-out"),
+                timeout: Duration::from_secs(1),
+            }
+            .into()),
+        }];
+        let summary = summary(&timed_out, true);
+        assert!(summary.contains("1 failed (1 timed out)"), "{summary}");
+        assert!(summary.contains("TIMED OUT  /out/timed-out"), "{summary}");
+    }
+
+    #[test]
+    pub fn wait_child_timeout() {
+        let spawn = |secs: &str| {
+            std::process::Command::new("sleep")
+                .arg(secs)
+                .stdout(Stdio::piped())
+                .spawn()
+                .unwrap()
+        };
+        let start = Instant::now();
+        let (status, _) = wait_child(spawn("10"), Some(Duration::from_millis(100))).unwrap();
+        assert_eq!(status, None);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let (status, _) = wait_child(spawn("0"), Some(Duration::from_secs(5))).unwrap();
+        assert!(status.unwrap().success());
+        let (status, _) = wait_child(spawn("0"), None).unwrap();
+        assert!(status.unwrap().success());
+
+        let output = std::process::Command::new("echo")
+            .arg("hello")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let (_, [stdout, stderr]) = wait_child(output, None).unwrap();
+        assert_eq!(stdout, b"hello\n");
+        assert!(stderr.is_empty());
+    }
+
+    #[test]
+    pub fn watch_changed_template() {
+        let template = test_template();
+        let mut other = test_template();
+        other.name = "other-template".to_string();
+        let template_dirs = [
+            (&template, PathBuf::from("/repo/my_template")),
+            (&other, PathBuf::from("/repo/other_template")),
+        ];
+        let ignored = [PathBuf::from("/repo/my_template/generated")];
+        let changed = |path: &str| {
+            changed_template(&template_dirs, &ignored, Path::new(path))
+                .map(|(template, _)| template.name.as_str())
+        };
+
+        assert_eq!(changed("/repo/my_template/src/lib.rs"), Some("my-template"));
+        assert_eq!(
+            changed("/repo/other_template/Cargo.toml"),
+            Some("other-template")
+        );
+        // only whole components match
+        assert_eq!(changed("/repo/my_template_old/src/lib.rs"), None);
+        assert_eq!(changed("/repo/README.md"), None);
+        assert_eq!(changed("/repo/my_template/target/debug/build"), None);
+        assert_eq!(changed("/repo/my_template/generated/src/lib.rs"), None);
+    }
+
     #[test]
     pub fn template_filter_all_test() {
         let discovery = TemplateDiscovery {
             templates: Vec::from([test_template()]),
+            post_generate: Vec::new(),
         };
         let filter = discovery.split_filter(std::iter::empty());
         assert_eq!(filter, Filters::default());
-        let result = discovery.filter_variants(std::iter::empty()).unwrap();
+        let result = discovery.filter_variants(std::iter::empty(), &[]).unwrap();
         let templates = result
             .iter()
             .map(|(t, _)| t.name.as_str())
             .collect::<Vec<_>>();
-        assert_eq!(templates, ["my-template"; 6])
+        assert_eq!(templates, ["my-template"; 12])
     }
 
     #[test]
     pub fn template_filter_one_test() {
         let discovery = TemplateDiscovery {
             templates: Vec::from([test_template()]),
+            post_generate: Vec::new(),
         };
         let filter_args = ["my-template"];
         let filter = discovery.split_filter(filter_args.iter().copied());
@@ -508,19 +2378,20 @@ mod tests {
             }
         );
         let result = discovery
-            .filter_variants(filter_args.iter().copied())
+            .filter_variants(filter_args.iter().copied(), &[])
             .unwrap();
         let templates = result
             .iter()
             .map(|(t, _)| t.name.as_str())
             .collect::<Vec<_>>();
-        assert_eq!(templates, ["my-template"; 6])
+        assert_eq!(templates, ["my-template"; 12])
     }
 
     #[test]
     pub fn template_filter_none_test() {
         let discovery = TemplateDiscovery {
             templates: Vec::from([test_template()]),
+            post_generate: Vec::new(),
         };
         let filter_args = ["unknown"];
         let filter = discovery.split_filter(filter_args.iter().copied());
@@ -531,8 +2402,186 @@ mod tests {
                 placeholder_filters: Vec::from(["unknown"]),
             }
         );
-        let result = discovery.filter_variants(filter_args.iter().copied());
+        let result = discovery.filter_variants(filter_args.iter().copied(), &[]);
         // Unknown filter
         assert!(result.is_err(), "Result: {result:#?}");
     }
+
+    #[test]
+    pub fn filter_aliases() {
+        let discovery = TemplateDiscovery {
+            templates: Vec::from([test_template()]),
+            post_generate: Vec::new(),
+        };
+        let result = discovery
+            .filter_variants(["vulkan", "gpu", "render"].into_iter(), &[])
+            .unwrap();
+        let variants = result.into_iter().map(|(_, v)| v).collect::<Vec<_>>();
+        assert_eq!(variants, [[RENDER, CARGO_GPU, ASH]]);
+
+        let error = discovery
+            .filter_variants(["wgup"].into_iter(), &[])
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("Unknown filter `wgup`, did you mean `wgpu`?\n"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    pub fn filter_defines() {
+        let mut template = test_template();
+        let discovery = TemplateDiscovery {
+            templates: Vec::from([template.clone()]),
+            post_generate: Vec::new(),
+        };
+        // the define overrides the `ash` filter for the same placeholder, while `kind` is still cross-producted
+        let result = discovery
+            .filter_variants(["ash", "cargo-gpu"].into_iter(), &[WGPU])
+            .unwrap();
+        let variants = result.into_iter().map(|(_, v)| v).collect::<Vec<_>>();
+        assert_eq!(
+            variants,
+            [[RENDER, CARGO_GPU, WGPU], [COMPUTE, CARGO_GPU, WGPU]]
+        );
+
+        let unknown = Define {
+            key: "backend",
+            value: "metal",
+        };
+        let error = discovery
+            .filter_variants(std::iter::empty(), &[unknown])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Unknown placeholder `backend` in `--define backend=metal`"
+        );
+
+        // a placeholder without `choices` must be defined
+        template
+            .placeholders
+            .insert("crate_name".to_string(), Vec::new());
+        let discovery = TemplateDiscovery {
+            templates: Vec::from([template]),
+            post_generate: Vec::new(),
+        };
+        let error = discovery
+            .filter_variants(std::iter::empty(), &[])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("`--define crate_name=...`"), "{error}");
+        let crate_name = Define {
+            key: "crate_name",
+            value: "my-crate",
+        };
+        let result = discovery
+            .filter_variants(
+                ["compute", "spirv-builder", "cpu"].into_iter(),
+                &[crate_name],
+            )
+            .unwrap();
+        let variants = result.into_iter().map(|(_, v)| v).collect::<Vec<_>>();
+        assert_eq!(variants, [[COMPUTE, SPIRV_BUILDER, CPU, crate_name]]);
+    }
+
+    #[test]
+    pub fn execute_post_generate() {
+        let out_dir = std::env::temp_dir().join("xtask-execute-post-generate-test");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        let generate = Generate {
+            execute: Some("touch executed".to_string()),
+            ..Generate::default()
+        };
+        let post_generate = ["touch first", "touch second"].map(String::from);
+        let passed = generate.execute(&post_generate, &out_dir);
+        let all_ran = ["first", "second", "executed"].map(|file| out_dir.join(file).exists());
+        std::fs::remove_dir_all(&out_dir).unwrap();
+        std::fs::create_dir_all(&out_dir).unwrap();
+        // stops at the first failing command
+        let post_generate = ["false", "touch second"].map(String::from);
+        let failed = generate.execute(&post_generate, &out_dir);
+        let none_ran = ["second", "executed"].map(|file| out_dir.join(file).exists());
+        std::fs::remove_dir_all(&out_dir).unwrap();
+
+        passed.unwrap();
+        assert_eq!(all_ran, [true; 3]);
+        let error = failed.unwrap_err().to_string();
+        assert!(error.starts_with("`false` in "), "{error}");
+        assert_eq!(none_ran, [false; 2]);
+    }
+
+    #[test]
+    pub fn execute_keep_going() {
+        let out_dir = std::env::temp_dir().join("xtask-execute-keep-going-test");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        let generate = Generate {
+            execute: Some("false".to_string()),
+            keep_going: true,
+            ..Generate::default()
+        };
+        let post_generate = ["false", "touch second"].map(String::from);
+        let failed = generate.execute(&post_generate, &out_dir);
+        let second_ran = out_dir.join("second").exists();
+        std::fs::remove_dir_all(&out_dir).unwrap();
+
+        assert!(second_ran);
+        let error = failed.unwrap_err();
+        assert!(!timed_out(&error));
+        let errors = error.downcast::<ExecuteErrors>().unwrap();
+        assert_eq!(errors.0.len(), 2);
+        assert!(
+            errors
+                .to_string()
+                .starts_with("2 commands failed:\n  `false` in ")
+        );
+    }
+
+    #[test]
+    pub fn parse_define_test() {
+        assert_eq!(
+            parse_define("api=wgpu"),
+            Ok(("api".to_string(), "wgpu".to_string()))
+        );
+        assert_eq!(
+            parse_define("name=a=b"),
+            Ok(("name".to_string(), "a=b".to_string()))
+        );
+        assert!(parse_define("wgpu").is_err());
+        assert!(parse_define("=wgpu").is_err());
+    }
+
+    #[test]
+    pub fn unknown_filter_suggestion() {
+        let discovery = TemplateDiscovery {
+            templates: Vec::from([test_template()]),
+            post_generate: Vec::new(),
+        };
+        let error = discovery
+            .filter_variants(["wpgu"].into_iter(), &[])
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Unknown filter `wpgu`, did you mean `wgpu`?\nValid filters are: my-template, render, compute, cargo-gpu, \
+             spirv-builder, ash, wgpu, cpu, gpu, vulkan"
+        );
+        let error = discovery
+            .filter_variants(["metal"].into_iter(), &[])
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Unknown filter `metal`\n"), "{error}");
+    }
+
+    #[test]
+    pub fn edit_distance_test() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("ash", ""), 3);
+        assert_eq!(edit_distance("ash", "ash"), 0);
+        assert_eq!(edit_distance("cargo-gpu", "cargo_gpu"), 1);
+        assert_eq!(edit_distance("wgup", "wgpu"), 1);
+        assert_eq!(edit_distance("wpgu", "cpu"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
 }
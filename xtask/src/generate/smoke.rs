@@ -0,0 +1,68 @@
+use crate::generate::{Define, Generate, TemplateDiscovery, parse_define};
+use clap::Parser;
+use log::info;
+use std::path::{Path, PathBuf};
+
+/// Generates one variant per value of a placeholder, by default one per `api`, into a temporary directory and runs
+/// `cargo check` on each. A quick sanity check of template changes, without the full cross-product of
+/// `generate -x "cargo check"`.
+#[derive(Parser, Debug)]
+pub struct Smoke {
+    /// The placeholder to check one variant of each value of.
+    #[clap(long, default_value = "api")]
+    placeholder: String,
+    /// Set a placeholder to a value, as `key=value`, instead of using its first choice in the checked variants.
+    ///
+    /// E.g. `--define integration=prebuilt` checks the variants without compiling any shaders.
+    #[clap(long, value_name = "KEY=VALUE", value_parser = parse_define)]
+    define: Vec<(String, String)>,
+    /// How many variants to check concurrently.
+    #[clap(long, default_value_t = 1)]
+    parallel: usize,
+    /// Directory containing the root `cargo-generate.toml` that lists the templates, defaults to this repository.
+    #[clap(long)]
+    templates_dir: Option<PathBuf>,
+    /// Keep the generated variants in the temporary directory, instead of deleting them once all checks passed. They
+    /// are always kept if any check failed, for inspecting them.
+    #[clap(long)]
+    keep: bool,
+}
+
+impl Smoke {
+    /// The directory the variants are generated into, emptied before each run
+    fn out_base_dir() -> PathBuf {
+        std::env::temp_dir().join("xtask-smoke")
+    }
+
+    pub fn run(&self) -> anyhow::Result<()> {
+        let templates_dir = self
+            .templates_dir
+            .as_deref()
+            .unwrap_or(Path::new(TemplateDiscovery::TEMPLATE_PATH));
+        let discovery = TemplateDiscovery::discover_at(templates_dir)?;
+        let defines = self
+            .define
+            .iter()
+            .map(|(key, value)| Define { key, value })
+            .collect::<Vec<_>>();
+        let variants = discovery.representatives(&self.placeholder, &defines)?;
+        for (template, variant) in &variants {
+            info!("Checking {} {variant:?}", template.name);
+        }
+
+        let out_base_dir = Self::out_base_dir();
+        if out_base_dir.exists() {
+            std::fs::remove_dir_all(&out_base_dir)?;
+        }
+        let generate = Generate {
+            execute: Some("cargo check".to_string()),
+            parallel: self.parallel,
+            ..Generate::default()
+        };
+        let result = generate.generate_all(&out_base_dir, &discovery.post_generate, &variants);
+        if result.is_ok() && !self.keep {
+            std::fs::remove_dir_all(&out_base_dir)?;
+        }
+        result
+    }
+}
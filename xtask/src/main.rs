@@ -1,14 +1,17 @@
 use crate::generate::Generate;
+use crate::generate::smoke::Smoke;
 use clap::Parser;
 use log::debug;
 
 pub mod cargo_generate_config;
+mod condition;
 mod generate;
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
 pub enum Command {
     Generate(Generate),
+    Smoke(Smoke),
 }
 
 pub fn main() -> anyhow::Result<()> {
@@ -19,5 +22,6 @@ pub fn main() -> anyhow::Result<()> {
     debug!("Command: {command:?}");
     match command {
         Command::Generate(generate) => generate.run(),
+        Command::Smoke(smoke) => smoke.run(),
     }
 }